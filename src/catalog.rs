@@ -0,0 +1,363 @@
+use crate::{
+    pipeline::{Pipeline, PipelineStep},
+    validation::suggest_column,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// Catalog shipped with the binary, from `catalog/services.json`.
+pub const DEFAULT_CATALOG: &str = include_str!("../catalog/services.json");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceKind {
+    Reconciler,
+    Extender,
+    Modifier,
+}
+
+impl ServiceKind {
+    pub const ALL: [ServiceKind; 3] = [
+        ServiceKind::Reconciler,
+        ServiceKind::Extender,
+        ServiceKind::Modifier,
+    ];
+
+    /// Kind of service an operation type calls, and the log field naming it.
+    pub fn for_op_type(op_type: &str) -> Option<(ServiceKind, &'static str)> {
+        match op_type {
+            "RECONCILIATION" => Some((ServiceKind::Reconciler, "Reconciler")),
+            "EXTENSION" => Some((ServiceKind::Extender, "Extender")),
+            "MODIFICATION" => Some((ServiceKind::Modifier, "Modifier")),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServiceKind::Reconciler => "reconciler",
+            ServiceKind::Extender => "extender",
+            ServiceKind::Modifier => "modifier",
+        }
+    }
+
+    fn heading(&self) -> &'static str {
+        match self {
+            ServiceKind::Reconciler => "Reconcilers",
+            ServiceKind::Extender => "Extenders",
+            ServiceKind::Modifier => "Modifiers",
+        }
+    }
+}
+
+/// A parameter of a service form, as listed by the backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormParam {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_type: Option<String>,
+    /// Validation rules such as `required`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Value>,
+}
+
+/// A reconciler, extender or modifier of the backend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Documentation page linked from the generated notebooks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deprecated: bool,
+    /// Service to use instead of a deprecated one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<String>,
+    /// The `properties` of its extensions are Wikidata property ids
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub wikidata_properties: bool,
+    #[serde(default)]
+    pub form_params: Vec<FormParam>,
+}
+
+/// The services the backend offers. Other fields of the backend's service list are ignored,
+/// so an exported list can be used as is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceCatalog {
+    #[serde(default)]
+    pub reconcilers: Vec<Service>,
+    #[serde(default)]
+    pub extenders: Vec<Service>,
+    #[serde(default)]
+    pub modifiers: Vec<Service>,
+}
+
+impl Default for ServiceCatalog {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ServiceCatalog {
+    pub fn builtin() -> Self {
+        serde_json::from_str(DEFAULT_CATALOG)
+            .unwrap_or_else(|e| panic!("invalid built-in service catalog: {}", e))
+    }
+
+    /// A catalog file replaces the built-in catalog entirely.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read catalog '{}': {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("invalid catalog '{}': {}", path.display(), e))
+    }
+
+    pub fn services(&self, kind: ServiceKind) -> &[Service] {
+        match kind {
+            ServiceKind::Reconciler => &self.reconcilers,
+            ServiceKind::Extender => &self.extenders,
+            ServiceKind::Modifier => &self.modifiers,
+        }
+    }
+
+    pub fn find(&self, kind: ServiceKind, id: &str) -> Option<&Service> {
+        self.services(kind).iter().find(|service| service.id == id)
+    }
+
+    /// Service called by a step, `None` when the step calls no service or it is unknown.
+    pub fn service_of(&self, step: &PipelineStep) -> Option<(ServiceKind, &Service)> {
+        let (kind, field) = ServiceKind::for_op_type(&step.op_type)?;
+        let id = step.operation.get(field)?;
+        self.find(kind, id).map(|service| (kind, service))
+    }
+
+    /// Problems with the services a step calls: unknown or deprecated ids, and properties
+    /// that are not Wikidata property ids for services that expect them.
+    pub fn check_step(&self, step: &PipelineStep) -> Vec<String> {
+        let (kind, field) = match ServiceKind::for_op_type(&step.op_type) {
+            Some(found) => found,
+            None => return Vec::new(),
+        };
+        let id = match step.operation.get(field) {
+            Some(id) => id,
+            None => return Vec::new(),
+        };
+        let service = match self.find(kind, id) {
+            Some(service) => service,
+            None => {
+                let ids: Vec<String> = self
+                    .services(kind)
+                    .iter()
+                    .map(|service| service.id.clone())
+                    .collect();
+                let suggestion = suggest_column(id, &ids)
+                    .map(|found| format!(" (did you mean '{}'?)", found))
+                    .unwrap_or_default();
+                return vec![format!(
+                    "unknown {} '{}', not in the service catalog{}",
+                    kind.name(),
+                    id,
+                    suggestion
+                )];
+            }
+        };
+
+        let mut issues = Vec::new();
+        if service.deprecated {
+            issues.push(match &service.replaced_by {
+                Some(replacement) => format!(
+                    "{} '{}' is deprecated, use '{}' instead",
+                    kind.name(),
+                    id,
+                    replacement
+                ),
+                None => format!("{} '{}' is deprecated", kind.name(), id),
+            });
+        }
+        if service.wikidata_properties {
+            let properties = step.params["properties"].as_array();
+            for property in properties.into_iter().flatten().filter_map(|p| p.as_str()) {
+                if !is_wikidata_property(property) {
+                    issues.push(format!(
+                        "'{}' is not a Wikidata property id (P followed by digits, e.g. P17)",
+                        property
+                    ));
+                }
+            }
+        }
+        issues
+    }
+
+    /// Markdown line linking the service a step calls to its catalog description.
+    pub fn markdown_description(&self, step: &PipelineStep) -> Option<String> {
+        let (_, service) = self.service_of(step)?;
+        let name = service.name.as_deref().unwrap_or(&service.id);
+        let mut line = match &service.url {
+            Some(url) => format!("[{}]({}) (`{}`)", name, url, service.id),
+            None => format!("**{}** (`{}`)", name, service.id),
+        };
+        if let Some(description) = &service.description {
+            line.push_str(": ");
+            line.push_str(description);
+        }
+        if service.deprecated {
+            line.push_str(" *Deprecated.*");
+        }
+        Some(line)
+    }
+
+    /// Sets the catalog description of every step of the pipeline.
+    pub fn describe_pipeline(&self, pipeline: &mut Pipeline) {
+        for step in &mut pipeline.steps {
+            step.service_description = self.markdown_description(step);
+        }
+    }
+}
+
+/// `P` followed by digits, e.g. `P17`.
+pub fn is_wikidata_property(id: &str) -> bool {
+    id.len() > 1 && id.starts_with('P') && id[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Text listing of the catalog, or of the services with the id `only`.
+pub fn format_catalog(catalog: &ServiceCatalog, only: Option<&str>) -> String {
+    let mut out = String::new();
+    for kind in ServiceKind::ALL {
+        let services: Vec<&Service> = catalog
+            .services(kind)
+            .iter()
+            .filter(|service| only.is_none_or(|id| service.id == id))
+            .collect();
+        if services.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{} ({})\n", kind.heading(), services.len()));
+        for service in services {
+            out.push_str(&format!("  {}", service.id));
+            if let Some(name) = &service.name {
+                out.push_str(&format!(" - {}", name));
+            }
+            if service.deprecated {
+                match &service.replaced_by {
+                    Some(replacement) => {
+                        out.push_str(&format!(" [deprecated, use {}]", replacement))
+                    }
+                    None => out.push_str(" [deprecated]"),
+                }
+            }
+            out.push('\n');
+            if let Some(description) = &service.description {
+                out.push_str(&format!("    {}\n", description));
+            }
+            if let Some(url) = &service.url {
+                out.push_str(&format!("    {}\n", url));
+            }
+            for param in &service.form_params {
+                let mut details: Vec<String> = param.input_type.iter().cloned().collect();
+                details.extend(
+                    param
+                        .rules
+                        .iter()
+                        .map(|rule| rule.as_str().map_or(rule.to_string(), str::to_string)),
+                );
+                out.push_str(&format!("    - {}", param.id));
+                if !details.is_empty() {
+                    out.push_str(&format!(" ({})", details.join(", ")));
+                }
+                if let Some(description) = param.description.as_ref().or(param.label.as_ref()) {
+                    out.push_str(&format!(": {}", description));
+                }
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generators::GeneratorRegistry, pipeline::build_pipeline};
+    use std::collections::HashMap;
+
+    fn operation(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn steps_are_checked_against_the_catalog() {
+        let mut catalog = ServiceCatalog::builtin();
+        catalog.modifiers[0].deprecated = true;
+        catalog.modifiers[0].replaced_by = Some("dateFormatterV2".to_string());
+        let operations = vec![
+            operation(&[
+                ("OpType", "RECONCILIATION"),
+                ("ColumnName", "City"),
+                ("Reconciler", "wikidataOpenRefin"),
+            ]),
+            operation(&[
+                ("OpType", "EXTENSION"),
+                ("ColumnName", "City"),
+                ("Extender", "wikidataPropertySPARQL"),
+                ("AdditionalData", r#"{"properties": "P17 population"}"#),
+            ]),
+            operation(&[
+                ("OpType", "MODIFICATION"),
+                ("ColumnName", "Date"),
+                ("Modifier", "dateFormatter"),
+                ("AdditionalData", r#"{"formatType": "iso"}"#),
+            ]),
+        ];
+        let registry = GeneratorRegistry::with_builtin_generators();
+        let mut pipeline = build_pipeline(
+            &operations,
+            "table.csv",
+            false,
+            &registry,
+            chrono::Utc::now(),
+        );
+        let issues: Vec<Vec<String>> = pipeline
+            .steps
+            .iter()
+            .map(|step| catalog.check_step(step))
+            .collect();
+        assert_eq!(
+            issues[0],
+            ["unknown reconciler 'wikidataOpenRefin', not in the service catalog (did you mean 'wikidataOpenRefine'?)"]
+        );
+        assert_eq!(
+            issues[1],
+            ["'population' is not a Wikidata property id (P followed by digits, e.g. P17)"]
+        );
+        assert_eq!(
+            issues[2],
+            ["modifier 'dateFormatter' is deprecated, use 'dateFormatterV2' instead"]
+        );
+
+        catalog.describe_pipeline(&mut pipeline);
+        assert_eq!(pipeline.steps[0].service_description, None);
+        assert!(pipeline.steps[1]
+            .service_description
+            .as_deref()
+            .is_some_and(|line| line.starts_with("[Wikidata SPARQL](https://")));
+        assert!(format_catalog(&catalog, Some("dateFormatter")).starts_with(
+            "Modifiers (1)\n  dateFormatter - Date formatter [deprecated, use dateFormatterV2]\n"
+        ));
+    }
+}
@@ -1,571 +1,369 @@
-use serde_json::Value;
-
-const BASE_PYTHON_FILE_CONTENT: &str = r#"
-import semt_py
-import getpass
-import argparse
-from semt_py import AuthManager
-from semt_py.extension_manager import ExtensionManager
-from semt_py.reconciliation_manager import ReconciliationManager
-from semt_py.utils import Utility
-from semt_py.dataset_manager import DatasetManager
-from semt_py.table_manager import TableManager
-from semt_py.modification_manager import ModificationManager
-
-def get_input_with_default(prompt, default):
-    user_input = input(f"{prompt} (default: {default}): ").strip()
-    return user_input if user_input else default
-
-parser = argparse.ArgumentParser(description="SemT Table Processor")
-parser.add_argument('--base-url', default=None, help='Base URL for the API')
-parser.add_argument('--username', default=None, help='Username for authentication')
-parser.add_argument('--password', default=None, help='Password for authentication')
-parser.add_argument('--dataset-id', default=None, help='Dataset ID')
-parser.add_argument('--table-name', default=None, help='Table name')
-parser.add_argument('--csv-file', default=None, help='Path to CSV file')
-args = parser.parse_args()
-
-if args.base_url:
-    base_url = args.base_url
-else:
-    base_url = get_input_with_default("Enter base URL or press Enter to keep default", "__BASE_URL__")
-api_url = base_url + "/api"
-if args.username:
-    username = args.username
-else:
-    username = get_input_with_default("Enter your username", "__USERNAME__")
-if args.password:
-    password = args.password
-else:
-    default_password = "__PASSWORD__"
-    password_prompt = f"Enter your password (default: use stored password): "
-    password_input = getpass.getpass(password_prompt)
-    password = password_input if password_input else default_password
-
-Auth_manager = AuthManager(api_url, username, password)
-token = Auth_manager.get_token()
-reconciliation_manager = ReconciliationManager(base_url, Auth_manager)
-dataset_manager = DatasetManager(base_url, Auth_manager)
-table_manager = TableManager(base_url, Auth_manager)
-extension_manager = ExtensionManager(base_url, token)
-utility = Utility(base_url, Auth_manager)
-manager = ModificationManager(base_url, token)
-
-"#;
-
-const BASE_NOTEBOOK_FILE_CONTENT: &str = r#"
-import semt_py
-import getpass
-from semt_py import AuthManager
-from semt_py.extension_manager import ExtensionManager
-from semt_py.reconciliation_manager import ReconciliationManager
-from semt_py.utils import Utility
-from semt_py.dataset_manager import DatasetManager
-from semt_py.table_manager import TableManager
-from semt_py.modification_manager import ModificationManager
-
-def get_input_with_default(prompt, default):
-    user_input = input(f"{prompt} (default: {default}): ").strip()
-    return user_input if user_input else default
-
-base_url = get_input_with_default("Enter base URL or press Enter to keep default", "__BASE_URL__")
-api_url = base_url + "/api"
-username = get_input_with_default("Enter your username", "__USERNAME__")
-default_password = "__PASSWORD__"
-password_prompt = f"Enter your password (default: use stored password): "
-password_input = getpass.getpass(password_prompt)
-password = password_input if password_input else default_password
-
-Auth_manager = AuthManager(api_url, username, password)
-token = Auth_manager.get_token()
-reconciliation_manager = ReconciliationManager(base_url, Auth_manager)
-dataset_manager = DatasetManager(base_url, Auth_manager)
-table_manager = TableManager(base_url, Auth_manager)
-extension_manager = ExtensionManager(base_url, token)
-utility = Utility(base_url, Auth_manager)
-manager = ModificationManager(base_url, token)
-
-"#;
-
-const BASE_PYTHON_DATASET_LOAD_DATAFRAME: &str = r#"
-import pandas as pd
-
-if args.dataset_id:
-    dataset_id = args.dataset_id
-else:
-    dataset_id = get_input_with_default("Enter dataset_id or press Enter to keep default", "__DATASET_ID__")
-if args.table_name:
-    table_name = args.table_name
-else:
-    table_name = get_input_with_default("Enter table_name or press Enter to keep default", "__TABLE_NAME__")
-
-if args.csv_file:
-    filename = args.csv_file
-else:
-    filename = get_input_with_default("Enter path to CSV file or press Enter to keep default", "__TABLE_PATH__")
-df = pd.read_csv(filename)
-
-columns_to_delete = [__COLUMNS_TO_DELETE__]
-if columns_to_delete and columns_to_delete != ['']:
-    for col in columns_to_delete:
-        if col in df.columns:
-            df = df.drop(columns=[col])
-            print(f"Deleted column: {col}")
-        else:
-            print(f"Column '{col}' not found in table")
-    print(f"Columns deleted: {[col for col in columns_to_delete if col in df.columns]}")
-
-table_id, message, table_data = table_manager.add_table(dataset_id, df, table_name)
-
-print(f"Table loaded successfully: {message}")
-try:
-    from IPython.display import display
-    print("Showing dataframe head:")
-    display(df.head())
-except Exception as e:
-    print(f"Could not display DataFrame head: {e}")
-    print(df.head().to_string())
-"#;
-
-const BASE_NOTEBOOK_DATASET_LOAD_DATAFRAME: &str = r#"
-import pandas as pd
-
-dataset_id = get_input_with_default("Enter dataset_id or press Enter to keep default", "__DATASET_ID__")
-table_name = get_input_with_default("Enter table_name or press Enter to keep default", "__TABLE_NAME__")
-
-filename = get_input_with_default("Enter path to CSV file or press Enter to keep default", "__TABLE_PATH__")
-df = pd.read_csv(filename)
-
-columns_to_delete = [__COLUMNS_TO_DELETE__]
-if columns_to_delete and columns_to_delete != ['']:
-    for col in columns_to_delete:
-        if col in df.columns:
-            df = df.drop(columns=[col])
-            print(f"Deleted column: {col}")
-        else:
-            print(f"Column '{col}' not found in table")
-    print(f"Columns deleted: {[col for col in columns_to_delete if col in df.columns]}")
-
-table_id, message, table_data = table_manager.add_table(dataset_id, df, table_name)
-
-print(f"Table loaded successfully: {message}")
-try:
-    from IPython.display import display
-    print("Showing dataframe head:")
-    display(df.head())
-except Exception as e:
-    print(f"Could not display DataFrame head: {e}")
-    print(df.head().to_string())
-"#;
-
-const BASE_RECONCILE_OPERATION: &str = r#"
-
-reconciliator_id = "__RECONCILIATOR_ID__"
-optional_columns = [__OPTIONAL_COLUMNS__]
-column_name = "__COLUMN_NAME__"
-try:
-    table_data = table_manager.get_table(dataset_id, table_id)
-    reconciled_table, backend_payload = reconciliation_manager.reconcile(
-        table_data,
-        column_name,
-        reconciliator_id,
-        optional_columns
-    )
-    payload = backend_payload
-
-    successMessage, sentPayload = utility.push_to_backend(
-    dataset_id,
-    table_id,
-    payload,
-    debug=False
-    )
-
-    print(successMessage)
-
-    # Display only the reconciled column with its metadata
-    affected_columns = [column_name] + [col + "_metadata" for col in [column_name]]
-    print(f"Displaying reconciled column: {column_name}")
-
-    html_table = Utility.display_json_table(
-        json_table=reconciled_table,
-        number_of_rows=4,
-        from_row=0,
-        labels=[column_name]
-    )
-    if html_table is not None:
-        from IPython.display import display
-        display(html_table)
-except Exception as e:
-    print(f"An error occurred during reconciliation: {e}")
-"#;
-
-const BASE_PROPAGATION_OPERATION: &str = r#"
-try:
-    # Fetch fresh table data from backend to ensure we have the latest state
-    table_data = table_manager.get_table(dataset_id, table_id)
-
-    type_obj = __TYPE_TO_PROPAGATE__
-    propagated_column = '__COL_TO_PROPAGATE__'
-
-    table_data, backend_payload = manager.propagate_type(table_data, propagated_column, type_obj)
-
-
-    successMessage, sentPayload = utility.push_to_backend(
-        dataset_id,
-        table_id,
-        backend_payload,
-        debug=False
-    )
-
-    print(successMessage)
-
-    # Display only the propagated column
-    print(f"Displaying propagated column: {propagated_column}")
-
-    html_table = Utility.display_json_table(
-        json_table=table_data,
-        number_of_rows=4,
-        from_row=0,
-        labels=[propagated_column]
-    )
-    if html_table is not None:
-        from IPython.display import display
-        display(html_table)
-except Exception as e:
-    print(f"An error occurred during propagation: {e}")
-
-"#;
-
-const BASE_EXTENSION_OPERATION: &str = r#"
-try:
-    table_data = table_manager.get_table(dataset_id, table_id)
-
-    # Store columns before extension
-    prev_columns = set(table_data['columns'].keys())
-    base_column = "__COLUMN_NAME__"
-
-    extended_table, extension_payload = extension_manager.extend_column(
-        table=table_data,
-        column_name=base_column,
-        extender_id="__EXTENDER_ID__",
-        properties=__EXTENSION_PROPERTIES__,
-        other_params={__EXTENSION_PARAMS__}
-    )
-    payload = extension_payload
-
-    successMessage, sentPayload = utility.push_to_backend(
-        dataset_id,
-        table_id,
-        payload,
-        debug=False
-    )
-
-    print(successMessage)
-
-    # Display only newly added columns from extension
-    current_columns = set(extended_table['columns'].keys())
-    new_columns = list(current_columns - prev_columns)
-
-    # Include the base column and the new extended columns
-    affected_columns = [base_column] + new_columns
-    print(f"Displaying base column '{base_column}' and new extended columns: {new_columns}")
-
-    html_table = Utility.display_json_table(
-        json_table=extended_table,
-        number_of_rows=4,
-        from_row=0,
-        labels=affected_columns
-    )
-    if html_table is not None:
-        from IPython.display import display
-        display(html_table)
-except Exception as e:
-    print(f"An error occurred during extension: {e}")
-"#;
-
-const BASE_EXPORT_CSV_OPERATION: &str = r#"
-# Export as CSV
-try:
-    csv_file = utility.download_csv(
-        dataset_id=dataset_id,
-        table_id=table_id,
-        output_file="__OUTPUT_FILE__"
-    )
-    print(f"✓ CSV downloaded: {csv_file}")
-except Exception as e:
-    print(f"✗ Error downloading CSV: {e}")
-"#;
-
-const BASE_MODIFICATION_OPERATION: &str = r#"
-try:
-    table_data = table_manager.get_table(dataset_id, table_id)
-    modified_column = "__COLUMN_NAME__"
-
-    modified_table, payload = manager.modify(
-        table=table_data,
-        column_name=modified_column,
-        modifier_name="__MODIFIER_NAME__",
-        props=__MODIFICATION_PROPS__
-    )
-
-    successMessage, sentPayload = utility.push_to_backend(
-        dataset_id,
-        table_id,
-        payload,
-        debug=False
-    )
-
-    print(successMessage)
-
-    # Display only the modified column
-    print(f"Displaying modified column: {modified_column}")
-
-    html_table = Utility.display_json_table(
-        json_table=modified_table,
-        number_of_rows=4,
-        from_row=0,
-        labels=[modified_column]
-    )
-    if html_table is not None:
-        from IPython.display import display
-        display(html_table)
-except Exception as e:
-    print(f"An error occurred during modification: {e}")
-"#;
-
-const BASE_EXPORT_JSON_OPERATION: &str = r#"
-# Export as JSON
-try:
-    json_file = utility.download_json(
-        dataset_id=dataset_id,
-        table_id=table_id,
-        output_file="__OUTPUT_FILE__"
-    )
-    print(f"✓ JSON downloaded: {json_file}")
-except Exception as e:
-    print(f"✗ Error downloading JSON: {e}")
-"#;
+mod python_literal;
+mod templates;
+
+pub use python_literal::{python_comment, python_float, python_literal, python_str};
+pub use templates::{find_placeholders, TemplateSpec, Templates, BUILTIN_TEMPLATES};
+
+use crate::{credentials::CredentialStrategy, pipeline::TableLoader};
+use minijinja::context;
+use serde_json::{Map, Value};
+
+pub fn get_base_python_file_loader_code(
+    templates: &Templates,
+    base_url: &str,
+    credentials: CredentialStrategy,
+) -> Result<String, String> {
+    templates.render("python_setup.py.j2", setup_context(base_url, credentials))
+}
 
-pub fn get_base_python_file_loader_code() -> String {
-    let formatted_code = BASE_PYTHON_FILE_CONTENT
-        .replace(
-            "__USERNAME__",
-            &std::env::var("USERNAME").unwrap_or_default(),
-        )
-        .replace(
-            "__PASSWORD__",
-            &std::env::var("PASSWORD").unwrap_or_default(),
-        )
-        .replace(
-            "__BASE_URL__",
-            &std::env::var("BASE_URL")
-                .unwrap_or("http://vm.chronos.disco.unimib.it:3003".to_string()),
-        );
-    formatted_code
+pub fn get_base_notebook_file_loader_code(
+    templates: &Templates,
+    base_url: &str,
+    credentials: CredentialStrategy,
+) -> Result<String, String> {
+    templates.render("notebook_setup.py.j2", setup_context(base_url, credentials))
 }
 
-pub fn get_base_notebook_file_loader_code() -> String {
-    let formatted_code = BASE_NOTEBOOK_FILE_CONTENT
-        .replace(
-            "__USERNAME__",
-            &std::env::var("USERNAME").unwrap_or_default(),
-        )
-        .replace(
-            "__PASSWORD__",
-            &std::env::var("PASSWORD").unwrap_or_default(),
-        )
-        .replace(
-            "__BASE_URL__",
-            &std::env::var("BASE_URL")
-                .unwrap_or("http://vm.chronos.disco.unimib.it:3003".to_string()),
-        );
-    formatted_code
+/// Only the `embed` strategy passes the username and password of the environment; the
+/// others leave them `None` so that no secret reaches the generated code.
+fn setup_context(base_url: &str, credentials: CredentialStrategy) -> minijinja::Value {
+    let embedded = |var: &str| match credentials {
+        CredentialStrategy::Embed => std::env::var(var).ok(),
+        _ => None,
+    };
+    context! {
+        base_url,
+        credentials => credentials.as_str(),
+        username => embedded("USERNAME"),
+        password => embedded("PASSWORD"),
+    }
 }
 
 pub fn get_base_python_dataset_loader(
-    table_path: &str,
-    dataset_id: &str,
-    table_name: &str,
-) -> String {
-    let formatted_code = BASE_PYTHON_DATASET_LOAD_DATAFRAME
-        .replace("__TABLE_PATH__", table_path)
-        .replace("__DATASET_ID__", dataset_id)
-        .replace("__TABLE_NAME__", table_name)
-        .replace("__COLUMNS_TO_DELETE__", "");
-    formatted_code
+    templates: &Templates,
+    loader: &TableLoader,
+) -> Result<String, String> {
+    templates.render("python_loader.py.j2", loader_context(loader))
 }
 
-pub fn get_base_python_dataset_loader_with_column_deletion(
-    table_path: &str,
-    dataset_id: &str,
-    table_name: &str,
-    columns_to_delete: Vec<String>,
-) -> String {
-    let columns_to_delete_str = if columns_to_delete.is_empty() {
-        "".to_string()
-    } else {
-        columns_to_delete
-            .iter()
-            .map(|col| format!("'{}'", col))
-            .collect::<Vec<String>>()
-            .join(", ")
-    };
-
-    let formatted_code = BASE_PYTHON_DATASET_LOAD_DATAFRAME
-        .replace("__TABLE_PATH__", table_path)
-        .replace("__DATASET_ID__", dataset_id)
-        .replace("__TABLE_NAME__", table_name)
-        .replace("__COLUMNS_TO_DELETE__", &columns_to_delete_str);
-    formatted_code
+fn loader_context(loader: &TableLoader) -> minijinja::Value {
+    context! {
+        loader => loader.mode.as_str(),
+        table_path => loader.table_file,
+        dataset_id => loader.dataset_id,
+        dataset_name => loader.dataset_name,
+        table_id => loader.table_id,
+        table_name => loader.table_name,
+        columns_to_delete => loader.deleted_columns,
+    }
 }
 
 pub fn get_base_notebook_dataset_loader(
-    table_path: &str,
-    dataset_id: &str,
-    table_name: &str,
-) -> String {
-    let formatted_code = BASE_NOTEBOOK_DATASET_LOAD_DATAFRAME
-        .replace("__TABLE_PATH__", table_path)
-        .replace("__DATASET_ID__", dataset_id)
-        .replace("__TABLE_NAME__", table_name)
-        .replace("__COLUMNS_TO_DELETE__", "");
-    formatted_code
-}
-
-pub fn get_base_notebook_dataset_loader_with_column_deletion(
-    table_path: &str,
-    dataset_id: &str,
-    table_name: &str,
-    columns_to_delete: Vec<String>,
-) -> String {
-    let columns_to_delete_str = if columns_to_delete.is_empty() {
-        "".to_string()
-    } else {
-        columns_to_delete
-            .iter()
-            .map(|col| format!("'{}'", col))
-            .collect::<Vec<String>>()
-            .join(", ")
-    };
-
-    let formatted_code = BASE_NOTEBOOK_DATASET_LOAD_DATAFRAME
-        .replace("__TABLE_PATH__", table_path)
-        .replace("__DATASET_ID__", dataset_id)
-        .replace("__TABLE_NAME__", table_name)
-        .replace("__COLUMNS_TO_DELETE__", &columns_to_delete_str);
-    formatted_code
+    templates: &Templates,
+    loader: &TableLoader,
+) -> Result<String, String> {
+    templates.render("notebook_loader.py.j2", loader_context(loader))
 }
 
 pub fn get_base_extension_operation(
+    templates: &Templates,
     column_name: &str,
-    properties: Vec<String>,
-    additional_params: Option<Vec<String>>,
+    properties: &[String],
+    other_params: &Map<String, Value>,
     extender_id: &str,
-) -> String {
-    // Format properties as a Python list (e.g. ["P373", "P31", "P625"])
-    let properties_str = if properties.is_empty() {
-        "[]".to_string()
-    } else {
-        format!(
-            "[{}]",
-            properties
-                .iter()
-                .map(|p| format!("\"{}\"", p))
-                .collect::<Vec<String>>()
-                .join(", ")
-        )
-    };
-
-    let additional_params_str = match additional_params {
-        Some(params) if !params.is_empty() => params.join(", "),
-        _ => String::from(""),
-    };
-    let formatted_code = BASE_EXTENSION_OPERATION
-        .replace("__COLUMN_NAME__", column_name)
-        .replace("__EXTENSION_PROPERTIES__", &properties_str)
-        .replace("__EXTENSION_PARAMS__", &additional_params_str)
-        .replace("__EXTENDER_ID__", extender_id);
-    formatted_code
+) -> Result<String, String> {
+    templates.render(
+        "extension.py.j2",
+        context! { column_name, properties, other_params, extender_id },
+    )
 }
 
 pub fn get_base_reconciliation_operation(
+    templates: &Templates,
     column_name: &str,
-    additional_columns: Option<Vec<String>>,
+    additional_columns: &[String],
     reconciler_id: &str,
-) -> String {
-    let additional_columns_str = match additional_columns {
-        Some(columns) if !columns.is_empty() => columns.join(", "),
-        _ => String::from(""),
-    };
-    let formatted_code = BASE_RECONCILE_OPERATION
-        .replace("__RECONCILIATOR_ID__", reconciler_id) // Replace with actual reconciliator ID
-        .replace("__COLUMN_NAME__", column_name)
-        .replace("__OPTIONAL_COLUMNS__", &additional_columns_str);
-    formatted_code
-}
-
-fn value_to_python(value: &Value) -> String {
-    match value {
-        Value::Null => "None".to_string(),
-        Value::Bool(b) => {
-            if *b {
-                "True".to_string()
-            } else {
-                "False".to_string()
-            }
-        }
-        Value::Number(n) => n.to_string(),
-        Value::String(s) => format!("\"{}\"", s.replace("\"", "\\\"")),
-        Value::Array(arr) => {
-            let items: Vec<String> = arr.iter().map(value_to_python).collect();
-            format!("[{}]", items.join(", "))
-        }
-        Value::Object(obj) => {
-            let items: Vec<String> = obj
-                .iter()
-                .map(|(k, v)| format!("\"{}\": {}", k, value_to_python(v)))
-                .collect();
-            format!("{{{}}}", items.join(", "))
-        }
-    }
+) -> Result<String, String> {
+    templates.render(
+        "reconciliation.py.j2",
+        context! { column_name, reconciler_id, optional_columns => additional_columns },
+    )
 }
 
 pub fn get_base_propagation_operation(
+    templates: &Templates,
     column_name: &str,
-    additional_data: Option<&Value>,
-) -> String {
-    let type_str = additional_data
-        .map(value_to_python)
-        .unwrap_or_else(|| "{}".to_string());
-    let formatted_code = BASE_PROPAGATION_OPERATION
-        .replace("__COL_TO_PROPAGATE__", column_name)
-        .replace("__TYPE_TO_PROPAGATE__", &type_str);
-    formatted_code
+    type_obj: &Value,
+) -> Result<String, String> {
+    templates.render("propagation.py.j2", context! { column_name, type_obj })
 }
 
 pub fn get_base_modification_operation(
+    templates: &Templates,
     column_name: &str,
     modifier_name: &str,
     props: &Value,
-) -> String {
-    let props_str = value_to_python(props);
-    let formatted_code = BASE_MODIFICATION_OPERATION
-        .replace("__COLUMN_NAME__", column_name)
-        .replace("__MODIFIER_NAME__", modifier_name)
-        .replace("__MODIFICATION_PROPS__", &props_str);
-    formatted_code
+) -> Result<String, String> {
+    templates.render(
+        "modification.py.j2",
+        context! { column_name, modifier_name, props },
+    )
 }
 
-pub fn get_base_export_operation(format: &str, output_file: &str) -> Option<String> {
+/// Template of an export format, `None` if the format is not supported.
+pub fn export_template(format: &str) -> Option<&'static str> {
     match format.to_lowercase().as_str() {
-        "csv" => {
-            let formatted_code = BASE_EXPORT_CSV_OPERATION.replace("__OUTPUT_FILE__", output_file);
-            Some(formatted_code)
+        "csv" => Some("export_csv.py.j2"),
+        "w3c" | "json" => Some("export_json.py.j2"),
+        _ => None,
+    }
+}
+
+pub fn get_base_export_operation(
+    templates: &Templates,
+    format: &str,
+    output_file: &str,
+) -> Result<String, String> {
+    let template =
+        export_template(format).ok_or_else(|| format!("unsupported export format: {}", format))?;
+    templates.render(template, context! { output_file })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{LoaderMode, DEFAULT_BASE_URL};
+
+    /// Every built-in template rendered through its `get_base_*` function.
+    fn render_builtin(templates: &Templates) -> Vec<(&'static str, Result<String, String>)> {
+        let columns = vec!["Country".to_string()];
+        let properties = vec!["P17".to_string(), "P1082".to_string()];
+        let type_obj = serde_json::json!({"id": "wd:Q515", "name": "city"});
+        let other_params = serde_json::json!({"date_column_name": "Date"})
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        let loader = |mode: LoaderMode, dataset_name: Option<&str>| TableLoader {
+            mode,
+            table_id: None,
+            table_file: "table.csv".to_string(),
+            dataset_id: "1".to_string(),
+            dataset_name: dataset_name.map(str::to_string),
+            table_name: "t".to_string(),
+            logged_table_name: None,
+            logged_dataset_name: None,
+            deleted_columns: columns.clone(),
+            operations: Vec::new(),
+        };
+        vec![
+            (
+                "python_setup.py.j2",
+                get_base_python_file_loader_code(
+                    templates,
+                    DEFAULT_BASE_URL,
+                    CredentialStrategy::Env,
+                ),
+            ),
+            (
+                "notebook_setup.py.j2",
+                get_base_notebook_file_loader_code(
+                    templates,
+                    DEFAULT_BASE_URL,
+                    CredentialStrategy::Env,
+                ),
+            ),
+            (
+                "python_loader.py.j2",
+                get_base_python_dataset_loader(templates, &loader(LoaderMode::Upload, None)),
+            ),
+            (
+                "notebook_loader.py.j2",
+                get_base_notebook_dataset_loader(
+                    templates,
+                    &loader(LoaderMode::Existing, Some("demo")),
+                ),
+            ),
+            (
+                "reconciliation.py.j2",
+                get_base_reconciliation_operation(templates, "City", &columns, "wikidata"),
+            ),
+            (
+                "extension.py.j2",
+                get_base_extension_operation(templates, "City", &properties, &other_params, "wd"),
+            ),
+            (
+                "propagation.py.j2",
+                get_base_propagation_operation(templates, "City", &type_obj),
+            ),
+            (
+                "modification.py.j2",
+                get_base_modification_operation(templates, "City", "toUpper", &type_obj),
+            ),
+            (
+                "export_csv.py.j2",
+                get_base_export_operation(templates, "csv", "out.csv"),
+            ),
+            (
+                "export_json.py.j2",
+                get_base_export_operation(templates, "json", "out.json"),
+            ),
+        ]
+    }
+
+    #[test]
+    fn get_base_functions_supply_the_declared_variables() {
+        let templates = Templates::builtin();
+        let rendered = render_builtin(&templates);
+        for spec in BUILTIN_TEMPLATES {
+            let (_, result) = rendered
+                .iter()
+                .find(|(name, _)| *name == spec.name)
+                .unwrap_or_else(|| panic!("no get_base_* function renders {}", spec.name));
+            match result {
+                Ok(code) => assert!(
+                    find_placeholders(code).is_empty(),
+                    "{} left placeholders in {}",
+                    spec.name,
+                    code
+                ),
+                Err(e) => panic!("{}", e),
+            }
         }
-        "w3c" | "json" => {
-            let formatted_code = BASE_EXPORT_JSON_OPERATION.replace("__OUTPUT_FILE__", output_file);
-            Some(formatted_code)
+    }
+
+    /// Whether python3 compiles `code`, `None` when python3 is not available.
+    fn python_compiles(code: &str) -> Option<bool> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+        let mut child = Command::new("python3")
+            .args([
+                "-c",
+                "import sys; compile(sys.stdin.read(), 'loader', 'exec')",
+            ])
+            .stdin(Stdio::piped())
+            .spawn()
+            .ok()?;
+        child.stdin.take()?.write_all(code.as_bytes()).ok()?;
+        Some(child.wait().ok()?.success())
+    }
+
+    #[test]
+    fn each_loader_mode_emits_its_code() {
+        let templates = Templates::builtin();
+        let loader =
+            |mode: LoaderMode, dataset_name: Option<&str>, table_id: Option<&str>| TableLoader {
+                mode,
+                table_id: table_id.map(str::to_string),
+                table_file: "table.csv".to_string(),
+                dataset_id: "1".to_string(),
+                dataset_name: dataset_name.map(str::to_string),
+                table_name: "t".to_string(),
+                logged_table_name: None,
+                logged_dataset_name: None,
+                deleted_columns: vec!["Notes".to_string()],
+                operations: Vec::new(),
+            };
+        // (loader, code of the script, code of the notebook, code the notebook leaves out)
+        let cases = [
+            (
+                loader(LoaderMode::Upload, None, None),
+                vec![
+                    "loader = args.loader or (\"existing\" if args.table_id else \"upload\")",
+                    "dataset_name = args.dataset_name or None",
+                    "table_id = args.table_id or None",
+                ],
+                vec![
+                    "keep default\", \"1\")",
+                    "table_manager.add_table(dataset_id, df, table_name)",
+                ],
+                vec!["find_dataset_id", "find_table_id", "get_table("],
+            ),
+            (
+                loader(LoaderMode::Upload, Some("demo"), None),
+                vec!["dataset_name = args.dataset_name or \"demo\""],
+                vec![
+                    "keep default\", \"demo\")",
+                    "dataset_id = find_dataset_id(dataset_name)",
+                    "raise ValueError(f\"No dataset named",
+                    "table_manager.add_table(dataset_id, df, table_name)",
+                ],
+                vec!["create_dataset", "find_table_id"],
+            ),
+            (
+                loader(LoaderMode::Existing, None, Some("9")),
+                vec![
+                    "loader = args.loader or (\"existing\" if args.table_id else \"existing\")",
+                    "table_id = args.table_id or \"9\"",
+                ],
+                vec![
+                    "keep default\", \"9\")",
+                    "table_data = table_manager.get_table(dataset_id, table_id)",
+                ],
+                vec!["add_table", "find_dataset_id", "create_dataset"],
+            ),
+            (
+                loader(LoaderMode::Existing, Some("demo"), None),
+                vec![
+                    "loader = args.loader or (\"existing\" if args.table_id else \"existing\")",
+                    "dataset_name = args.dataset_name or \"demo\"",
+                ],
+                vec![
+                    "dataset_id = create_dataset(dataset_name, df, table_name)",
+                    "raise ValueError(f\"Could not create dataset",
+                    "table_id = find_table_id(dataset_id, table_name)",
+                    "table_data = table_manager.get_table(dataset_id, table_id)",
+                ],
+                vec!["add_table", "No dataset named"],
+            ),
+        ];
+        for (loader, script_has, notebook_has, notebook_lacks) in cases {
+            let script = get_base_python_dataset_loader(&templates, &loader).unwrap();
+            let notebook = get_base_notebook_dataset_loader(&templates, &loader).unwrap();
+            for code in script_has {
+                assert!(script.contains(code), "{} not in {}", code, script);
+            }
+            for code in notebook_has {
+                assert!(notebook.contains(code), "{} not in {}", code, notebook);
+            }
+            for code in notebook_lacks {
+                assert!(!notebook.contains(code), "{} in {}", code, notebook);
+            }
+            // The script decides at runtime, so it always carries every mode and guard
+            assert!(script.contains("raise SystemExit(f\"Could not create dataset"));
+            for code in [&script, &notebook] {
+                match python_compiles(code) {
+                    Some(compiles) => assert!(compiles, "invalid Python: {}", code),
+                    None => eprintln!("python3 not available, skipping the compile check"),
+                }
+            }
         }
-        _ => None,
+    }
+
+    #[test]
+    fn missing_or_undeclared_variables_fail_rendering() {
+        let templates = Templates::builtin();
+        let error = templates
+            .render(
+                "reconciliation.py.j2",
+                context! { column_name => "City", reconciler => "wikidata" },
+            )
+            .unwrap_err();
+        assert!(
+            error.contains("missing reconciler_id, optional_columns"),
+            "{}",
+            error
+        );
+        assert!(error.contains("undeclared reconciler"), "{}", error);
+    }
+
+    #[test]
+    fn finds_upper_case_placeholders() {
+        assert_eq!(
+            find_placeholders(
+                "x = [__OPTIONAL_COLUMNS__]\ny = \"__COLUMN_NAME__\" + __COLUMN_NAME__"
+            ),
+            vec!["__OPTIONAL_COLUMNS__", "__COLUMN_NAME__"]
+        );
+        assert!(find_placeholders("if __name__ == \"__main__\": a__B__ = __init__").is_empty());
     }
 }
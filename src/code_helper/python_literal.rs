@@ -0,0 +1,641 @@
+use serde::ser::{self, Serialize};
+use std::fmt::{self, Write};
+
+/// Writes `value` as Python source that evaluates to the same value.
+///
+/// Strings are always double-quoted with every quote, backslash and control character
+/// escaped, so a value read from the log cannot end the literal and inject code. Maps become
+/// dicts, sequences lists, `None`/unit `None`. Everything but non-finite floats (see
+/// [`python_float`]) is accepted by `ast.literal_eval`.
+pub fn python_literal<T: Serialize + ?Sized>(value: &T) -> Result<String, String> {
+    let mut serializer = PythonSerializer::default();
+    value
+        .serialize(&mut serializer)
+        .map_err(|e| format!("cannot write value as a Python literal: {}", e))?;
+    Ok(serializer.out)
+}
+
+/// A Python `str` literal for `value`.
+pub fn python_str(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            // C0/C1 controls and DEL; everything else is valid in UTF-8 source
+            c if c.is_control() => {
+                let _ = write!(out, "\\x{:02x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `text` made safe to follow a `#`: line breaks and other control characters are escaped
+/// so a log value cannot end the comment.
+pub fn python_comment(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() && c != '\t' => {
+                let _ = write!(out, "\\x{:02x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A Python `float` expression. Finite values are literals that keep their exact value,
+/// e.g. `1.0` or `1e-7`. NaN and infinities have no literal and are written
+/// `float("nan")`, `float("inf")` and `-float("inf")`.
+pub fn python_float(value: f64) -> String {
+    if value.is_nan() {
+        "float(\"nan\")".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 {
+            "float(\"inf\")".to_string()
+        } else {
+            "-float(\"inf\")".to_string()
+        }
+    } else {
+        // Debug gives the shortest representation that reads back as the same f64 and
+        // always keeps a `.` or an exponent, so Python does not read it as an int
+        format!("{:?}", value)
+    }
+}
+
+fn python_bytes(value: &[u8]) -> String {
+    let mut out = String::from("b\"");
+    for &b in value {
+        match b {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            0x20..=0x7e => out.push(b as char),
+            _ => {
+                let _ = write!(out, "\\x{:02x}", b);
+            }
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[derive(Debug)]
+pub struct LiteralError(String);
+
+impl fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for LiteralError {}
+
+impl ser::Error for LiteralError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        LiteralError(msg.to_string())
+    }
+}
+
+#[derive(Default)]
+struct PythonSerializer {
+    out: String,
+    /// Serializing a dict key: only hashable scalars are allowed
+    in_key: bool,
+}
+
+impl PythonSerializer {
+    fn open(&mut self, bracket: char) -> Result<(), LiteralError> {
+        if self.in_key {
+            return Err(LiteralError(
+                "dict keys must be strings, numbers, booleans or None".to_string(),
+            ));
+        }
+        self.out.push(bracket);
+        Ok(())
+    }
+}
+
+/// A list or dict being written; `first` tells whether a separator is needed.
+struct Compound<'a> {
+    ser: &'a mut PythonSerializer,
+    first: bool,
+    close: char,
+}
+
+impl Compound<'_> {
+    fn separator(&mut self) {
+        if !self.first {
+            self.ser.out.push_str(", ");
+        }
+        self.first = false;
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), LiteralError> {
+        self.separator();
+        value.serialize(&mut *self.ser)
+    }
+
+    fn entry<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), LiteralError> {
+        self.separator();
+        self.ser.out.push_str(&python_str(key));
+        self.ser.out.push_str(": ");
+        value.serialize(&mut *self.ser)
+    }
+
+    fn close(self) -> Result<(), LiteralError> {
+        self.ser.out.push(self.close);
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut PythonSerializer {
+    type Ok = ();
+    type Error = LiteralError;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), LiteralError> {
+        self.out.push_str(if v { "True" } else { "False" });
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), LiteralError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), LiteralError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), LiteralError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), LiteralError> {
+        let _ = write!(self.out, "{}", v);
+        Ok(())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), LiteralError> {
+        let _ = write!(self.out, "{}", v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), LiteralError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), LiteralError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), LiteralError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), LiteralError> {
+        let _ = write!(self.out, "{}", v);
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), LiteralError> {
+        let _ = write!(self.out, "{}", v);
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), LiteralError> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), LiteralError> {
+        self.out.push_str(&python_float(v));
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), LiteralError> {
+        self.out.push_str(&python_str(v.encode_utf8(&mut [0; 4])));
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), LiteralError> {
+        self.out.push_str(&python_str(v));
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), LiteralError> {
+        self.out.push_str(&python_bytes(v));
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), LiteralError> {
+        self.out.push_str("None");
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), LiteralError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), LiteralError> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), LiteralError> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), LiteralError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), LiteralError> {
+        value.serialize(self)
+    }
+
+    /// `{"Variant": value}`, as serde_json writes it
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), LiteralError> {
+        self.open('{')?;
+        let mut compound = Compound {
+            ser: self,
+            first: true,
+            close: '}',
+        };
+        compound.entry(variant, value)?;
+        compound.close()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, LiteralError> {
+        self.open('[')?;
+        Ok(Compound {
+            ser: self,
+            first: true,
+            close: ']',
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, LiteralError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, LiteralError> {
+        self.serialize_seq(Some(len))
+    }
+
+    /// `{"Variant": [..]}`
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, LiteralError> {
+        self.open('{')?;
+        self.out.push_str(&python_str(variant));
+        self.out.push_str(": [");
+        Ok(Compound {
+            ser: self,
+            first: true,
+            close: ']',
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, LiteralError> {
+        self.open('{')?;
+        Ok(Compound {
+            ser: self,
+            first: true,
+            close: '}',
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'a>, LiteralError> {
+        self.serialize_map(Some(len))
+    }
+
+    /// `{"Variant": {..}}`
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a>, LiteralError> {
+        self.open('{')?;
+        self.out.push_str(&python_str(variant));
+        self.out.push_str(": {");
+        Ok(Compound {
+            ser: self,
+            first: true,
+            close: '}',
+        })
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = LiteralError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), LiteralError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), LiteralError> {
+        self.close()
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = LiteralError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), LiteralError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), LiteralError> {
+        self.close()
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = LiteralError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), LiteralError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), LiteralError> {
+        self.close()
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = LiteralError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), LiteralError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), LiteralError> {
+        self.ser.out.push_str("]}");
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = LiteralError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), LiteralError> {
+        self.separator();
+        self.ser.in_key = true;
+        let result = key.serialize(&mut *self.ser);
+        self.ser.in_key = false;
+        result?;
+        self.ser.out.push_str(": ");
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), LiteralError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), LiteralError> {
+        self.close()
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = LiteralError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), LiteralError> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<(), LiteralError> {
+        self.close()
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = LiteralError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), LiteralError> {
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<(), LiteralError> {
+        self.ser.out.push_str("}}");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json::Value;
+    use std::{
+        io::Write as _,
+        process::{Command, Stdio},
+    };
+
+    fn arb_string() -> impl Strategy<Value = String> {
+        prop::collection::vec(any::<char>(), 0..24).prop_map(|chars| chars.into_iter().collect())
+    }
+
+    fn arb_value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            any::<u64>().prop_map(Value::from),
+            any::<f64>()
+                .prop_filter("JSON has no NaN or infinity", |f| f.is_finite())
+                .prop_map(Value::from),
+            arb_string().prop_map(Value::from),
+        ];
+        leaf.prop_recursive(3, 48, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(Value::from),
+                prop::collection::btree_map(arb_string(), inner, 0..6)
+                    .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    /// Reads back a literal written by `python_str`, as the Python tokenizer would.
+    fn decode_python_str(literal: &str) -> Option<String> {
+        let body = literal.strip_prefix('"')?.strip_suffix('"')?;
+        let mut out = String::new();
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                // An unescaped quote would end the literal early
+                '"' => return None,
+                '\\' => match chars.next()? {
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    'x' => {
+                        let hex: String = chars.by_ref().take(2).collect();
+                        out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    escaped @ ('"' | '\\') => out.push(escaped),
+                    _ => return None,
+                },
+                c if c.is_control() => return None,
+                c => out.push(c),
+            }
+        }
+        Some(out)
+    }
+
+    /// Literals whose `ast.literal_eval` differs from the expected value (type included, so
+    /// `1` is not `1.0`); `None` without a Python 3. Compared in Python, which reads floats
+    /// exactly.
+    fn literal_eval_mismatches(literals: &[String], expected: &[Value]) -> Option<Vec<String>> {
+        let script = r#"
+import ast, json, math, sys
+
+def same(a, b):
+    if type(a) is not type(b):
+        return False
+    if isinstance(a, list):
+        return len(a) == len(b) and all(same(x, y) for x, y in zip(a, b))
+    if isinstance(a, dict):
+        return a.keys() == b.keys() and all(same(a[k], b[k]) for k in a)
+    if isinstance(a, float):
+        return a == b and math.copysign(1, a) == math.copysign(1, b)
+    return a == b
+
+data = json.load(sys.stdin)
+for literal, value in zip(data["literals"], data["expected"]):
+    if not same(ast.literal_eval(literal), value):
+        print(literal)
+"#;
+        let mut child = Command::new("python3")
+            .args(["-c", script])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let input = serde_json::json!({"literals": literals, "expected": expected});
+        child
+            .stdin
+            .take()?
+            .write_all(input.to_string().as_bytes())
+            .ok()?;
+        let output = child.wait_with_output().ok()?;
+        assert!(
+            output.status.success(),
+            "literal_eval failed on {:?}",
+            literals
+        );
+        Some(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.to_string())
+                .collect(),
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn strings_round_trip(value in arb_string()) {
+            let literal = python_str(&value);
+            prop_assert_eq!(decode_python_str(&literal), Some(value));
+        }
+
+        #[test]
+        fn comments_stay_on_one_line(value in arb_string()) {
+            let comment = python_comment(&value);
+            prop_assert!(!comment.contains(['\n', '\r']));
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn values_round_trip_through_literal_eval(
+            values in prop::collection::vec(arb_value(), 1..32)
+        ) {
+            let literals = values
+                .iter()
+                .map(|value| python_literal(value).unwrap())
+                .collect::<Vec<_>>();
+            // A raw line break would let a value continue on a line of its own
+            prop_assert!(literals.iter().all(|literal| !literal.contains(['\n', '\r'])));
+            match literal_eval_mismatches(&literals, &values) {
+                Some(mismatches) => prop_assert!(mismatches.is_empty(), "{:?}", mismatches),
+                None => eprintln!("python3 not available, skipping the literal_eval check"),
+            }
+        }
+    }
+
+    #[test]
+    fn non_finite_floats_are_expressions() {
+        assert_eq!(python_float(f64::NAN), "float(\"nan\")");
+        assert_eq!(python_float(f64::INFINITY), "float(\"inf\")");
+        assert_eq!(python_float(f64::NEG_INFINITY), "-float(\"inf\")");
+        assert_eq!(python_float(1.0), "1.0");
+        assert_eq!(python_literal(&vec![f64::NAN]).unwrap(), "[float(\"nan\")]");
+    }
+
+    #[test]
+    fn unhashable_dict_keys_are_rejected() {
+        let map = std::collections::BTreeMap::from([(vec![1], 2)]);
+        assert!(python_literal(&map).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use super::python_literal::python_literal;
+use crate::generators::GeneratorRegistry;
+use minijinja::{AutoEscape, Environment, Error, ErrorKind, UndefinedBehavior, Value};
+use serde::Serialize;
+use std::{collections::HashSet, path::Path};
+
+/// A block of generated code that a template directory can override.
+pub struct TemplateSpec {
+    /// File name of the template, e.g. `reconciliation.py.j2`
+    pub name: &'static str,
+    pub source: &'static str,
+    /// Variables the generator passes to the template
+    pub variables: &'static [&'static str],
+}
+
+/// The templates shipped in `templates/`.
+pub const BUILTIN_TEMPLATES: &[TemplateSpec] = &[
+    TemplateSpec {
+        name: "python_setup.py.j2",
+        source: include_str!("../../templates/python_setup.py.j2"),
+        variables: &["base_url", "credentials", "username", "password"],
+    },
+    TemplateSpec {
+        name: "notebook_setup.py.j2",
+        source: include_str!("../../templates/notebook_setup.py.j2"),
+        variables: &["base_url", "credentials", "username", "password"],
+    },
+    TemplateSpec {
+        name: "python_loader.py.j2",
+        source: include_str!("../../templates/python_loader.py.j2"),
+        variables: &[
+            "loader",
+            "table_path",
+            "dataset_id",
+            "dataset_name",
+            "table_id",
+            "table_name",
+            "columns_to_delete",
+        ],
+    },
+    TemplateSpec {
+        name: "notebook_loader.py.j2",
+        source: include_str!("../../templates/notebook_loader.py.j2"),
+        variables: &[
+            "loader",
+            "table_path",
+            "dataset_id",
+            "dataset_name",
+            "table_id",
+            "table_name",
+            "columns_to_delete",
+        ],
+    },
+    TemplateSpec {
+        name: "reconciliation.py.j2",
+        source: include_str!("../../templates/reconciliation.py.j2"),
+        variables: &["column_name", "reconciler_id", "optional_columns"],
+    },
+    TemplateSpec {
+        name: "extension.py.j2",
+        source: include_str!("../../templates/extension.py.j2"),
+        variables: &["column_name", "extender_id", "properties", "other_params"],
+    },
+    TemplateSpec {
+        name: "propagation.py.j2",
+        source: include_str!("../../templates/propagation.py.j2"),
+        variables: &["column_name", "type_obj"],
+    },
+    TemplateSpec {
+        name: "modification.py.j2",
+        source: include_str!("../../templates/modification.py.j2"),
+        variables: &["column_name", "modifier_name", "props"],
+    },
+    TemplateSpec {
+        name: "export_csv.py.j2",
+        source: include_str!("../../templates/export_csv.py.j2"),
+        variables: &["output_file"],
+    },
+    TemplateSpec {
+        name: "export_json.py.j2",
+        source: include_str!("../../templates/export_json.py.j2"),
+        variables: &["output_file"],
+    },
+];
+
+/// The code templates used for generation: the built-in ones and those registered by
+/// generators, with the files of a template directory replacing those with the same name.
+///
+/// Templates use Jinja syntax (minijinja). Every printed value is written as a Python
+/// literal (`{{ column_name }}` gives `"City"`, quotes escaped), unless marked `| safe`.
+/// Printing a variable the generator does not pass is an error.
+pub struct Templates {
+    env: Environment<'static>,
+    specs: Vec<&'static TemplateSpec>,
+    overridden: Vec<String>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl Templates {
+    pub fn builtin() -> Self {
+        let mut env = new_environment();
+        for spec in BUILTIN_TEMPLATES {
+            env.add_template(spec.name, spec.source)
+                .unwrap_or_else(|e| panic!("invalid built-in template {}: {}", spec.name, e));
+        }
+        Templates {
+            env,
+            specs: BUILTIN_TEMPLATES.iter().collect(),
+            overridden: Vec::new(),
+        }
+    }
+
+    /// The built-in templates and those of every generator in `registry`.
+    pub fn for_registry(registry: &GeneratorRegistry) -> Result<Self, String> {
+        let mut templates = Self::builtin();
+        for spec in registry.templates() {
+            templates.register(spec)?;
+        }
+        Ok(templates)
+    }
+
+    /// Adds the template of a generator. Its name must not be taken by another template.
+    pub fn register(&mut self, spec: &'static TemplateSpec) -> Result<(), String> {
+        if self.spec(spec.name).is_some() {
+            return Err(format!("template {} is already registered", spec.name));
+        }
+        self.env
+            .add_template(spec.name, spec.source)
+            .map_err(|e| format!("template {}: {}", spec.name, e))?;
+        self.specs.push(spec);
+        Ok(())
+    }
+
+    /// Names of the templates that can be rendered and overridden.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.specs.iter().map(|spec| spec.name).collect()
+    }
+
+    fn spec(&self, name: &str) -> Option<&'static TemplateSpec> {
+        self.specs.iter().find(|spec| spec.name == name).copied()
+    }
+
+    /// Replaces templates with the `*.py.j2` files of `dir`.
+    ///
+    /// A file that is not named after a known template, fails to parse or uses a variable
+    /// its generator does not pass is an error.
+    pub fn override_from_dir(&mut self, dir: &Path) -> Result<(), String> {
+        let entries = std::fs::read_dir(dir).map_err(|e| {
+            format!(
+                "could not read template directory '{}': {}",
+                dir.display(),
+                e
+            )
+        })?;
+        let mut paths = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        for path in paths {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let spec = self.spec(&file_name).ok_or_else(|| {
+                format!(
+                    "unknown template '{}' in '{}' (expected one of: {})",
+                    file_name,
+                    dir.display(),
+                    self.names().join(", ")
+                )
+            })?;
+            let source = std::fs::read_to_string(&path)
+                .map_err(|e| format!("could not read template '{}': {}", path.display(), e))?;
+            self.add_override(spec, source)?;
+        }
+        Ok(())
+    }
+
+    /// Names of the templates taken from the template directory.
+    pub fn overridden(&self) -> &[String] {
+        &self.overridden
+    }
+
+    /// Renders a template; undefined variables and template errors are reported with the
+    /// template name and line.
+    ///
+    /// The context must supply exactly the variables of the template's [`TemplateSpec`],
+    /// and no `__UPPER_CASE__` placeholder written in the template may survive in the code.
+    pub fn render<S: Serialize>(&self, name: &str, context: S) -> Result<String, String> {
+        let spec = self
+            .spec(name)
+            .ok_or_else(|| format!("unknown template {}", name))?;
+        let context = Value::from_serialize(&context);
+        check_supplied_variables(spec, &context)?;
+
+        let template = self
+            .env
+            .get_template(name)
+            .map_err(|e| format!("template {}: {}", name, e))?;
+        let code = template
+            .render(&context)
+            .map_err(|e| format!("template {}: {}", name, e))?;
+
+        // Placeholders can also come from log values (a column named __ID__); only those
+        // written in the template itself are unreplaced
+        let unreplaced: Vec<String> = find_placeholders(&code)
+            .into_iter()
+            .filter(|placeholder| template.source().contains(placeholder.as_str()))
+            .collect();
+        if !unreplaced.is_empty() {
+            return Err(format!(
+                "template {} left unreplaced placeholders: {}",
+                name,
+                unreplaced.join(", ")
+            ));
+        }
+        Ok(code)
+    }
+
+    fn add_override(&mut self, spec: &TemplateSpec, source: String) -> Result<(), String> {
+        let placeholders = find_placeholders(&source);
+        if !placeholders.is_empty() {
+            return Err(format!(
+                "template {} contains placeholders {}; use variables such as {{{{ {} }}}} instead",
+                spec.name,
+                placeholders.join(", "),
+                spec.variables.first().unwrap_or(&"column_name")
+            ));
+        }
+        self.env
+            .add_template_owned(spec.name, source)
+            .map_err(|e| format!("template {}: {}", spec.name, e))?;
+        let unknown = self.unknown_variables(spec);
+        if !unknown.is_empty() {
+            return Err(format!(
+                "template {} uses unknown variables: {} (available: {})",
+                spec.name,
+                unknown.join(", "),
+                spec.variables.join(", ")
+            ));
+        }
+        self.overridden.push(spec.name.to_string());
+        Ok(())
+    }
+
+    /// Variables used by the template that its generator does not pass.
+    fn unknown_variables(&self, spec: &TemplateSpec) -> Vec<String> {
+        let globals: HashSet<&str> = self.env.globals().map(|(name, _)| name).collect();
+        let mut unknown: Vec<String> = match self.env.get_template(spec.name) {
+            Ok(template) => template
+                .undeclared_variables(false)
+                .into_iter()
+                .filter(|name| {
+                    !spec.variables.contains(&name.as_str()) && !globals.contains(name.as_str())
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        unknown.sort();
+        unknown
+    }
+}
+
+/// `__UPPER_CASE__` placeholders in `text`, in order of first occurrence.
+pub fn find_placeholders(text: &str) -> Vec<String> {
+    let is_part = |c: char| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_';
+    let mut placeholders: Vec<String> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| is_part(c)) {
+        let before = rest[..start].chars().next_back();
+        let run_len = rest[start..]
+            .find(|c: char| !is_part(c))
+            .unwrap_or(rest.len() - start);
+        let run = &rest[start..start + run_len];
+        rest = &rest[start + run_len..];
+
+        // A run glued to lowercase letters is part of an identifier like `a__B__`
+        let glued = before.is_some_and(|c| c.is_alphanumeric())
+            || rest.chars().next().is_some_and(|c| c.is_alphanumeric());
+        let name = run.trim_matches('_');
+        let is_placeholder = run.starts_with("__")
+            && run.ends_with("__")
+            && name.starts_with(|c: char| c.is_ascii_uppercase());
+        if is_placeholder && !glued && !placeholders.iter().any(|p| p == run) {
+            placeholders.push(run.to_string());
+        }
+    }
+    placeholders
+}
+
+/// Errors unless the context supplies exactly the variables the template declares.
+fn check_supplied_variables(spec: &TemplateSpec, context: &Value) -> Result<(), String> {
+    let supplied: Vec<String> = context
+        .try_iter()
+        .map_err(|e| format!("template {}: invalid context: {}", spec.name, e))?
+        .filter_map(|key| key.as_str().map(|key| key.to_string()))
+        .collect();
+    let missing: Vec<&str> = spec
+        .variables
+        .iter()
+        .filter(|variable| !supplied.iter().any(|key| key == *variable))
+        .copied()
+        .collect();
+    let extra: Vec<&str> = supplied
+        .iter()
+        .filter(|key| !spec.variables.contains(&key.as_str()))
+        .map(|key| key.as_str())
+        .collect();
+    if missing.is_empty() && extra.is_empty() {
+        return Ok(());
+    }
+    let mut problems = Vec::new();
+    if !missing.is_empty() {
+        problems.push(format!("missing {}", missing.join(", ")));
+    }
+    if !extra.is_empty() {
+        problems.push(format!("undeclared {}", extra.join(", ")));
+    }
+    Err(format!(
+        "template {} declares {} but the generator supplies {} ({})",
+        spec.name,
+        spec.variables.join(", "),
+        supplied.join(", "),
+        problems.join("; ")
+    ))
+}
+
+fn new_environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_keep_trailing_newline(true);
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.set_auto_escape_callback(|_| AutoEscape::None);
+    // Every `{{ value }}` is written as a Python literal; `| safe` pastes the text as is
+    env.set_formatter(|out, _state, value| {
+        if value.is_safe() {
+            write!(out, "{}", value)?;
+        } else {
+            out.write_str(&literal(value)?)?;
+        }
+        Ok(())
+    });
+    env.add_filter("python", |value: Value| -> Result<Value, Error> {
+        Ok(Value::from_safe_string(literal(&value)?))
+    });
+    env
+}
+
+fn literal(value: &Value) -> Result<String, Error> {
+    python_literal(value).map_err(|e| Error::new(ErrorKind::BadSerialization, e))
+}
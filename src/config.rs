@@ -0,0 +1,544 @@
+use crate::{
+    credentials::CredentialStrategy,
+    lint::LintConfig,
+    output::{DEFAULT_NOTEBOOK_NAME, DEFAULT_PYTHON_NAME},
+    pipeline::{LoaderMode, DEFAULT_BASE_URL},
+    remap::Remap,
+};
+use serde::Deserialize;
+use std::{collections::BTreeMap, fmt, path::Path};
+
+pub const DEFAULT_CONFIG_FILE: &str = "./semtparser.toml";
+/// Environment variable selecting the profile when `--profile` is not given.
+pub const PROFILE_ENV: &str = "SEMTPARSER_PROFILE";
+
+/// Project configuration read from `semtparser.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub lint: LintConfig,
+    /// Service and dataset ids replaced when generating
+    pub remap: Remap,
+    /// `[profile.<name>]` sections, e.g. `dev`, `staging` and `prod`
+    #[serde(rename = "profile")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// File the configuration was read from, `None` when there is none
+    #[serde(skip)]
+    pub path: Option<String>,
+}
+
+/// Settings of one environment. Unset values fall back to the defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub base_url: Option<String>,
+    /// Dataset the table is uploaded to, instead of the logged one
+    pub dataset_id: Option<String>,
+    /// Dataset looked up by name when the code runs, e.g. `{original}`
+    pub dataset_name: Option<String>,
+    /// Name of the uploaded table, e.g. `{original}-{date}`
+    pub table_name: Option<String>,
+    /// `upload`, or `existing` to use a table already on the backend
+    pub loader: Option<String>,
+    /// Existing table to use, instead of finding it by name
+    pub table_id: Option<String>,
+    /// `python` or `notebook`
+    pub format: Option<String>,
+    pub output_dir: Option<String>,
+    pub name_template: Option<String>,
+    /// Directory of templates overriding the built-in ones
+    pub templates: Option<String>,
+    /// How the generated code gets the credentials, e.g. `keyring`
+    pub credentials: Option<String>,
+    /// Merged over the top-level `[remap]`
+    pub remap: Remap,
+}
+
+/// Where the value of a setting comes from, by decreasing precedence.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    Cli,
+    Env(&'static str),
+    Profile(String),
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Cli => write!(f, "command line"),
+            Source::Env(var) => write!(f, "environment ({})", var),
+            Source::Profile(name) => write!(f, "profile '{}'", name),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A resolved setting. `value` is `None` when it is left to the log or the generator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting {
+    pub value: Option<String>,
+    pub source: Source,
+}
+
+impl Setting {
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+}
+
+/// Settings given on the command line, `None` when not given.
+#[derive(Debug, Clone, Default)]
+pub struct CliSettings {
+    pub profile: Option<String>,
+    pub base_url: Option<String>,
+    pub dataset_id: Option<String>,
+    pub dataset_name: Option<String>,
+    pub table_name: Option<String>,
+    pub loader: Option<String>,
+    pub table_id: Option<String>,
+    pub format: Option<String>,
+    pub output_dir: Option<String>,
+    pub name_template: Option<String>,
+    pub templates: Option<String>,
+    pub credentials: Option<String>,
+}
+
+/// The configuration in effect for a run.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Selected profile, and whether `--profile` or the environment selected it
+    pub profile: Option<(String, Source)>,
+    pub base_url: Setting,
+    pub dataset_id: Setting,
+    pub dataset_name: Setting,
+    pub table_name: Setting,
+    pub loader: Setting,
+    pub table_id: Setting,
+    pub format: Setting,
+    pub output_dir: Setting,
+    pub name_template: Setting,
+    pub templates: Setting,
+    pub credentials: Setting,
+    /// Top-level `[remap]` with the profile's merged over it
+    pub remap: Remap,
+}
+
+impl Settings {
+    /// The settings with their names, in the order `config show` prints them.
+    pub fn entries(&self) -> [(&'static str, &Setting); 11] {
+        [
+            ("base_url", &self.base_url),
+            ("dataset_id", &self.dataset_id),
+            ("dataset_name", &self.dataset_name),
+            ("table_name", &self.table_name),
+            ("loader", &self.loader),
+            ("table_id", &self.table_id),
+            ("format", &self.format),
+            ("output_dir", &self.output_dir),
+            ("name_template", &self.name_template),
+            ("templates", &self.templates),
+            ("credentials", &self.credentials),
+        ]
+    }
+}
+
+impl Config {
+    /// Rejects profiles with settings the command line does not accept together.
+    fn check_profiles(&self) -> Result<(), String> {
+        for (name, profile) in &self.profiles {
+            if profile.dataset_id.is_some() && profile.dataset_name.is_some() {
+                return Err(format!(
+                    "profile '{}' sets both dataset_id and dataset_name; keep one",
+                    name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves every setting: the command line over environment variables over the
+    /// selected profile over the defaults.
+    pub fn settings(&self, cli: &CliSettings) -> Result<Settings, String> {
+        self.resolve_settings(cli, &env_value)
+    }
+
+    /// [`Config::settings`] with the environment read through `env`.
+    fn resolve_settings(
+        &self,
+        cli: &CliSettings,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Settings, String> {
+        if env("SEMTPARSER_DATASET_ID").is_some() && env("SEMTPARSER_DATASET_NAME").is_some() {
+            return Err(
+                "the environment sets both SEMTPARSER_DATASET_ID and SEMTPARSER_DATASET_NAME; \
+                 keep one"
+                    .to_string(),
+            );
+        }
+        let selected = match (&cli.profile, env(PROFILE_ENV)) {
+            (Some(name), _) => Some((name.clone(), Source::Cli)),
+            (None, Some(name)) => Some((name, Source::Env(PROFILE_ENV))),
+            (None, None) => None,
+        };
+        let profile = match &selected {
+            Some((name, _)) => Some((name.as_str(), self.profile(name)?)),
+            None => None,
+        };
+        let resolve = |cli: &Option<String>,
+                       var: &'static str,
+                       field: fn(&Profile) -> &Option<String>,
+                       default: Option<&str>| {
+            if let Some(value) = cli {
+                return Setting {
+                    value: Some(value.clone()),
+                    source: Source::Cli,
+                };
+            }
+            if let Some(value) = env(var) {
+                return Setting {
+                    value: Some(value),
+                    source: Source::Env(var),
+                };
+            }
+            if let Some((name, Some(value))) = profile.map(|(name, p)| (name, field(p))) {
+                return Setting {
+                    value: Some(value.clone()),
+                    source: Source::Profile(name.to_string()),
+                };
+            }
+            Setting {
+                value: default.map(str::to_string),
+                source: Source::Default,
+            }
+        };
+
+        let format = resolve(
+            &cli.format,
+            "SEMTPARSER_FORMAT",
+            |p| &p.format,
+            Some("python"),
+        );
+        if !matches!(format.value(), Some("python" | "notebook")) {
+            return Err(format!(
+                "format '{}' from the {} must be python or notebook",
+                format.value().unwrap_or_default(),
+                format.source
+            ));
+        }
+        let credentials = resolve(
+            &cli.credentials,
+            "SEMTPARSER_CREDENTIALS",
+            |p| &p.credentials,
+            Some(CredentialStrategy::default().as_str()),
+        );
+        if let Err(e) = credentials
+            .value()
+            .unwrap_or_default()
+            .parse::<CredentialStrategy>()
+        {
+            return Err(format!("{} from the {}", e, credentials.source));
+        }
+        let dataset_id = resolve(
+            &cli.dataset_id,
+            "SEMTPARSER_DATASET_ID",
+            |p| &p.dataset_id,
+            None,
+        );
+        let mut dataset_name = resolve(
+            &cli.dataset_name,
+            "SEMTPARSER_DATASET_NAME",
+            |p| &p.dataset_name,
+            None,
+        );
+        // A dataset id given with a higher precedence wins over a dataset name
+        if dataset_id.value.is_some() && dataset_id.source < dataset_name.source {
+            dataset_name = Setting {
+                value: None,
+                source: dataset_id.source.clone(),
+            };
+        }
+        let table_id = resolve(&cli.table_id, "SEMTPARSER_TABLE_ID", |p| &p.table_id, None);
+        let mut loader = resolve(
+            &cli.loader,
+            "SEMTPARSER_LOADER",
+            |p| &p.loader,
+            Some(LoaderMode::default().as_str()),
+        );
+        if let Err(e) = loader.value().unwrap_or_default().parse::<LoaderMode>() {
+            return Err(format!("{} from the {}", e, loader.source));
+        }
+        // A table id implies the existing loader, unless a higher level asks to upload
+        if table_id.value.is_some() && table_id.source <= loader.source {
+            loader = Setting {
+                value: Some(LoaderMode::Existing.as_str().to_string()),
+                source: table_id.source.clone(),
+            };
+        }
+        let mut remap = self.remap.clone();
+        if let Some((_, profile)) = profile {
+            remap.services.extend(profile.remap.services.clone());
+            remap.datasets.extend(profile.remap.datasets.clone());
+        }
+        Ok(Settings {
+            base_url: resolve(
+                &cli.base_url,
+                "BASE_URL",
+                |p| &p.base_url,
+                Some(DEFAULT_BASE_URL),
+            ),
+            dataset_id,
+            dataset_name,
+            table_name: resolve(
+                &cli.table_name,
+                "SEMTPARSER_TABLE_NAME",
+                |p| &p.table_name,
+                None,
+            ),
+            loader,
+            table_id,
+            format,
+            output_dir: resolve(
+                &cli.output_dir,
+                "SEMTPARSER_OUTPUT_DIR",
+                |p| &p.output_dir,
+                None,
+            ),
+            name_template: resolve(
+                &cli.name_template,
+                "SEMTPARSER_NAME_TEMPLATE",
+                |p| &p.name_template,
+                None,
+            ),
+            templates: resolve(
+                &cli.templates,
+                "SEMTPARSER_TEMPLATES",
+                |p| &p.templates,
+                None,
+            ),
+            credentials,
+            remap,
+            profile: selected,
+        })
+    }
+
+    fn profile(&self, name: &str) -> Result<&Profile, String> {
+        self.profiles.get(name).ok_or_else(|| {
+            let file = self.path.as_deref().unwrap_or(DEFAULT_CONFIG_FILE);
+            if self.profiles.is_empty() {
+                format!("no profile '{}': {} defines no profiles", name, file)
+            } else {
+                format!(
+                    "no profile '{}' in {} (available: {})",
+                    name,
+                    file,
+                    self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+                )
+            }
+        })
+    }
+}
+
+/// A set, non-empty environment variable.
+fn env_value(var: &str) -> Option<String> {
+    std::env::var(var).ok().filter(|value| !value.is_empty())
+}
+
+/// Text output of `config show`.
+pub fn format_settings(config: &Config, settings: &Settings) -> String {
+    let mut out = format!(
+        "Configuration file: {}\n",
+        config.path.as_deref().unwrap_or("none")
+    );
+    match &settings.profile {
+        Some((name, source)) => out.push_str(&format!("Profile: {} ({})\n", name, source)),
+        None => out.push_str("Profile: none\n"),
+    }
+    out.push('\n');
+    for (name, setting) in settings.entries() {
+        let value = match (name, setting.value()) {
+            (_, Some(value)) => value,
+            ("dataset_id", None) => "(from the log)",
+            ("dataset_name", None) => "(none, the id is used)",
+            ("table_name", None) => "(the logged name, or test_table-{timestamp})",
+            ("table_id", None) => "(found by name)",
+            ("output_dir", None) => "(current directory)",
+            ("name_template", None) if settings.format.value() == Some("notebook") => {
+                DEFAULT_NOTEBOOK_NAME
+            }
+            ("name_template", None) => DEFAULT_PYTHON_NAME,
+            _ => "(built-in)",
+        };
+        out.push_str(&format!("{:<14} {:<44} {}\n", name, value, setting.source));
+    }
+    if !settings.remap.is_empty() {
+        out.push_str("\nRemapped ids:\n");
+        for (old, new) in &settings.remap.services {
+            out.push_str(&format!("  service {} -> {}\n", old, new));
+        }
+        for (old, new) in &settings.remap.datasets {
+            out.push_str(&format!("  dataset {} -> {}\n", old, new));
+        }
+    }
+    out
+}
+
+/// Loads the configuration file. An explicit path must exist; the default
+/// `./semtparser.toml` is optional.
+pub fn load_config(path: Option<&str>) -> Result<Config, String> {
+    let config_path = path.unwrap_or(DEFAULT_CONFIG_FILE);
+    if path.is_none() && !Path::new(config_path).exists() {
+        return Ok(Config::default());
+    }
+    let content = std::fs::read_to_string(config_path)
+        .map_err(|e| format!("could not read config file '{}': {}", config_path, e))?;
+    let mut config: Config = toml::from_str(&content)
+        .map_err(|e| format!("invalid config file '{}': {}", config_path, e))?;
+    config.path = Some(config_path.to_string());
+    config
+        .check_profiles()
+        .map_err(|e| format!("invalid config file '{}': {}", config_path, e))?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[remap.services]
+wikidataOpenRefine = "wikidataAlligator"
+
+[profile.dev]
+base_url = "http://localhost:3003"
+dataset_name = "{original}"
+table_name = "dev-{timestamp}"
+
+[profile.prod]
+base_url = "https://semt.example.org"
+dataset_id = "7"
+templates = "./prod_templates"
+
+[profile.prod.remap.datasets]
+2 = "7"
+"#;
+
+    /// An empty environment, so the tests do not depend on the caller's shell.
+    fn no_env(_var: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn command_line_overrides_profile_over_defaults() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let cli = CliSettings {
+            profile: Some("prod".to_string()),
+            templates: Some("./mine".to_string()),
+            ..Default::default()
+        };
+        let settings = config.resolve_settings(&cli, &no_env).unwrap();
+        let prod = Source::Profile("prod".to_string());
+        assert_eq!(settings.profile, Some(("prod".to_string(), Source::Cli)));
+        assert_eq!(settings.dataset_id.value(), Some("7"));
+        assert_eq!(settings.dataset_id.source, prod);
+        assert_eq!(settings.dataset_name.value(), None);
+        assert_eq!(settings.templates.value(), Some("./mine"));
+        assert_eq!(settings.templates.source, Source::Cli);
+        assert_eq!(settings.table_name.value(), None);
+        assert_eq!(settings.table_name.source, Source::Default);
+        assert_eq!(settings.format.value(), Some("python"));
+        // The profile's remapping adds to the top-level one
+        assert_eq!(
+            settings.remap.services["wikidataOpenRefine"],
+            "wikidataAlligator"
+        );
+        assert_eq!(settings.remap.datasets["2"], "7");
+
+        // An id on the command line replaces the profile's dataset name
+        let cli_id = CliSettings {
+            profile: Some("dev".to_string()),
+            dataset_id: Some("3".to_string()),
+            ..Default::default()
+        };
+        let settings = config.resolve_settings(&cli_id, &no_env).unwrap();
+        assert_eq!(settings.dataset_id.value(), Some("3"));
+        assert_eq!(settings.dataset_name.value(), None);
+
+        // A table id selects the existing loader
+        let cli_table = CliSettings {
+            table_id: Some("9".to_string()),
+            ..Default::default()
+        };
+        let settings = config.resolve_settings(&cli_table, &no_env).unwrap();
+        assert_eq!(settings.loader.value(), Some("existing"));
+        assert_eq!(settings.loader.source, Source::Cli);
+
+        let missing = CliSettings {
+            profile: Some("staging".to_string()),
+            ..Default::default()
+        };
+        assert!(config
+            .resolve_settings(&missing, &no_env)
+            .unwrap_err()
+            .contains("available: dev, prod"));
+    }
+
+    #[test]
+    fn environment_overrides_profile() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let env = |var: &str| match var {
+            "SEMTPARSER_PROFILE" => Some("prod".to_string()),
+            "SEMTPARSER_DATASET_NAME" => Some("demo".to_string()),
+            _ => None,
+        };
+        let settings = config
+            .resolve_settings(&CliSettings::default(), &env)
+            .unwrap();
+        assert_eq!(
+            settings.profile,
+            Some(("prod".to_string(), Source::Env("SEMTPARSER_PROFILE")))
+        );
+        // The dataset name set in the environment wins over the profile's dataset id
+        assert_eq!(settings.dataset_name.value(), Some("demo"));
+        assert_eq!(
+            settings.dataset_name.source,
+            Source::Env("SEMTPARSER_DATASET_NAME")
+        );
+
+        let both = |var: &str| match var {
+            "SEMTPARSER_DATASET_ID" => Some("3".to_string()),
+            "SEMTPARSER_DATASET_NAME" => Some("demo".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            config
+                .resolve_settings(&CliSettings::default(), &both)
+                .unwrap_err(),
+            "the environment sets both SEMTPARSER_DATASET_ID and SEMTPARSER_DATASET_NAME; keep one"
+        );
+    }
+
+    #[test]
+    fn profiles_with_a_dataset_id_and_name_are_rejected() {
+        let path =
+            std::env::temp_dir().join(format!("semtparser-config-{}.toml", std::process::id()));
+        let both = CONFIG.replace(
+            "dataset_id = \"7\"",
+            "dataset_id = \"7\"\ndataset_name = \"x\"",
+        );
+        std::fs::write(&path, &both).unwrap();
+        let rejected = load_config(path.to_str());
+        std::fs::write(&path, CONFIG).unwrap();
+        let accepted = load_config(path.to_str());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            rejected.unwrap_err(),
+            format!(
+                "invalid config file '{}': profile 'prod' sets both dataset_id and dataset_name; keep one",
+                path.display()
+            )
+        );
+        assert_eq!(accepted.unwrap().profiles.len(), 2);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{generators::Operation, metadata::read_script_metadata};
+use serde_json::Value;
+use std::{collections::BTreeMap, path::Path};
+
+/// Reads the operations embedded in a script or notebook written by semTParser, in the
+/// order `build_pipeline` expects: GET_TABLE, the replayed operations, then SAVE_TABLE.
+pub fn read_generated_file(path: &Path) -> Result<Vec<Operation>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read '{}': {}", path.display(), e))?;
+    let operations = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("py") => operations_from_script(&content),
+        Some("ipynb") => operations_from_notebook(&content),
+        _ => Err("expected a .py script or an .ipynb notebook".to_string()),
+    }
+    .map_err(|e| format!("{}: {}", path.display(), e))?;
+    if operations.is_empty() {
+        return Err(format!(
+            "{}: no semtparser metadata found (was it generated by semTParser?)",
+            path.display()
+        ));
+    }
+    Ok(operations)
+}
+
+/// Operations of the `# semtparser:` lines of a Python script. Operation 0 holds the
+/// logged GET_TABLE and SAVE_TABLE.
+pub fn operations_from_script(script: &str) -> Result<Vec<Operation>, String> {
+    let mut loader = Vec::new();
+    let mut steps = Vec::new();
+    for metadata in read_script_metadata(script)? {
+        if metadata.operation == 0 {
+            loader.push(metadata.to_operation());
+        } else {
+            steps.push(metadata.to_operation());
+        }
+    }
+    Ok(in_pipeline_order(loader, steps))
+}
+
+/// Operations of the `semtparser` cell metadata of a notebook. The setup cells hold the
+/// logged GET_TABLE and SAVE_TABLE; a step's markdown and code cells share its metadata.
+pub fn operations_from_notebook(notebook: &str) -> Result<Vec<Operation>, String> {
+    let notebook: Value =
+        serde_json::from_str(notebook).map_err(|e| format!("invalid notebook: {}", e))?;
+    let cells = notebook["cells"]
+        .as_array()
+        .ok_or_else(|| "invalid notebook: no cells".to_string())?;
+
+    let mut loader = Vec::new();
+    let mut steps: BTreeMap<u64, Operation> = BTreeMap::new();
+    for cell in cells {
+        let metadata = &cell["metadata"]["semtparser"];
+        if metadata["operation_type"] == "SETUP" {
+            if loader.is_empty() {
+                loader = string_maps(&metadata["loader_operations"])?;
+            }
+            continue;
+        }
+        // The default export and the summary and schema cells hold no logged operation
+        let (Some(index), Some(data)) = (
+            metadata["operation_index"].as_u64(),
+            metadata["operation_data"].as_object(),
+        ) else {
+            continue;
+        };
+        if !data.contains_key("OpType") {
+            continue;
+        }
+        let operation = string_map(&metadata["operation_data"])
+            .map_err(|e| format!("operation {}: {}", index, e))?;
+        steps.entry(index).or_insert(operation);
+    }
+    Ok(in_pipeline_order(loader, steps.into_values().collect()))
+}
+
+fn in_pipeline_order(loader: Vec<Operation>, steps: Vec<Operation>) -> Vec<Operation> {
+    let (get_tables, save_tables): (Vec<Operation>, Vec<Operation>) = loader
+        .into_iter()
+        .partition(|op| op.get("OpType").map(|s| s.as_str()) != Some("SAVE_TABLE"));
+    get_tables
+        .into_iter()
+        .chain(steps)
+        .chain(save_tables)
+        .collect()
+}
+
+fn string_maps(value: &Value) -> Result<Vec<Operation>, String> {
+    match value {
+        Value::Null => Ok(Vec::new()),
+        Value::Array(items) => items.iter().map(string_map).collect(),
+        _ => Err("loader_operations is not a list".to_string()),
+    }
+}
+
+fn string_map(value: &Value) -> Result<Operation, String> {
+    value
+        .as_object()
+        .ok_or_else(|| "operation is not an object".to_string())?
+        .iter()
+        .map(|(key, value)| match value.as_str() {
+            Some(s) => Ok((key.clone(), s.to_string())),
+            None => Err(format!("field {} is not a string", key)),
+        })
+        .collect()
+}
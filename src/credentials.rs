@@ -0,0 +1,130 @@
+use std::{fmt, str::FromStr};
+
+/// Names of the credential strategies, as accepted by `--credentials`.
+pub const CREDENTIAL_STRATEGIES: [&str; 5] = ["env", "dotenv", "keyring", "prompt", "embed"];
+/// Parts of the names of environment variables whose values are secrets.
+const SECRET_MARKERS: [&str; 4] = ["PASSWORD", "SECRET", "TOKEN", "API_KEY"];
+/// Shorter values are not looked for, as they would match by chance.
+const MIN_SECRET_LEN: usize = 4;
+
+/// How the generated code gets the username and password of the backend. Command-line
+/// arguments of a generated script (`--username`, `--password`) win over every strategy,
+/// and whatever is still missing is asked for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CredentialStrategy {
+    /// The `USERNAME` and `PASSWORD` environment variables when the code runs
+    #[default]
+    Env,
+    /// The `.env` file next to the script, or in the working directory of a notebook
+    DotEnv,
+    /// The password stored for the base URL in the system keyring, with Python's `keyring`
+    Keyring,
+    /// Always ask
+    Prompt,
+    /// The `USERNAME` and `PASSWORD` of the generation environment, written into the code
+    Embed,
+}
+
+impl CredentialStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CredentialStrategy::Env => "env",
+            CredentialStrategy::DotEnv => "dotenv",
+            CredentialStrategy::Keyring => "keyring",
+            CredentialStrategy::Prompt => "prompt",
+            CredentialStrategy::Embed => "embed",
+        }
+    }
+}
+
+impl FromStr for CredentialStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "env" => Ok(CredentialStrategy::Env),
+            "dotenv" => Ok(CredentialStrategy::DotEnv),
+            "keyring" => Ok(CredentialStrategy::Keyring),
+            "prompt" => Ok(CredentialStrategy::Prompt),
+            "embed" => Ok(CredentialStrategy::Embed),
+            _ => Err(format!(
+                "unknown credential strategy '{}' (expected one of: {})",
+                s,
+                CREDENTIAL_STRATEGIES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CredentialStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Environment variables holding a secret (`PASSWORD`, `*_TOKEN`, ...) whose value appears
+/// in `content`, sorted.
+pub fn leaked_secrets(content: &str) -> Vec<String> {
+    let vars = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    secrets_in(content, vars)
+}
+
+fn secrets_in(content: &str, vars: impl Iterator<Item = (String, String)>) -> Vec<String> {
+    let mut names: Vec<String> = vars
+        .filter(|(name, value)| {
+            let name = name.to_uppercase();
+            SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+                && value.chars().count() >= MIN_SECRET_LEN
+                && appears_in(content, value)
+        })
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+    names
+}
+
+/// `value` is in `content` as written, or escaped as in a JSON (notebook) string.
+fn appears_in(content: &str, value: &str) -> bool {
+    if content.contains(value) {
+        return true;
+    }
+    let escaped = serde_json::to_string(value).unwrap_or_default();
+    content.contains(escaped.trim_matches('"'))
+}
+
+/// Warns when the output written to `path` contains a secret of the environment.
+pub fn warn_about_secrets(path: &str, content: &str) {
+    for name in leaked_secrets(content) {
+        eprintln!(
+            "Warning: {} contains the value of {}; do not commit or share it",
+            path, name
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_values_are_found_in_the_output() {
+        let vars = [
+            ("PASSWORD", "hunter2!"),
+            ("GITHUB_TOKEN", "ghp_abc\"def"),
+            ("USERNAME", "analyst"),
+            ("API_KEY", "abc"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let content = r#"password = "hunter2!"  # analyst, abc, "ghp_abc\"def""#;
+        assert_eq!(
+            secrets_in(content, vars.clone().into_iter()),
+            ["GITHUB_TOKEN", "PASSWORD"]
+        );
+        assert!(secrets_in("password = None", vars.into_iter()).is_empty());
+        assert_eq!(
+            "keyring".parse::<CredentialStrategy>(),
+            Ok(CredentialStrategy::Keyring)
+        );
+    }
+}
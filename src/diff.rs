@@ -0,0 +1,617 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+type Operation = HashMap<String, String>;
+
+/// Fields that identify where and when an operation ran rather than what it did.
+const IGNORED_FIELDS: &[&str] = &["timestamp", "DatasetId", "TableId", "OpType", "ColumnName"];
+
+#[derive(Debug, Serialize)]
+pub struct ParameterChange {
+    pub parameter: String,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum DiffEntry {
+    Unchanged {
+        op_type: String,
+        column: Option<String>,
+    },
+    Added {
+        op_type: String,
+        column: Option<String>,
+        service: Option<String>,
+        right_position: usize,
+    },
+    Removed {
+        op_type: String,
+        column: Option<String>,
+        service: Option<String>,
+        left_position: usize,
+    },
+    Changed {
+        op_type: String,
+        column: Option<String>,
+        changes: Vec<ParameterChange>,
+    },
+    Reordered {
+        op_type: String,
+        column: Option<String>,
+        left_position: usize,
+        right_position: usize,
+        changes: Vec<ParameterChange>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionDiff {
+    pub left: String,
+    pub right: String,
+    pub entries: Vec<DiffEntry>,
+}
+
+impl SessionDiff {
+    pub fn has_differences(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|e| !matches!(e, DiffEntry::Unchanged { .. }))
+    }
+}
+
+fn op_type(operation: &Operation) -> String {
+    operation
+        .get("OpType")
+        .cloned()
+        .unwrap_or_else(|| "UNKNOWN".to_string())
+}
+
+fn column(operation: &Operation) -> Option<String> {
+    operation
+        .get("ColumnName")
+        .filter(|c| !c.is_empty())
+        .cloned()
+}
+
+fn service(operation: &Operation) -> Option<String> {
+    ["Reconciler", "Extender", "Modifier"]
+        .iter()
+        .find_map(|key| operation.get(*key).cloned())
+}
+
+/// Operations that are replayed, keyed by type, column and occurrence of that pair, so that
+/// the second reconciliation of a column is aligned with the second one on the other side.
+fn keyed_operations(operations: &[Operation]) -> Vec<((String, String, usize), &Operation)> {
+    let mut occurrences: HashMap<(String, String), usize> = HashMap::new();
+    operations
+        .iter()
+        .filter(|op| {
+            matches!(
+                op.get("OpType").map(|s| s.as_str()),
+                Some("RECONCILIATION" | "EXTENSION" | "PROPAGATE_TYPE" | "EXPORT" | "MODIFICATION")
+            )
+        })
+        .map(|op| {
+            let pair = (op_type(op), column(op).unwrap_or_default());
+            let occurrence = occurrences.entry(pair.clone()).or_insert(0);
+            *occurrence += 1;
+            ((pair.0, pair.1, *occurrence), op)
+        })
+        .collect()
+}
+
+/// Flattens the comparable parameters of an operation into `path -> value`; AdditionalData
+/// objects are expanded (`AdditionalData.additionalColumns.Country`), so an empty object
+/// contributes nothing, and arrays are compared as a whole.
+fn flatten_parameters(operation: &Operation) -> BTreeMap<String, Value> {
+    fn flatten_value(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+        match value {
+            Value::Object(obj) => {
+                for (key, child) in obj {
+                    flatten_value(&format!("{}.{}", prefix, key), child, out);
+                }
+            }
+            _ => {
+                out.insert(prefix.to_string(), value.clone());
+            }
+        }
+    }
+
+    let mut parameters = BTreeMap::new();
+    for (key, value) in operation {
+        if IGNORED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+        if key == "AdditionalData" {
+            match serde_json::from_str::<Value>(value) {
+                Ok(json) => flatten_value(key, &json, &mut parameters),
+                Err(_) => {
+                    parameters.insert(key.clone(), Value::String(value.clone()));
+                }
+            }
+        } else {
+            parameters.insert(key.clone(), Value::String(value.clone()));
+        }
+    }
+    parameters
+}
+
+fn compare_parameters(left: &Operation, right: &Operation) -> Vec<ParameterChange> {
+    let left_parameters = flatten_parameters(left);
+    let right_parameters = flatten_parameters(right);
+    let mut keys: Vec<&String> = left_parameters
+        .keys()
+        .chain(right_parameters.keys())
+        .collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|key| left_parameters.get(*key) != right_parameters.get(*key))
+        .map(|key| ParameterChange {
+            parameter: key.clone(),
+            left: left_parameters.get(key).cloned(),
+            right: right_parameters.get(key).cloned(),
+        })
+        .collect()
+}
+
+/// Indices (into `pairs`) of the longest subsequence whose right positions increase: the
+/// matched operations that kept their relative order.
+fn longest_ordered_subsequence(pairs: &[(usize, usize)]) -> Vec<usize> {
+    let n = pairs.len();
+    let mut lengths = vec![1; n];
+    let mut previous = vec![None; n];
+    for i in 0..n {
+        for j in 0..i {
+            if pairs[j].1 < pairs[i].1 && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+    let mut best = match (0..n).max_by_key(|&i| lengths[i]) {
+        Some(best) => Some(best),
+        None => return Vec::new(),
+    };
+    let mut result = Vec::new();
+    while let Some(i) = best {
+        result.push(i);
+        best = previous[i];
+    }
+    result.reverse();
+    result
+}
+
+/// Aligns the operations of two processed sessions by type and column and reports what
+/// was added, removed, reordered or run with different parameters.
+pub fn diff_sessions(
+    left_label: &str,
+    left: &[Operation],
+    right_label: &str,
+    right: &[Operation],
+) -> SessionDiff {
+    let left_keyed = keyed_operations(left);
+    let right_keyed = keyed_operations(right);
+
+    // (left position, right position) of operations present on both sides, in left order
+    let matched: Vec<(usize, usize)> = left_keyed
+        .iter()
+        .enumerate()
+        .filter_map(|(li, (key, _))| {
+            right_keyed
+                .iter()
+                .position(|(right_key, _)| right_key == key)
+                .map(|ri| (li, ri))
+        })
+        .collect();
+    let in_order = longest_ordered_subsequence(&matched);
+
+    // Entries follow the right session; each removed operation is placed after the matched
+    // operation that preceded it on the left. Sort key: (right anchor, removed?, left index)
+    let mut keyed_entries: Vec<((i64, usize, usize), DiffEntry)> = Vec::new();
+    for (mi, &(li, ri)) in matched.iter().enumerate() {
+        let (_, left_op) = left_keyed[li];
+        let (_, right_op) = right_keyed[ri];
+        let changes = compare_parameters(left_op, right_op);
+        let entry = if !in_order.contains(&mi) {
+            DiffEntry::Reordered {
+                op_type: op_type(left_op),
+                column: column(left_op),
+                left_position: li + 1,
+                right_position: ri + 1,
+                changes,
+            }
+        } else if changes.is_empty() {
+            DiffEntry::Unchanged {
+                op_type: op_type(left_op),
+                column: column(left_op),
+            }
+        } else {
+            DiffEntry::Changed {
+                op_type: op_type(left_op),
+                column: column(left_op),
+                changes,
+            }
+        };
+        keyed_entries.push(((ri as i64, 0, 0), entry));
+    }
+    for (ri, (_, op)) in right_keyed.iter().enumerate() {
+        if !matched.iter().any(|&(_, r)| r == ri) {
+            keyed_entries.push((
+                (ri as i64, 0, 0),
+                DiffEntry::Added {
+                    op_type: op_type(op),
+                    column: column(op),
+                    service: service(op),
+                    right_position: ri + 1,
+                },
+            ));
+        }
+    }
+    for (li, (_, op)) in left_keyed.iter().enumerate() {
+        if matched.iter().any(|&(l, _)| l == li) {
+            continue;
+        }
+        let anchor = matched
+            .iter()
+            .filter(|&&(l, _)| l < li)
+            .max_by_key(|&&(l, _)| l)
+            .map_or(-1, |&(_, r)| r as i64);
+        keyed_entries.push((
+            (anchor, 1, li),
+            DiffEntry::Removed {
+                op_type: op_type(op),
+                column: column(op),
+                service: service(op),
+                left_position: li + 1,
+            },
+        ));
+    }
+    keyed_entries.sort_by_key(|(key, _)| *key);
+
+    SessionDiff {
+        left: left_label.to_string(),
+        right: right_label.to_string(),
+        entries: keyed_entries.into_iter().map(|(_, entry)| entry).collect(),
+    }
+}
+
+fn describe(op_type: &str, column: &Option<String>) -> String {
+    match column {
+        Some(column) => format!("{} on '{}'", op_type, column),
+        None => op_type.to_string(),
+    }
+}
+
+fn format_value(value: &Option<Value>) -> String {
+    match value {
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+        None => "(none)".to_string(),
+    }
+}
+
+pub fn format_session_diff(diff: &SessionDiff) -> String {
+    let mut output = format!("--- {}\n+++ {}\n", diff.left, diff.right);
+    let (mut added, mut removed, mut changed, mut reordered, mut unchanged) = (0, 0, 0, 0, 0);
+    let push_changes = |output: &mut String, changes: &[ParameterChange]| {
+        for change in changes {
+            output.push_str(&format!(
+                "      {}: {} -> {}\n",
+                change.parameter,
+                format_value(&change.left),
+                format_value(&change.right)
+            ));
+        }
+    };
+    for entry in &diff.entries {
+        match entry {
+            DiffEntry::Unchanged { op_type, column } => {
+                unchanged += 1;
+                output.push_str(&format!("  = {}\n", describe(op_type, column)));
+            }
+            DiffEntry::Added {
+                op_type,
+                column,
+                service,
+                right_position,
+            } => {
+                added += 1;
+                output.push_str(&format!(
+                    "  + {}{} (right #{})\n",
+                    describe(op_type, column),
+                    service
+                        .as_ref()
+                        .map_or(String::new(), |s| format!(" by {}", s)),
+                    right_position
+                ));
+            }
+            DiffEntry::Removed {
+                op_type,
+                column,
+                service,
+                left_position,
+            } => {
+                removed += 1;
+                output.push_str(&format!(
+                    "  - {}{} (left #{})\n",
+                    describe(op_type, column),
+                    service
+                        .as_ref()
+                        .map_or(String::new(), |s| format!(" by {}", s)),
+                    left_position
+                ));
+            }
+            DiffEntry::Changed {
+                op_type,
+                column,
+                changes,
+            } => {
+                changed += 1;
+                output.push_str(&format!("  ~ {}\n", describe(op_type, column)));
+                push_changes(&mut output, changes);
+            }
+            DiffEntry::Reordered {
+                op_type,
+                column,
+                left_position,
+                right_position,
+                changes,
+            } => {
+                reordered += 1;
+                output.push_str(&format!(
+                    "  > {} reordered (left #{}, right #{})\n",
+                    describe(op_type, column),
+                    left_position,
+                    right_position
+                ));
+                push_changes(&mut output, changes);
+            }
+        }
+    }
+    output.push_str(&format!(
+        "{} added, {} removed, {} changed, {} reordered, {} unchanged\n",
+        added, removed, changed, reordered, unchanged
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn operation(op_type: &str, column: &str, fields: &[(&str, &str)]) -> Operation {
+        let mut operation = HashMap::from([
+            ("OpType".to_string(), op_type.to_string()),
+            ("ColumnName".to_string(), column.to_string()),
+            (
+                "timestamp".to_string(),
+                "2025-06-01T10:00:00+00:00".to_string(),
+            ),
+        ]);
+        for (key, value) in fields {
+            operation.insert(key.to_string(), value.to_string());
+        }
+        operation
+    }
+
+    fn reconcile(column: &str, reconciler: &str) -> Operation {
+        operation("RECONCILIATION", column, &[("Reconciler", reconciler)])
+    }
+
+    fn extend(column: &str, properties: &str) -> Operation {
+        operation(
+            "EXTENSION",
+            column,
+            &[
+                ("Extender", "wikidataPropertySPARQL"),
+                ("AdditionalData", properties),
+            ],
+        )
+    }
+
+    /// One line per entry: change, type, column and the changed parameters.
+    fn summary(diff: &SessionDiff) -> Vec<String> {
+        diff.entries
+            .iter()
+            .map(|entry| match entry {
+                DiffEntry::Unchanged { op_type, column } => {
+                    format!("= {}", describe(op_type, column))
+                }
+                DiffEntry::Added {
+                    op_type,
+                    column,
+                    right_position,
+                    ..
+                } => format!("+ {} at {}", describe(op_type, column), right_position),
+                DiffEntry::Removed {
+                    op_type,
+                    column,
+                    left_position,
+                    ..
+                } => format!("- {} at {}", describe(op_type, column), left_position),
+                DiffEntry::Changed {
+                    op_type,
+                    column,
+                    changes,
+                } => format!(
+                    "~ {} {:?}",
+                    describe(op_type, column),
+                    changes.iter().map(|c| &c.parameter).collect::<Vec<_>>()
+                ),
+                DiffEntry::Reordered {
+                    op_type,
+                    column,
+                    left_position,
+                    right_position,
+                    ..
+                } => format!(
+                    "> {} {} -> {}",
+                    describe(op_type, column),
+                    left_position,
+                    right_position
+                ),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn added_removed_and_changed_operations() {
+        let left = [
+            reconcile("City", "wikidataOpenRefine"),
+            reconcile("Country", "wikidataOpenRefine"),
+            extend("City", r#"{"properties":"P17"}"#),
+        ];
+        let right = [
+            reconcile("City", "wikidataAlligator"),
+            extend("City", r#"{"properties":"P17"}"#),
+            operation("EXPORT", "", &[("AdditionalData", r#"{"format":"csv"}"#)]),
+        ];
+        let diff = diff_sessions("left", &left, "right", &right);
+        assert!(diff.has_differences());
+        assert_eq!(
+            summary(&diff),
+            [
+                "~ RECONCILIATION on 'City' [\"Reconciler\"]",
+                "- RECONCILIATION on 'Country' at 2",
+                "= EXTENSION on 'City'",
+                "+ EXPORT at 3",
+            ]
+        );
+        assert!(!diff_sessions("left", &left, "right", &left).has_differences());
+    }
+
+    #[test]
+    fn reordered_operations() {
+        let left = [
+            reconcile("City", "wikidataOpenRefine"),
+            reconcile("Country", "wikidataOpenRefine"),
+            reconcile("Region", "wikidataOpenRefine"),
+        ];
+        let right = [
+            reconcile("Country", "wikidataOpenRefine"),
+            reconcile("Region", "wikidataOpenRefine"),
+            reconcile("City", "wikidataAlligator"),
+        ];
+        let diff = diff_sessions("left", &left, "right", &right);
+        assert_eq!(
+            summary(&diff),
+            [
+                "= RECONCILIATION on 'Country'",
+                "= RECONCILIATION on 'Region'",
+                "> RECONCILIATION on 'City' 1 -> 3",
+            ]
+        );
+        match &diff.entries[2] {
+            DiffEntry::Reordered { changes, .. } => {
+                assert_eq!(changes.len(), 1);
+                assert_eq!(changes[0].left, Some(json!("wikidataOpenRefine")));
+                assert_eq!(changes[0].right, Some(json!("wikidataAlligator")));
+            }
+            entry => panic!("not reordered: {:?}", entry),
+        }
+    }
+
+    #[test]
+    fn repeated_reconciliations_are_aligned_by_occurrence() {
+        let left = [
+            reconcile("City", "wikidataOpenRefine"),
+            extend("City", r#"{"properties":"P17"}"#),
+            reconcile("City", "wikidataOpenRefine"),
+        ];
+        let right = [
+            reconcile("City", "wikidataOpenRefine"),
+            extend("City", r#"{"properties":"P17"}"#),
+            reconcile("City", "geocodingHere"),
+            reconcile("City", "wikidataAlligator"),
+        ];
+        let keys: Vec<(String, String, usize)> = keyed_operations(&right)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("RECONCILIATION".to_string(), "City".to_string(), 1),
+                ("EXTENSION".to_string(), "City".to_string(), 1),
+                ("RECONCILIATION".to_string(), "City".to_string(), 2),
+                ("RECONCILIATION".to_string(), "City".to_string(), 3),
+            ]
+        );
+        // The second reconciliation is compared with the second one, the third is new
+        assert_eq!(
+            summary(&diff_sessions("left", &left, "right", &right)),
+            [
+                "= RECONCILIATION on 'City'",
+                "= EXTENSION on 'City'",
+                "~ RECONCILIATION on 'City' [\"Reconciler\"]",
+                "+ RECONCILIATION on 'City' at 4",
+            ]
+        );
+    }
+
+    #[test]
+    fn operations_that_are_not_replayed_are_not_keyed() {
+        let operations = [
+            operation("GET_TABLE", "", &[]),
+            reconcile("City", "wikidataOpenRefine"),
+            operation("SAVE_TABLE", "", &[]),
+        ];
+        assert_eq!(keyed_operations(&operations).len(), 1);
+    }
+
+    #[test]
+    fn additional_data_is_flattened() {
+        let operation = operation(
+            "RECONCILIATION",
+            "City",
+            &[
+                ("Reconciler", "wikidataOpenRefine"),
+                ("DatasetId", "2"),
+                (
+                    "AdditionalData",
+                    r#"{"additionalColumns":{"Country":"x","Empty":{}},"labels":["a","b"]}"#,
+                ),
+            ],
+        );
+        assert_eq!(
+            flatten_parameters(&operation),
+            BTreeMap::from([
+                (
+                    "AdditionalData.additionalColumns.Country".to_string(),
+                    json!("x")
+                ),
+                ("AdditionalData.labels".to_string(), json!(["a", "b"])),
+                ("Reconciler".to_string(), json!("wikidataOpenRefine")),
+            ])
+        );
+
+        let mut invalid = operation.clone();
+        invalid.insert("AdditionalData".to_string(), "{not json".to_string());
+        assert_eq!(
+            flatten_parameters(&invalid).get("AdditionalData"),
+            Some(&json!("{not json"))
+        );
+
+        let left = [extend("City", r#"{"properties":"P17"}"#)];
+        let right = [extend("City", r#"{"properties":"P17 P1082"}"#)];
+        assert_eq!(
+            summary(&diff_sessions("left", &left, "right", &right)),
+            ["~ EXTENSION on 'City' [\"AdditionalData.properties\"]"]
+        );
+    }
+
+    #[test]
+    fn the_longest_ordered_subsequence_keeps_the_most_matches_in_order() {
+        assert!(longest_ordered_subsequence(&[]).is_empty());
+        assert_eq!(
+            longest_ordered_subsequence(&[(0, 2), (1, 0), (2, 1), (3, 3)]),
+            [1, 2, 3]
+        );
+        assert_eq!(longest_ordered_subsequence(&[(0, 0), (1, 1)]), [0, 1]);
+    }
+}
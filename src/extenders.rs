@@ -0,0 +1,294 @@
+use jsonschema::Validator;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+
+/// Mapping shipped with the binary, from `mappings/extenders.json`.
+pub const DEFAULT_EXTENDER_MAPPINGS: &str = include_str!("../mappings/extenders.json");
+
+/// Where a value is read from in an extension's `AdditionalData`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValueSource {
+    /// JSON pointers tried in order; the first one present is used. A `*` segment stands
+    /// for the first member of an object or array (e.g. `/dates/*/2`).
+    #[serde(deserialize_with = "one_or_many")]
+    pub path: Vec<String>,
+    /// Separator splitting a string value into several properties
+    #[serde(default)]
+    pub split: Option<String>,
+}
+
+/// How the `AdditionalData` of one extender becomes the arguments of `extend_column`.
+/// Fields left out of an extender's entry are taken from the default entry.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtenderMapping {
+    /// Sources of the `properties` list, concatenated in order
+    pub properties: Option<Vec<ValueSource>>,
+    /// `other_params` names and the source of each; missing values are left out
+    pub other_params: Option<BTreeMap<String, ValueSource>>,
+    /// JSON Schema the `AdditionalData` must match
+    pub schema: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MappingFile {
+    #[serde(default)]
+    default: Option<ExtenderMapping>,
+    #[serde(default)]
+    extenders: BTreeMap<String, ExtenderMapping>,
+}
+
+/// Extender mappings: the shipped defaults, optionally overridden by a user file.
+#[derive(Clone)]
+pub struct ExtenderMappings {
+    default: ExtenderMapping,
+    extenders: BTreeMap<String, ExtenderMapping>,
+    validators: BTreeMap<String, Arc<Validator>>,
+    default_validator: Option<Arc<Validator>>,
+}
+
+impl Default for ExtenderMappings {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ExtenderMappings {
+    pub fn builtin() -> Self {
+        Self::parse(DEFAULT_EXTENDER_MAPPINGS, None)
+            .unwrap_or_else(|e| panic!("invalid built-in extender mappings: {}", e))
+    }
+
+    /// The shipped mappings with the entries of `path` replacing those of the same extender
+    /// (and its `default` replacing the default entry).
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            format!(
+                "could not read extender mappings '{}': {}",
+                path.display(),
+                e
+            )
+        })?;
+        Self::parse(&content, Some(Self::builtin()))
+            .map_err(|e| format!("invalid extender mappings '{}': {}", path.display(), e))
+    }
+
+    fn parse(content: &str, base: Option<Self>) -> Result<Self, String> {
+        let file: MappingFile = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let (mut default, mut extenders) = match base {
+            Some(base) => (base.default, base.extenders),
+            None => (ExtenderMapping::default(), BTreeMap::new()),
+        };
+        if let Some(file_default) = file.default {
+            default = file_default;
+        }
+        extenders.extend(file.extenders);
+
+        let default_validator = compile_schema("default", default.schema.as_ref())?;
+        let mut validators = BTreeMap::new();
+        for (extender, mapping) in &extenders {
+            if let Some(validator) = compile_schema(extender, mapping.schema.as_ref())? {
+                validators.insert(extender.clone(), validator);
+            }
+        }
+        Ok(ExtenderMappings {
+            default,
+            extenders,
+            validators,
+            default_validator,
+        })
+    }
+
+    /// Extenders with their own entry.
+    pub fn extenders(&self) -> Vec<&str> {
+        self.extenders.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// The `properties` passed to `extend_column`.
+    pub fn properties(&self, extender: &str, additional_data: &Value) -> Vec<String> {
+        let sources = self
+            .extenders
+            .get(extender)
+            .and_then(|mapping| mapping.properties.as_ref())
+            .or(self.default.properties.as_ref());
+        sources
+            .into_iter()
+            .flatten()
+            .flat_map(|source| source_strings(source, additional_data))
+            .collect()
+    }
+
+    /// The `other_params` passed to `extend_column`.
+    pub fn other_params(&self, extender: &str, additional_data: &Value) -> Map<String, Value> {
+        let sources = self
+            .extenders
+            .get(extender)
+            .and_then(|mapping| mapping.other_params.as_ref())
+            .or(self.default.other_params.as_ref());
+        sources
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, source)| {
+                source_value(source, additional_data).map(|value| (name.clone(), value.clone()))
+            })
+            .collect()
+    }
+
+    /// Where `additional_data` does not match the extender's schema.
+    pub fn validate(&self, extender: &str, additional_data: &Value) -> Vec<String> {
+        let validator = match self.extenders.get(extender) {
+            Some(mapping) if mapping.schema.is_some() => self.validators.get(extender),
+            _ => self.default_validator.as_ref(),
+        };
+        match validator {
+            Some(validator) => validator
+                .iter_errors(additional_data)
+                .map(|error| {
+                    let path = error.instance_path().to_string();
+                    if path.is_empty() {
+                        error.to_string()
+                    } else {
+                        format!("{}: {}", path, error)
+                    }
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Built-in mappings, parsed once for the commands that do not take a mapping file.
+pub fn builtin_mappings() -> &'static ExtenderMappings {
+    static MAPPINGS: OnceLock<ExtenderMappings> = OnceLock::new();
+    MAPPINGS.get_or_init(ExtenderMappings::builtin)
+}
+
+fn compile_schema(name: &str, schema: Option<&Value>) -> Result<Option<Arc<Validator>>, String> {
+    schema
+        .map(|schema| {
+            jsonschema::validator_for(schema)
+                .map(Arc::new)
+                .map_err(|e| format!("invalid schema for {}: {}", name, e))
+        })
+        .transpose()
+}
+
+/// Value of the first path of `source` present in `data`.
+fn source_value<'a>(source: &ValueSource, data: &'a Value) -> Option<&'a Value> {
+    source
+        .path
+        .iter()
+        .find_map(|path| lookup(data, path))
+        .filter(|value| !value.is_null())
+}
+
+/// Strings of a source: the string items of an array, or a string (split if requested).
+fn source_strings(source: &ValueSource, data: &Value) -> Vec<String> {
+    match source_value(source, data) {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str())
+            .map(|s| s.to_string())
+            .collect(),
+        Some(Value::String(s)) => match &source.split {
+            Some(separator) => s
+                .split(separator.as_str())
+                .filter(|part| !part.is_empty())
+                .map(|part| part.to_string())
+                .collect(),
+            None => vec![s.clone()],
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// JSON pointer lookup where a `*` segment selects the first member.
+fn lookup<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = data;
+    for segment in path.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        current = match current {
+            Value::Object(map) if segment == "*" => map.values().next()?,
+            Value::Object(map) => map.get(&segment)?,
+            Value::Array(items) if segment == "*" => items.first()?,
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(path) => vec![path],
+        OneOrMany::Many(paths) => paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn builtin_mappings_read_additional_data() {
+        let mappings = ExtenderMappings::builtin();
+        let sparql = json!({"properties": "P17  P1082", "dates": {"r0": ["a", "b", "Date"]}});
+        assert_eq!(
+            mappings.properties("wikidataPropertySPARQL", &sparql),
+            ["P17", "P1082"]
+        );
+        assert_eq!(
+            mappings.other_params("wikidataPropertySPARQL", &sparql),
+            *json!({"date_column_name": "Date"}).as_object().unwrap()
+        );
+        let unknown = json!({"property": ["P31"], "properties": "P17", "labels": ["en"]});
+        assert_eq!(mappings.properties("other", &unknown), ["P31", "en"]);
+        assert!(mappings.other_params("other", &unknown).is_empty());
+
+        assert!(mappings
+            .validate("wikidataPropertySPARQL", &sparql)
+            .is_empty());
+        let errors = mappings.validate("wikidataPropertySPARQL", &json!({"properties": 17}));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("/properties: "), "{}", errors[0]);
+    }
+
+    #[test]
+    fn user_entries_replace_builtin_ones() {
+        let file = r#"{"extenders": {"reconciledColumnExt": {
+            "properties": [{"path": "/fields"}],
+            "other_params": {"lang": {"path": ["/language", "/lang"]}}
+        }}}"#;
+        let mappings = ExtenderMappings::parse(file, Some(ExtenderMappings::builtin())).unwrap();
+        let data = json!({"fields": ["a"], "lang": "en", "property": ["P1"]});
+        assert_eq!(mappings.properties("reconciledColumnExt", &data), ["a"]);
+        assert_eq!(
+            mappings.other_params("reconciledColumnExt", &data),
+            *json!({"lang": "en"}).as_object().unwrap()
+        );
+        // Without its own schema the entry is checked against the default one
+        assert!(mappings
+            .validate("reconciledColumnExt", &json!({"labels": "en"}))
+            .iter()
+            .any(|error| error.starts_with("/labels")));
+        assert!(mappings.extenders().contains(&"meteoPropertiesOpenMeteo"));
+
+        assert!(
+            ExtenderMappings::parse(r#"{"extenders": {"x": {"schema": {"type": 5}}}}"#, None)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use super::{additional_data, step_params, to_params, Operation, OperationGenerator, OutputFormat};
+use crate::{
+    code_helper::{export_template, get_base_export_operation, Templates},
+    pipeline::PipelineStep,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportParams {
+    pub format: String,
+    pub output_file: String,
+}
+
+pub struct ExportGenerator;
+
+impl OperationGenerator for ExportGenerator {
+    fn op_type(&self) -> &str {
+        "EXPORT"
+    }
+
+    fn parse(&self, operation: &Operation) -> Result<Value, String> {
+        let data = additional_data(operation)?;
+        let format = data
+            .get("format")
+            .and_then(|f| f.as_str())
+            .ok_or_else(|| "no format specified in EXPORT AdditionalData".to_string())?;
+        if export_template(format).is_none() {
+            return Err(format!("unsupported export format: {}", format));
+        }
+        to_params(&ExportParams {
+            format: format.to_string(),
+            output_file: data
+                .get("outputFile")
+                .and_then(|f| f.as_str())
+                .unwrap_or("export_output")
+                .to_string(),
+        })
+    }
+
+    fn describe(&self, step: &PipelineStep) -> String {
+        match step_params::<ExportParams>(step) {
+            Ok(params) if step.generated => {
+                format!("Export as {} (Default)", params.format.to_uppercase())
+            }
+            Ok(params) => format!("Export as {}", params.format.to_uppercase()),
+            Err(e) => e,
+        }
+    }
+
+    fn summary(&self, step: &PipelineStep) -> String {
+        let format = step_params::<ExportParams>(step)
+            .map_or("UNKNOWN".to_string(), |params| params.format.to_uppercase());
+        format!("as **{}**", format)
+    }
+
+    fn in_summary(&self) -> bool {
+        false
+    }
+
+    fn heading(&self, step: &PipelineStep) -> Option<String> {
+        let params: ExportParams = step_params(step).ok()?;
+        step.generated
+            .then(|| format!("Default Export ({})", params.format.to_uppercase()))
+    }
+
+    fn operation_data(&self, step: &PipelineStep) -> Value {
+        if !step.generated {
+            return serde_json::to_value(&step.operation).unwrap_or_default();
+        }
+        serde_json::json!({
+            "description": "Default JSON export",
+            "format": step.params["format"]
+        })
+    }
+
+    fn render(
+        &self,
+        step: &PipelineStep,
+        _format: OutputFormat,
+        templates: &Templates,
+    ) -> Result<String, String> {
+        let params: ExportParams = step_params(step)?;
+        get_base_export_operation(templates, &params.format, &params.output_file)
+    }
+}
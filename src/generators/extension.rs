@@ -0,0 +1,116 @@
+use super::{
+    additional_data, required_field, step_params, to_params, Operation, OperationGenerator,
+    OutputFormat,
+};
+use crate::{
+    code_helper::{get_base_extension_operation, Templates},
+    extenders::ExtenderMappings,
+    pipeline::PipelineStep,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize)]
+pub struct ExtensionParams {
+    pub column_name: String,
+    pub extender: String,
+    /// Read from `AdditionalData` as the extender's mapping describes
+    pub properties: Vec<String>,
+    pub other_params: Map<String, Value>,
+    /// Where `AdditionalData` does not match the extender's schema
+    #[serde(default)]
+    pub schema_errors: Vec<String>,
+}
+
+/// Generates `extend_column` calls, reading their arguments through [`ExtenderMappings`].
+#[derive(Default)]
+pub struct ExtensionGenerator {
+    mappings: Arc<ExtenderMappings>,
+}
+
+impl ExtensionGenerator {
+    pub fn new(mappings: impl Into<Arc<ExtenderMappings>>) -> Self {
+        ExtensionGenerator {
+            mappings: mappings.into(),
+        }
+    }
+}
+
+impl OperationGenerator for ExtensionGenerator {
+    fn op_type(&self) -> &str {
+        "EXTENSION"
+    }
+
+    fn parse(&self, operation: &Operation) -> Result<Value, String> {
+        let data = additional_data(operation)?;
+        let extender = required_field(operation, "Extender")?;
+        to_params(&ExtensionParams {
+            column_name: required_field(operation, "ColumnName")?.clone(),
+            extender: extender.clone(),
+            properties: self.mappings.properties(extender, &data),
+            other_params: self.mappings.other_params(extender, &data),
+            schema_errors: self.mappings.validate(extender, &data),
+        })
+    }
+
+    fn validate(&self, step: &PipelineStep) -> Vec<String> {
+        let params = match step_params::<ExtensionParams>(step) {
+            Ok(params) => params,
+            Err(_) => return Vec::new(),
+        };
+        let mut warnings: Vec<String> = params
+            .schema_errors
+            .iter()
+            .map(|error| {
+                format!(
+                    "extension of column '{}' by {}: AdditionalData {}",
+                    params.column_name, params.extender, error
+                )
+            })
+            .collect();
+        if params.properties.is_empty() && params.other_params.is_empty() {
+            warnings.push(format!(
+                "extension of column '{}' requests no properties",
+                params.column_name
+            ));
+        }
+        warnings
+    }
+
+    fn describe(&self, step: &PipelineStep) -> String {
+        match step_params::<ExtensionParams>(step) {
+            Ok(params) => format!(
+                "Extension for column {} by {}",
+                params.column_name, params.extender
+            ),
+            Err(e) => e,
+        }
+    }
+
+    fn summary(&self, step: &PipelineStep) -> String {
+        match step_params::<ExtensionParams>(step) {
+            Ok(params) => format!(
+                "on column `{}` using **{}** extender",
+                params.column_name, params.extender
+            ),
+            Err(e) => e,
+        }
+    }
+
+    fn render(
+        &self,
+        step: &PipelineStep,
+        _format: OutputFormat,
+        templates: &Templates,
+    ) -> Result<String, String> {
+        let params: ExtensionParams = step_params(step)?;
+        get_base_extension_operation(
+            templates,
+            &params.column_name,
+            &params.properties,
+            &params.other_params,
+            &params.extender,
+        )
+    }
+}
@@ -0,0 +1,379 @@
+mod export;
+mod extension;
+mod modification;
+mod propagation;
+mod reconciliation;
+
+pub use export::{ExportGenerator, ExportParams};
+pub use extension::{ExtensionGenerator, ExtensionParams};
+pub use modification::{ModificationGenerator, ModificationParams};
+pub use propagation::{PropagationGenerator, PropagationParams};
+pub use reconciliation::{ReconciliationGenerator, ReconciliationParams};
+
+use crate::{
+    code_helper::{TemplateSpec, Templates},
+    extenders::ExtenderMappings,
+    operations::parse_json,
+    pipeline::PipelineStep,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc};
+
+pub type Operation = HashMap<String, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Python,
+    Notebook,
+}
+
+/// Turns one logged operation type into generated code.
+///
+/// `parse` resolves the logged fields into the step parameters (a JSON object) once; every
+/// other hook works on the resolved [`PipelineStep`], so all output formats see the same
+/// parameters.
+pub trait OperationGenerator: Send + Sync {
+    /// `OpType` handled by this generator, e.g. `RECONCILIATION`
+    fn op_type(&self) -> &str;
+
+    /// Resolves the parameters of a logged operation. An error skips the operation (it keeps
+    /// its OPERATION_N) and is reported with the reason.
+    fn parse(&self, operation: &Operation) -> Result<Value, String>;
+
+    /// Warnings about resolved parameters that still produce code.
+    fn validate(&self, _step: &PipelineStep) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Heading of the step, e.g. `Reconciliation for column City by wikidataOpenRefine`.
+    fn describe(&self, step: &PipelineStep) -> String;
+
+    /// What follows the operation type in summaries, e.g. ``on column `City` ``.
+    fn summary(&self, step: &PipelineStep) -> String {
+        format!("on column `{}`", step.column.as_deref().unwrap_or("N/A"))
+    }
+
+    /// Whether the step is listed in the operation summary of the script and the notebook.
+    fn in_summary(&self) -> bool {
+        true
+    }
+
+    /// Comment written above the code of the step in the Python script, if any.
+    fn heading(&self, _step: &PipelineStep) -> Option<String> {
+        None
+    }
+
+    /// `operation_data` of the step's notebook cells: the operation as logged.
+    fn operation_data(&self, step: &PipelineStep) -> Value {
+        serde_json::to_value(&step.operation).unwrap_or_default()
+    }
+
+    /// Templates the generator renders besides the built-in ones. They are added by
+    /// [`Templates::for_registry`] and a template directory can override them.
+    fn templates(&self) -> &'static [TemplateSpec] {
+        &[]
+    }
+
+    /// Code of the step for the given output format, usually rendered from one of
+    /// `templates`, the built-in ones or those returned by [`Self::templates`].
+    fn render(
+        &self,
+        step: &PipelineStep,
+        format: OutputFormat,
+        templates: &Templates,
+    ) -> Result<String, String>;
+}
+
+/// Generators by operation type. Registering a generator for a type that already has one
+/// replaces it.
+#[derive(Clone, Default)]
+pub struct GeneratorRegistry {
+    generators: Vec<Arc<dyn OperationGenerator>>,
+    /// Read by the EXTENSION generator and the schema simulation alike
+    extender_mappings: Arc<ExtenderMappings>,
+}
+
+impl GeneratorRegistry {
+    /// A registry without generators.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The generators for the operations semT logs today.
+    pub fn with_builtin_generators() -> Self {
+        let mut registry = Self::new();
+        registry.register(Arc::new(ReconciliationGenerator));
+        registry.register(Arc::new(ExtensionGenerator::new(
+            registry.extender_mappings.clone(),
+        )));
+        registry.register(Arc::new(PropagationGenerator));
+        registry.register(Arc::new(ExportGenerator));
+        registry.register(Arc::new(ModificationGenerator));
+        registry
+    }
+
+    /// Replaces the extender mappings, registering an EXTENSION generator that reads them.
+    pub fn set_extender_mappings(&mut self, mappings: ExtenderMappings) {
+        self.extender_mappings = Arc::new(mappings);
+        self.register(Arc::new(ExtensionGenerator::new(
+            self.extender_mappings.clone(),
+        )));
+    }
+
+    /// The extender mappings, which tell the columns an extension creates.
+    pub fn extender_mappings(&self) -> &ExtenderMappings {
+        &self.extender_mappings
+    }
+
+    pub fn register(&mut self, generator: Arc<dyn OperationGenerator>) {
+        self.generators
+            .retain(|existing| existing.op_type() != generator.op_type());
+        self.generators.push(generator);
+    }
+
+    pub fn get(&self, op_type: &str) -> Option<Arc<dyn OperationGenerator>> {
+        self.generators
+            .iter()
+            .find(|generator| generator.op_type() == op_type)
+            .cloned()
+    }
+
+    pub fn op_types(&self) -> Vec<&str> {
+        self.generators.iter().map(|g| g.op_type()).collect()
+    }
+
+    /// Templates of the registered generators.
+    pub fn templates(&self) -> Vec<&'static TemplateSpec> {
+        self.generators
+            .iter()
+            .flat_map(|g| g.templates().iter())
+            .collect()
+    }
+}
+
+/// Parsed `AdditionalData` of an operation.
+pub fn additional_data(operation: &Operation) -> Result<Value, String> {
+    let additional_data_str = operation
+        .get("AdditionalData")
+        .ok_or_else(|| "no AdditionalData".to_string())?;
+    parse_json(additional_data_str).ok_or_else(|| "AdditionalData is not valid JSON".to_string())
+}
+
+/// A field the operation cannot be replayed without.
+pub fn required_field<'a>(operation: &'a Operation, key: &str) -> Result<&'a String, String> {
+    operation.get(key).ok_or_else(|| format!("no {}", key))
+}
+
+/// Reads the resolved parameters of a step back into the generator's parameter struct.
+pub fn step_params<T: DeserializeOwned>(step: &PipelineStep) -> Result<T, String> {
+    serde_json::from_value(step.params.clone())
+        .map_err(|e| format!("invalid {} parameters: {}", step.op_type, e))
+}
+
+fn to_params<T: serde::Serialize>(params: &T) -> Result<Value, String> {
+    serde_json::to_value(params).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        notebook_helpers::render_notebook, pipeline::build_pipeline, python_helpers::render_python,
+    };
+
+    /// A generator registered outside the built-in ones, with a template of its own.
+    struct DeleteRowsGenerator;
+
+    const DELETE_ROWS_TEMPLATES: &[TemplateSpec] = &[TemplateSpec {
+        name: "delete_rows.py.j2",
+        source: "\ntable_manager.delete_rows(dataset_id, table_id, {{ rows }})\n",
+        variables: &["rows"],
+    }];
+
+    impl OperationGenerator for DeleteRowsGenerator {
+        fn op_type(&self) -> &str {
+            "DELETE_ROWS"
+        }
+
+        fn parse(&self, operation: &Operation) -> Result<Value, String> {
+            additional_data(operation)
+        }
+
+        fn describe(&self, _step: &PipelineStep) -> String {
+            "Delete rows".to_string()
+        }
+
+        fn summary(&self, step: &PipelineStep) -> String {
+            format!("of rows {}", step.params["rows"])
+        }
+
+        fn templates(&self) -> &'static [TemplateSpec] {
+            DELETE_ROWS_TEMPLATES
+        }
+
+        fn render(
+            &self,
+            step: &PipelineStep,
+            _format: OutputFormat,
+            templates: &Templates,
+        ) -> Result<String, String> {
+            templates.render(
+                "delete_rows.py.j2",
+                serde_json::json!({ "rows": step.params["rows"] }),
+            )
+        }
+    }
+
+    fn operation(fields: &[(&str, &str)]) -> Operation {
+        fields
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn operations() -> Vec<Operation> {
+        vec![
+            operation(&[("OpType", "GET_TABLE"), ("DatasetId", "2")]),
+            operation(&[
+                ("OpType", "RECONCILIATION"),
+                ("ColumnName", "City"),
+                ("Reconciler", "wikidataOpenRefine"),
+            ]),
+            operation(&[
+                ("OpType", "DELETE_ROWS"),
+                ("AdditionalData", r#"{"rows":[1,2]}"#),
+            ]),
+            operation(&[("OpType", "UNKNOWN_OP"), ("ColumnName", "City")]),
+            operation(&[
+                ("OpType", "EXPORT"),
+                ("AdditionalData", r#"{"format":"csv"}"#),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn generators_are_looked_up_by_op_type() {
+        let mut registry = GeneratorRegistry::with_builtin_generators();
+        assert_eq!(
+            registry.op_types(),
+            [
+                "RECONCILIATION",
+                "EXTENSION",
+                "PROPAGATE_TYPE",
+                "EXPORT",
+                "MODIFICATION"
+            ]
+        );
+        assert_eq!(registry.get("EXPORT").unwrap().op_type(), "EXPORT");
+        assert!(registry.get("DELETE_ROWS").is_none());
+        assert!(registry.get("export").is_none());
+        assert!(registry.templates().is_empty());
+
+        registry.register(Arc::new(DeleteRowsGenerator));
+        assert_eq!(
+            registry.get("DELETE_ROWS").unwrap().op_type(),
+            "DELETE_ROWS"
+        );
+        assert_eq!(registry.templates()[0].name, "delete_rows.py.j2");
+
+        // Registering a type again replaces its generator
+        registry.register(Arc::new(ExtensionGenerator::new(
+            registry.extender_mappings.clone(),
+        )));
+        assert_eq!(registry.op_types().len(), 6);
+        assert!(GeneratorRegistry::new().get("RECONCILIATION").is_none());
+    }
+
+    #[test]
+    fn unknown_op_types_are_not_replayed() {
+        let registry = GeneratorRegistry::with_builtin_generators();
+        let pipeline = build_pipeline(
+            &operations(),
+            "table.csv",
+            false,
+            &registry,
+            Default::default(),
+        );
+        let op_types: Vec<&str> = pipeline.steps.iter().map(|s| s.op_type.as_str()).collect();
+        assert_eq!(op_types, ["RECONCILIATION", "EXPORT"]);
+        assert_eq!(pipeline.steps[1].number, 2);
+        assert_eq!(pipeline.steps[1].position, 5);
+    }
+
+    #[test]
+    fn generators_render_their_own_templates() {
+        let mut registry = GeneratorRegistry::with_builtin_generators();
+        registry.register(Arc::new(DeleteRowsGenerator));
+        let pipeline = build_pipeline(
+            &operations(),
+            "table.csv",
+            false,
+            &registry,
+            Default::default(),
+        );
+
+        let script =
+            render_python(&pipeline, &Templates::for_registry(&registry).unwrap()).unwrap();
+        assert!(script.contains("    table_manager.delete_rows(dataset_id, table_id, [1, 2])\n"));
+        assert!(script.contains("# - DELETE_ROWS of rows [1,2] at N/A\n"));
+        let error = render_python(&pipeline, &Templates::builtin()).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("unknown template delete_rows.py.j2"));
+
+        let mut templates = Templates::for_registry(&registry).unwrap();
+        assert!(templates.names().contains(&"delete_rows.py.j2"));
+        assert_eq!(
+            templates.register(&DELETE_ROWS_TEMPLATES[0]),
+            Err("template delete_rows.py.j2 is already registered".to_string())
+        );
+
+        // A template directory overrides them like the built-in ones
+        let dir =
+            std::env::temp_dir().join(format!("semtparser-generators-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("delete_rows.py.j2"), "\ndrop_rows({{ rows }})\n").unwrap();
+        let overridden = templates.override_from_dir(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        overridden.unwrap();
+        assert_eq!(templates.overridden(), ["delete_rows.py.j2"]);
+        assert!(render_python(&pipeline, &templates)
+            .unwrap()
+            .contains("    drop_rows([1, 2])\n"));
+    }
+
+    #[test]
+    fn both_summaries_list_the_same_steps() {
+        let mut registry = GeneratorRegistry::with_builtin_generators();
+        registry.register(Arc::new(DeleteRowsGenerator));
+        let pipeline = build_pipeline(
+            &operations(),
+            "table.csv",
+            false,
+            &registry,
+            Default::default(),
+        );
+        let templates = Templates::for_registry(&registry).unwrap();
+
+        // The export is not summarized
+        let script = render_python(&pipeline, &templates).unwrap();
+        assert!(script.contains(
+            "# Total operations: 2\n\
+             # - RECONCILIATION on column `City` using **wikidataOpenRefine** reconciler at N/A\n\
+             # - DELETE_ROWS of rows [1,2] at N/A\n"
+        ));
+        let notebook: Value =
+            serde_json::from_str(&render_notebook(&pipeline, &templates).unwrap()).unwrap();
+        let summary = &notebook["cells"][0]["metadata"]["semtparser"];
+        assert_eq!(summary["total_operations"], 2);
+        assert_eq!(
+            summary["operation_types"],
+            serde_json::json!(["RECONCILIATION", "DELETE_ROWS"])
+        );
+        assert_eq!(
+            notebook["cells"][0]["source"][3],
+            "- **DELETE_ROWS** of rows [1,2] at `N/A`\n"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use super::{
+    additional_data, required_field, step_params, to_params, Operation, OperationGenerator,
+    OutputFormat,
+};
+use crate::{
+    code_helper::{get_base_modification_operation, Templates},
+    pipeline::PipelineStep,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize)]
+pub struct ModificationParams {
+    pub column_name: String,
+    pub modifier: String,
+    /// `AdditionalData` as logged, passed to the modifier as `props`
+    pub props: Value,
+}
+
+pub struct ModificationGenerator;
+
+impl OperationGenerator for ModificationGenerator {
+    fn op_type(&self) -> &str {
+        "MODIFICATION"
+    }
+
+    fn parse(&self, operation: &Operation) -> Result<Value, String> {
+        to_params(&ModificationParams {
+            column_name: required_field(operation, "ColumnName")?.clone(),
+            modifier: required_field(operation, "Modifier")?.clone(),
+            props: additional_data(operation)?,
+        })
+    }
+
+    fn describe(&self, step: &PipelineStep) -> String {
+        match step_params::<ModificationParams>(step) {
+            Ok(params) => format!(
+                "Modification for column {} by {}",
+                params.column_name, params.modifier
+            ),
+            Err(e) => e,
+        }
+    }
+
+    fn summary(&self, step: &PipelineStep) -> String {
+        match step_params::<ModificationParams>(step) {
+            Ok(params) => format!(
+                "on column `{}` using **{}** modifier",
+                params.column_name, params.modifier
+            ),
+            Err(e) => e,
+        }
+    }
+
+    fn render(
+        &self,
+        step: &PipelineStep,
+        _format: OutputFormat,
+        templates: &Templates,
+    ) -> Result<String, String> {
+        let params: ModificationParams = step_params(step)?;
+        get_base_modification_operation(
+            templates,
+            &params.column_name,
+            &params.modifier,
+            &params.props,
+        )
+    }
+}
@@ -0,0 +1,63 @@
+use super::{
+    additional_data, required_field, step_params, to_params, Operation, OperationGenerator,
+    OutputFormat,
+};
+use crate::{
+    code_helper::{get_base_propagation_operation, Templates},
+    pipeline::PipelineStep,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize)]
+pub struct PropagationParams {
+    pub column_name: String,
+    /// Type object sent to `propagate_type`
+    pub type_obj: Value,
+}
+
+pub struct PropagationGenerator;
+
+impl OperationGenerator for PropagationGenerator {
+    fn op_type(&self) -> &str {
+        "PROPAGATE_TYPE"
+    }
+
+    fn parse(&self, operation: &Operation) -> Result<Value, String> {
+        let type_obj = match additional_data(operation)? {
+            type_obj @ Value::Object(_) => type_obj,
+            _ => return Err("additional data for PROPAGATE_TYPE is not an object".to_string()),
+        };
+        to_params(&PropagationParams {
+            column_name: required_field(operation, "ColumnName")?.clone(),
+            type_obj,
+        })
+    }
+
+    fn validate(&self, step: &PipelineStep) -> Vec<String> {
+        match step_params::<PropagationParams>(step) {
+            Ok(params) if params.type_obj.get("id").is_none() => vec![format!(
+                "type propagated on column '{}' has no id",
+                params.column_name
+            )],
+            _ => Vec::new(),
+        }
+    }
+
+    fn describe(&self, step: &PipelineStep) -> String {
+        match step_params::<PropagationParams>(step) {
+            Ok(params) => format!("Propagation for column {}", params.column_name),
+            Err(e) => e,
+        }
+    }
+
+    fn render(
+        &self,
+        step: &PipelineStep,
+        _format: OutputFormat,
+        templates: &Templates,
+    ) -> Result<String, String> {
+        let params: PropagationParams = step_params(step)?;
+        get_base_propagation_operation(templates, &params.column_name, &params.type_obj)
+    }
+}
@@ -0,0 +1,78 @@
+use super::{
+    additional_data, required_field, step_params, to_params, Operation, OperationGenerator,
+    OutputFormat,
+};
+use crate::{
+    code_helper::{get_base_reconciliation_operation, Templates},
+    pipeline::PipelineStep,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize)]
+pub struct ReconciliationParams {
+    pub column_name: String,
+    pub reconciler: String,
+    /// Keys of `additionalColumns`, sent to the reconciler as context
+    pub additional_columns: Vec<String>,
+}
+
+pub struct ReconciliationGenerator;
+
+impl OperationGenerator for ReconciliationGenerator {
+    fn op_type(&self) -> &str {
+        "RECONCILIATION"
+    }
+
+    fn parse(&self, operation: &Operation) -> Result<Value, String> {
+        // additionalColumns is optional: a reconciliation without it uses no extra context
+        let additional_columns = additional_data(operation)
+            .ok()
+            .and_then(|data| {
+                data.get("additionalColumns")
+                    .and_then(|v| v.as_object())
+                    .map(|obj| obj.keys().cloned().collect())
+            })
+            .unwrap_or_default();
+        to_params(&ReconciliationParams {
+            column_name: required_field(operation, "ColumnName")?.clone(),
+            reconciler: required_field(operation, "Reconciler")?.clone(),
+            additional_columns,
+        })
+    }
+
+    fn describe(&self, step: &PipelineStep) -> String {
+        match step_params::<ReconciliationParams>(step) {
+            Ok(params) => format!(
+                "Reconciliation for column {} by {}",
+                params.column_name, params.reconciler
+            ),
+            Err(e) => e,
+        }
+    }
+
+    fn summary(&self, step: &PipelineStep) -> String {
+        match step_params::<ReconciliationParams>(step) {
+            Ok(params) => format!(
+                "on column `{}` using **{}** reconciler",
+                params.column_name, params.reconciler
+            ),
+            Err(e) => e,
+        }
+    }
+
+    fn render(
+        &self,
+        step: &PipelineStep,
+        _format: OutputFormat,
+        templates: &Templates,
+    ) -> Result<String, String> {
+        let params: ReconciliationParams = step_params(step)?;
+        get_base_reconciliation_operation(
+            templates,
+            &params.column_name,
+            &params.additional_columns,
+            &params.reconciler,
+        )
+    }
+}
@@ -0,0 +1,26 @@
+//! Parsing of semT operation logs and generation of Python scripts and notebooks that
+//! replay them.
+//!
+//! Operation types are turned into code by the [`generators::OperationGenerator`]s of a
+//! [`generators::GeneratorRegistry`]; register your own to support new backend operations.
+
+pub mod catalog;
+pub mod code_helper;
+pub mod config;
+pub mod convert;
+pub mod credentials;
+pub mod diff;
+pub mod extenders;
+pub mod generators;
+pub mod lint;
+pub mod metadata;
+pub mod notebook_helpers;
+pub mod operations;
+pub mod output;
+pub mod pipeline;
+pub mod python_helpers;
+pub mod remap;
+pub mod schema;
+pub mod stats;
+pub mod update;
+pub mod validation;
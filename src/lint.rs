@@ -0,0 +1,465 @@
+use crate::{
+    extenders::ExtenderMappings,
+    schema::{simulate_schema, ProblemKind},
+    validation::Severity,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+type Operation = HashMap<String, String>;
+type RuleCheck = fn(&[(usize, &Operation)], &ExtenderMappings) -> Vec<(usize, String)>;
+
+/// A lint rule: `check` returns `(OPERATION_N, message)` for every questionable operation.
+/// The extender mappings tell which columns an extension creates.
+pub struct LintRule {
+    pub id: &'static str,
+    pub default_severity: Severity,
+    pub description: &'static str,
+    check: RuleCheck,
+}
+
+pub const RULES: &[LintRule] = &[
+    LintRule {
+        id: "extend-unreconciled",
+        default_severity: Severity::Warning,
+        description: "Extension of a column that was never reconciled",
+        check: check_extend_unreconciled,
+    },
+    LintRule {
+        id: "reconcile-after-extension",
+        default_severity: Severity::Warning,
+        description:
+            "Column reconciled again after an extension already used its previous reconciliation",
+        check: check_reconcile_after_extension,
+    },
+    LintRule {
+        id: "empty-propagate-type",
+        default_severity: Severity::Error,
+        description: "PROPAGATE_TYPE with an empty or missing type object",
+        check: check_empty_propagate_type,
+    },
+    LintRule {
+        id: "propagate-unreconciled",
+        default_severity: Severity::Error,
+        description: "PROPAGATE_TYPE on a column that is not reconciled",
+        check: check_propagate_unreconciled,
+    },
+    LintRule {
+        id: "export-before-annotation",
+        default_severity: Severity::Warning,
+        description: "EXPORT before any reconciliation, extension, propagation or modification",
+        check: check_export_before_annotation,
+    },
+    LintRule {
+        id: "invalid-additional-data",
+        default_severity: Severity::Error,
+        description: "AdditionalData that is not valid JSON",
+        check: check_invalid_additional_data,
+    },
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Rule ids that are not run
+    pub disable: Vec<String>,
+    /// Per-rule severity overrides
+    pub severity: HashMap<String, Severity>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LintFinding {
+    pub rule: String,
+    pub severity: Severity,
+    pub operation_number: usize,
+    pub op_type: String,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+fn op_type(operation: &Operation) -> &str {
+    operation.get("OpType").map_or("UNKNOWN", |s| s.as_str())
+}
+
+fn column_name(operation: &Operation) -> &str {
+    operation.get("ColumnName").map_or("", |s| s.as_str())
+}
+
+fn check_extend_unreconciled(
+    operations: &[(usize, &Operation)],
+    _mappings: &ExtenderMappings,
+) -> Vec<(usize, String)> {
+    let mut reconciled: Vec<&str> = Vec::new();
+    let mut findings = Vec::new();
+    for (number, operation) in operations {
+        match op_type(operation) {
+            "RECONCILIATION" => reconciled.push(column_name(operation)),
+            "EXTENSION" if !reconciled.contains(&column_name(operation)) => findings.push((
+                *number,
+                format!(
+                    "column '{}' is extended with {} but was never reconciled",
+                    column_name(operation),
+                    operation.get("Extender").map_or("N/A", |s| s.as_str())
+                ),
+            )),
+            _ => {}
+        }
+    }
+    findings
+}
+
+fn check_reconcile_after_extension(
+    operations: &[(usize, &Operation)],
+    _mappings: &ExtenderMappings,
+) -> Vec<(usize, String)> {
+    let mut reconciled: Vec<&str> = Vec::new();
+    let mut extended_since_reconciliation: HashMap<&str, usize> = HashMap::new();
+    let mut findings = Vec::new();
+    for (number, operation) in operations {
+        let column = column_name(operation);
+        match op_type(operation) {
+            "RECONCILIATION" => {
+                reconciled.push(column);
+                if let Some(extension_number) = extended_since_reconciliation.remove(column) {
+                    findings.push((
+                        *number,
+                        format!(
+                            "column '{}' is reconciled again after OPERATION_{} already extended it using the previous reconciliation",
+                            column, extension_number
+                        ),
+                    ));
+                }
+            }
+            "EXTENSION" if reconciled.contains(&column) => {
+                extended_since_reconciliation.insert(column, *number);
+            }
+            _ => {}
+        }
+    }
+    findings
+}
+
+fn check_empty_propagate_type(
+    operations: &[(usize, &Operation)],
+    _mappings: &ExtenderMappings,
+) -> Vec<(usize, String)> {
+    operations
+        .iter()
+        .filter(|(_, operation)| op_type(operation) == "PROPAGATE_TYPE")
+        .filter(|(_, operation)| {
+            operation
+                .get("AdditionalData")
+                .and_then(|s| serde_json::from_str::<serde_json::Value>(s).ok())
+                .and_then(|v| v.as_object().map(|obj| obj.is_empty()))
+                .unwrap_or(true)
+        })
+        .map(|(number, operation)| {
+            (
+                *number,
+                format!(
+                    "type propagated on column '{}' without a type object",
+                    column_name(operation)
+                ),
+            )
+        })
+        .collect()
+}
+
+fn check_propagate_unreconciled(
+    operations: &[(usize, &Operation)],
+    mappings: &ExtenderMappings,
+) -> Vec<(usize, String)> {
+    let owned: Vec<Operation> = operations.iter().map(|(_, op)| (*op).clone()).collect();
+    simulate_schema(&owned, None, mappings)
+        .into_iter()
+        .flat_map(|step| {
+            let number = step.operation_number;
+            step.problems
+                .into_iter()
+                .filter(|p| p.kind == ProblemKind::NotReconciled)
+                .map(move |p| (number, p.message))
+        })
+        .collect()
+}
+
+fn check_export_before_annotation(
+    operations: &[(usize, &Operation)],
+    _mappings: &ExtenderMappings,
+) -> Vec<(usize, String)> {
+    let mut findings = Vec::new();
+    for (number, operation) in operations {
+        match op_type(operation) {
+            "EXPORT" => findings.push((
+                *number,
+                "table exported before any annotation was applied".to_string(),
+            )),
+            _ => break,
+        }
+    }
+    findings
+}
+
+fn check_invalid_additional_data(
+    operations: &[(usize, &Operation)],
+    _mappings: &ExtenderMappings,
+) -> Vec<(usize, String)> {
+    operations
+        .iter()
+        .filter_map(|(number, operation)| {
+            let additional_data = operation.get("AdditionalData")?;
+            if serde_json::from_str::<serde_json::Value>(additional_data).is_ok() {
+                return None;
+            }
+            Some((
+                *number,
+                format!(
+                    "{} on column '{}' has AdditionalData that is not valid JSON",
+                    op_type(operation),
+                    column_name(operation)
+                ),
+            ))
+        })
+        .collect()
+}
+
+pub fn find_rule(id: &str) -> Option<&'static LintRule> {
+    RULES.iter().find(|rule| rule.id == id)
+}
+
+/// Runs every enabled rule over the operations that the generated code replays.
+pub fn lint_operations(
+    operations: &[Operation],
+    config: &LintConfig,
+    mappings: &ExtenderMappings,
+) -> Vec<LintFinding> {
+    let mut displayed_operation_number = 0;
+    let numbered: Vec<(usize, &Operation)> = operations
+        .iter()
+        .filter(|op| {
+            matches!(
+                op_type(op),
+                "RECONCILIATION" | "EXTENSION" | "PROPAGATE_TYPE" | "EXPORT" | "MODIFICATION"
+            )
+        })
+        .map(|op| {
+            displayed_operation_number += 1;
+            (displayed_operation_number, op)
+        })
+        .collect();
+
+    let mut findings = Vec::new();
+    for rule in RULES {
+        if config.disable.iter().any(|id| id == rule.id) {
+            continue;
+        }
+        let severity = config
+            .severity
+            .get(rule.id)
+            .copied()
+            .unwrap_or(rule.default_severity);
+        for (number, message) in (rule.check)(&numbered, mappings) {
+            let operation = numbered
+                .iter()
+                .find(|(n, _)| *n == number)
+                .map(|(_, op)| *op);
+            findings.push(LintFinding {
+                rule: rule.id.to_string(),
+                severity,
+                operation_number: number,
+                op_type: operation.map_or("UNKNOWN", op_type).to_string(),
+                column: operation
+                    .and_then(|op| op.get("ColumnName"))
+                    .filter(|c| !c.is_empty())
+                    .cloned(),
+                message,
+            });
+        }
+    }
+    findings.sort_by_key(|f| f.operation_number);
+    findings
+}
+
+/// Whether the findings make `lint` exit with a non-zero status: any error, or any warning
+/// with `--deny-warnings`.
+pub fn lint_failed(findings: &[LintFinding], deny_warnings: bool) -> bool {
+    findings.iter().any(|f| {
+        f.severity == Severity::Error || (deny_warnings && f.severity == Severity::Warning)
+    })
+}
+
+pub fn format_lint_findings(findings: &[LintFinding], log_file: &str) -> String {
+    let mut output = String::new();
+    for finding in findings {
+        output.push_str(&format!(
+            "{}: OPERATION_{} ({}): {}: {} [{}]\n",
+            log_file,
+            finding.operation_number,
+            finding.op_type,
+            finding.severity,
+            finding.message,
+            finding.rule
+        ));
+    }
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    output.push_str(&format!(
+        "{} error(s), {} warning(s)\n",
+        errors,
+        findings.len() - errors
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extenders::builtin_mappings;
+
+    fn operation(op_type: &str, column: &str, additional_data: Option<&str>) -> Operation {
+        let mut operation = HashMap::from([
+            ("OpType".to_string(), op_type.to_string()),
+            ("ColumnName".to_string(), column.to_string()),
+        ]);
+        if let Some(additional_data) = additional_data {
+            operation.insert("AdditionalData".to_string(), additional_data.to_string());
+        }
+        operation
+    }
+
+    fn reconcile(column: &str) -> Operation {
+        let mut operation = operation("RECONCILIATION", column, None);
+        operation.insert("Reconciler".to_string(), "wikidataOpenRefine".to_string());
+        operation
+    }
+
+    fn extend(column: &str) -> Operation {
+        operation("EXTENSION", column, Some(r#"{"properties":"P17"}"#))
+    }
+
+    fn propagate(column: &str, type_obj: &str) -> Operation {
+        operation("PROPAGATE_TYPE", column, Some(type_obj))
+    }
+
+    fn export() -> Operation {
+        operation("EXPORT", "", Some(r#"{"format":"csv"}"#))
+    }
+
+    /// `(OPERATION_N, severity)` of the findings of `rule` with the default configuration.
+    fn findings_of(rule: &str, operations: &[Operation]) -> Vec<(usize, Severity)> {
+        lint_operations(operations, &LintConfig::default(), builtin_mappings())
+            .into_iter()
+            .filter(|f| f.rule == rule)
+            .map(|f| (f.operation_number, f.severity))
+            .collect()
+    }
+
+    #[test]
+    fn extend_unreconciled() {
+        assert_eq!(
+            findings_of("extend-unreconciled", &[extend("City")]),
+            [(1, Severity::Warning)]
+        );
+        assert!(
+            findings_of("extend-unreconciled", &[reconcile("City"), extend("City")]).is_empty()
+        );
+    }
+
+    #[test]
+    fn reconcile_after_extension() {
+        let operations = [reconcile("City"), extend("City"), reconcile("City")];
+        assert_eq!(
+            findings_of("reconcile-after-extension", &operations),
+            [(3, Severity::Warning)]
+        );
+        let operations = [reconcile("City"), reconcile("City"), extend("City")];
+        assert!(findings_of("reconcile-after-extension", &operations).is_empty());
+    }
+
+    #[test]
+    fn empty_propagate_type() {
+        let operations = [reconcile("City"), propagate("City", "{}")];
+        assert_eq!(
+            findings_of("empty-propagate-type", &operations),
+            [(2, Severity::Error)]
+        );
+        let operations = [reconcile("City"), propagate("City", r#"{"id":"Q515"}"#)];
+        assert!(findings_of("empty-propagate-type", &operations).is_empty());
+    }
+
+    #[test]
+    fn propagate_unreconciled() {
+        let operations = [reconcile("City"), propagate("Country", r#"{"id":"Q6256"}"#)];
+        assert_eq!(
+            findings_of("propagate-unreconciled", &operations),
+            [(2, Severity::Error)]
+        );
+        let operations = [reconcile("City"), propagate("City", r#"{"id":"Q515"}"#)];
+        assert!(findings_of("propagate-unreconciled", &operations).is_empty());
+    }
+
+    #[test]
+    fn export_before_annotation() {
+        assert_eq!(
+            findings_of("export-before-annotation", &[export(), reconcile("City")]),
+            [(1, Severity::Warning)]
+        );
+        assert!(findings_of("export-before-annotation", &[reconcile("City"), export()]).is_empty());
+    }
+
+    #[test]
+    fn invalid_additional_data() {
+        let operations = [operation("RECONCILIATION", "City", Some("{not json"))];
+        let findings = lint_operations(&operations, &LintConfig::default(), builtin_mappings());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "invalid-additional-data");
+        assert_eq!(
+            findings[0].message,
+            "RECONCILIATION on column 'City' has AdditionalData that is not valid JSON"
+        );
+        let operations = [operation("RECONCILIATION", "City", Some(r#"{"a":1}"#))];
+        assert!(findings_of("invalid-additional-data", &operations).is_empty());
+    }
+
+    #[test]
+    fn the_config_overrides_severities_and_disables_rules() {
+        let config: LintConfig = toml::from_str(
+            r#"
+            disable = ["export-before-annotation"]
+            [severity]
+            extend-unreconciled = "error"
+            "#,
+        )
+        .unwrap();
+        let operations = [export(), extend("City")];
+        let findings = lint_operations(&operations, &config, builtin_mappings());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, "extend-unreconciled");
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].column.as_deref(), Some("City"));
+    }
+
+    #[test]
+    fn errors_fail_the_lint_and_warnings_only_when_denied() {
+        let warnings = lint_operations(
+            &[extend("City")],
+            &LintConfig::default(),
+            builtin_mappings(),
+        );
+        assert!(!lint_failed(&warnings, false));
+        assert!(lint_failed(&warnings, true));
+
+        let errors = lint_operations(
+            &[propagate("City", r#"{"id":"Q515"}"#)],
+            &LintConfig::default(),
+            builtin_mappings(),
+        );
+        assert!(lint_failed(&errors, false));
+        assert!(!lint_failed(&[], true));
+        assert_eq!(
+            format_lint_findings(&errors, "logs.txt"),
+            "logs.txt: OPERATION_1 (PROPAGATE_TYPE): error: type propagated on column 'City' which is not reconciled [propagate-unreconciled]\n1 error(s), 0 warning(s)\n"
+        );
+    }
+}
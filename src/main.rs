@@ -1,99 +1,1164 @@
-mod code_helper;
-mod notebook_helpers;
-mod operations;
-mod python_helpers;
-
-use crate::{
-    notebook_helpers::create_notebook,
-    operations::{logs_from_last_get_table, pre_process_operations, process_operations},
-    python_helpers::create_python,
-};
-use clap::Parser;
+use chrono::{DateTime, FixedOffset, Utc};
+use clap::{Parser, Subcommand};
 use dotenv::dotenv;
+use semtparser::{
+    catalog::{format_catalog, ServiceCatalog, ServiceKind},
+    code_helper::Templates,
+    config::{format_settings, load_config, CliSettings, Config, Settings},
+    convert::read_generated_file,
+    credentials::{warn_about_secrets, CredentialStrategy, CREDENTIAL_STRATEGIES},
+    diff::{diff_sessions, format_session_diff},
+    extenders::ExtenderMappings,
+    generators::GeneratorRegistry,
+    lint::{find_rule, format_lint_findings, lint_failed, lint_operations, RULES},
+    notebook_helpers::{create_notebook, render_notebook},
+    operations::{
+        consolidate_sessions, filter_operations_by_time, logs_by_session, logs_for_table_sessions,
+        logs_from_get_table_before, logs_from_last_get_table, parse_cutoff_timestamp,
+        pre_process_operations, process_operations,
+    },
+    output::{check_name_template, write_output, Destination},
+    pipeline::{
+        build_pipeline, check_name_pattern, generation_time, generation_time_of_file, LoaderMode,
+        Pipeline, DEFAULT_BASE_URL, LOADER_MODES,
+    },
+    python_helpers::{create_python, render_python},
+    remap::{parse_id_mapping, Remap},
+    schema::{format_schema_steps, simulate_schema},
+    stats::{collect_stats, format_stats, write_stats_csv, LoggedSession},
+    update::{update_notebook, update_script},
+    validation::{print_validation_report, read_csv_sample, validate_operations, Severity},
+};
+use std::{collections::HashMap, path::PathBuf};
 
 #[derive(Parser)]
 #[command(name = "semTParser")]
 #[command(about = "A tool to parse and process semT logs for table operations.")]
-struct Args {
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a Python script or notebook that replays the session (default)
+    Generate(GenerateArgs),
+    /// Print how the table schema evolves over the session, without touching the backend
+    Schema(SchemaArgs),
+    /// Report questionable workflows in the session; exits with status 1 on errors
+    Lint(LintArgs),
+    /// Compare two sessions from one or two logs; exits with status 1 on differences with --exit-code
+    Diff(DiffArgs),
+    /// Summarise one or more logs: operation counts, services, properties and timings
+    Stats(StatsArgs),
+    /// Show the reconcilers, extenders and modifiers of the service catalog and their parameters
+    Catalog(CatalogArgs),
+    /// Regenerate a script or notebook written by semTParser, e.g. in the other format
+    Convert(ConvertArgs),
+    /// Bring a generated script or notebook up to date with the log, keeping edits made to it;
+    /// exits with status 1 on conflicts
+    Update(UpdateArgs),
+    /// Inspect the configuration of semtparser.toml, the environment and the command line
+    Config(ConfigArgs),
+}
+
+#[derive(clap::Args)]
+struct SessionArgs {
     #[arg(short, long, default_value = "./logs.txt")]
     log_file: String,
 
+    /// Only replay operations logged at or after this timestamp
+    #[arg(long, value_parser = parse_cutoff_timestamp)]
+    from: Option<DateTime<FixedOffset>>,
+
+    /// Reproduce the table as it was at this timestamp
+    #[arg(long, value_parser = parse_cutoff_timestamp)]
+    until: Option<DateTime<FixedOffset>>,
+
+    /// Replay every session on the same DatasetId/TableId as the selected one, in order
+    #[arg(long)]
+    consolidate: bool,
+}
+
+#[derive(clap::Args)]
+struct GenerateArgs {
+    #[command(flatten)]
+    session: SessionArgs,
+
+    #[arg(short, long, default_value = "./table_1.csv")]
+    table_file: String,
+
+    /// Output format (default: python)
+    #[arg(short, long, value_parser = ["python", "notebook"])]
+    format: Option<String>,
+
+    /// Number of CSV rows to sample when validating the operations against the table
+    #[arg(long, default_value_t = 0)]
+    sample_rows: usize,
+
+    /// Abort generation if validation finds errors
+    #[arg(long)]
+    strict: bool,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    destination: DestinationArgs,
+}
+
+/// Options shared by the commands that write a script or notebook.
+#[derive(clap::Args)]
+struct OutputArgs {
+    /// Document the expected table schema after every operation in the generated output
+    #[arg(long)]
+    document_schema: bool,
+
+    /// Directory of `*.py.j2` templates overriding the built-in ones with the same name
+    #[arg(long, value_name = "DIR")]
+    templates: Option<String>,
+
+    /// JSON file of extender mappings overriding the built-in ones (see mappings/extenders.json)
+    #[arg(long, value_name = "FILE")]
+    extenders: Option<String>,
+
+    /// Service catalog (JSON) replacing the built-in one, e.g. exported from the backend
+    #[arg(long, value_name = "FILE")]
+    catalog: Option<String>,
+
+    /// Configuration file with [remap] and [profile.<name>] sections (default:
+    /// ./semtparser.toml if present)
+    #[arg(long)]
+    config: Option<String>,
+
+    /// Profile of the configuration file to use, e.g. dev or prod (default:
+    /// SEMTPARSER_PROFILE if set)
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Backend URL offered by the generated code (default: BASE_URL, the profile's, or the
+    /// built-in one)
+    #[arg(long, value_name = "URL")]
+    base_url: Option<String>,
+
+    /// How the generated code gets the username and password: from the environment, a .env
+    /// file next to it, the system keyring, a prompt, or written into it (default: env)
+    #[arg(long, value_name = "STRATEGY", value_parser = CREDENTIAL_STRATEGIES)]
+    credentials: Option<String>,
+
+    /// Dataset the table is uploaded to (default: the logged one)
+    #[arg(long, value_name = "ID", conflicts_with = "dataset_name")]
+    dataset_id: Option<String>,
+
+    /// Dataset the generated code looks up by name when it runs, instead of using an id;
+    /// {original} is the logged dataset name, {date} and {timestamp} the generation time
+    #[arg(long, value_name = "PATTERN")]
+    dataset_name: Option<String>,
+
+    /// Name of the uploaded table, e.g. {original}-{date} (default: the logged name, or
+    /// test_table-{timestamp})
+    #[arg(long, value_name = "PATTERN")]
+    table_name: Option<String>,
+
+    /// Upload the CSV file as a new table, or use an existing table of the backend found by
+    /// --table-id or by name (default: upload)
+    #[arg(long, value_name = "MODE", value_parser = LOADER_MODES)]
+    loader: Option<String>,
+
+    /// Existing table the generated code uses instead of uploading the CSV file (implies
+    /// --loader existing)
+    #[arg(long, value_name = "ID")]
+    table_id: Option<String>,
+
+    /// Replace a reconciler, extender or modifier id in the generated code (can be repeated)
+    #[arg(long = "map-service", value_name = "OLD=NEW", value_parser = parse_id_mapping)]
+    map_services: Vec<(String, String)>,
+
+    /// Replace a dataset id in the generated code (can be repeated)
+    #[arg(long = "map-dataset", value_name = "OLD=NEW", value_parser = parse_id_mapping)]
+    map_datasets: Vec<(String, String)>,
+
+    /// Time used in the output file and table names instead of now (default: SOURCE_DATE_EPOCH
+    /// if set), for reproducible output
+    #[arg(long, value_parser = parse_cutoff_timestamp)]
+    timestamp: Option<DateTime<FixedOffset>>,
+}
+
+/// Where the commands that create a script or notebook write it.
+#[derive(clap::Args)]
+struct DestinationArgs {
+    /// File to write instead of a timestamped one
+    #[arg(short, long, value_name = "PATH", conflicts_with_all = ["output_dir", "name_template"])]
+    output: Option<String>,
+
+    /// Directory to write the timestamped file in, created if missing (default: .)
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<String>,
+
+    /// Name of the file without extension; {timestamp}, {dataset_id} and {format} are
+    /// replaced (default: base_file_{timestamp} or base_notebook_file_{timestamp})
+    #[arg(long, value_name = "TEMPLATE")]
+    name_template: Option<String>,
+
+    /// Replace the output file if it exists, instead of refusing to write
+    #[arg(long)]
+    force: bool,
+}
+
+#[derive(clap::Args)]
+struct ConvertArgs {
+    /// Script (.py) or notebook (.ipynb) generated by semTParser
+    input: String,
+
+    /// Format to write
+    #[arg(long, value_parser = ["python", "notebook"])]
+    to: String,
+
+    /// Table file the regenerated output uploads
     #[arg(short, long, default_value = "./table_1.csv")]
     table_file: String,
 
-    #[arg(short, long, default_value = "python", value_parser = ["python", "notebook"])]
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    destination: DestinationArgs,
+}
+
+#[derive(clap::Args)]
+struct UpdateArgs {
+    /// Script (.py) or notebook (.ipynb) generated by semTParser, updated in place
+    file: String,
+
+    #[command(flatten)]
+    session: SessionArgs,
+
+    /// Table file the output uploads, as when the file was generated
+    #[arg(short, long, default_value = "./table_1.csv")]
+    table_file: String,
+
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(clap::Args)]
+struct ConfigArgs {
+    #[command(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the effective configuration and where each value comes from
+    Show(ConfigShowArgs),
+}
+
+#[derive(clap::Args)]
+struct ConfigShowArgs {
+    /// Output format whose settings to show
+    #[arg(short, long, value_parser = ["python", "notebook"])]
+    format: Option<String>,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    destination: DestinationArgs,
+}
+
+#[derive(clap::Args)]
+struct SchemaArgs {
+    #[command(flatten)]
+    session: SessionArgs,
+
+    /// CSV table providing the initial columns; without it columns are discovered from the log
+    #[arg(short, long)]
+    table_file: Option<String>,
+
+    /// JSON file of extender mappings overriding the built-in ones (see mappings/extenders.json)
+    #[arg(long, value_name = "FILE")]
+    extenders: Option<String>,
+
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
     format: String,
 }
 
-fn main() {
-    let args = Args::parse();
+#[derive(clap::Args)]
+struct LintArgs {
+    #[command(flatten)]
+    session: SessionArgs,
 
-    dotenv().ok();
-    match logs_from_last_get_table(&args.log_file) {
+    /// Configuration file with a [lint] section (default: ./semtparser.toml if present)
+    #[arg(long)]
+    config: Option<String>,
+
+    /// Disable a rule by id (can be repeated)
+    #[arg(long = "disable", value_name = "RULE")]
+    disabled_rules: Vec<String>,
+
+    /// Also exit with status 1 when only warnings are found
+    #[arg(long)]
+    deny_warnings: bool,
+
+    /// List the available rules and exit
+    #[arg(long)]
+    list_rules: bool,
+
+    /// JSON file of extender mappings overriding the built-in ones (see mappings/extenders.json)
+    #[arg(long, value_name = "FILE")]
+    extenders: Option<String>,
+
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
+    format: String,
+}
+
+#[derive(clap::Args)]
+struct DiffArgs {
+    /// Log containing the left (old) session
+    left_log: String,
+
+    /// Log containing the right (new) session; defaults to the left log
+    right_log: Option<String>,
+
+    /// 1-based session of the left log (default: the last one, or the one before it when
+    /// both sessions come from the same log)
+    #[arg(long, value_name = "N")]
+    left_session: Option<usize>,
+
+    /// 1-based session of the right log (default: the last one)
+    #[arg(long, value_name = "N")]
+    right_session: Option<usize>,
+
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
+    format: String,
+
+    /// Exit with status 1 when the sessions differ
+    #[arg(long)]
+    exit_code: bool,
+}
+
+#[derive(clap::Args)]
+struct StatsArgs {
+    /// Log files to summarise; every session of each log is counted
+    #[arg(default_value = "./logs.txt")]
+    log_files: Vec<String>,
+
+    /// Only count operations logged at or after this timestamp
+    #[arg(long, value_parser = parse_cutoff_timestamp)]
+    from: Option<DateTime<FixedOffset>>,
+
+    /// Only count operations logged at or before this timestamp
+    #[arg(long, value_parser = parse_cutoff_timestamp)]
+    until: Option<DateTime<FixedOffset>>,
+
+    /// Number of Wikidata properties to list
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json", "csv"])]
+    format: String,
+}
+
+#[derive(clap::Args)]
+struct CatalogArgs {
+    /// Only show the service with this id
+    service: Option<String>,
+
+    /// Service catalog (JSON) replacing the built-in one
+    #[arg(long, value_name = "FILE")]
+    catalog: Option<String>,
+
+    #[arg(short, long, default_value = "text", value_parser = ["text", "json"])]
+    format: String,
+}
+
+/// Loads the built-in service catalog, or the one given with `--catalog`.
+fn load_catalog(path: Option<&str>) -> Result<ServiceCatalog, String> {
+    match path {
+        Some(path) => ServiceCatalog::from_file(std::path::Path::new(path)),
+        None => Ok(ServiceCatalog::builtin()),
+    }
+}
+
+/// Loads the processed operations of the selected session. Returns `Ok(None)` when the log
+/// has no GET_TABLE entry.
+fn load_session(args: &SessionArgs) -> Result<Option<Vec<HashMap<String, String>>>, String> {
+    if let (Some(from), Some(until)) = (args.from, args.until) {
+        if from > until {
+            return Err(format!("--from ({}) is after --until ({})", from, until));
+        }
+    }
+    if args.consolidate {
+        let sessions = match logs_for_table_sessions(&args.log_file, args.until) {
+            Ok(Some(sessions)) => sessions,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        };
+        eprintln!("Found {} sessions on the same table", sessions.len());
+        let mut preprocessed = Vec::new();
+        for lines in sessions {
+            let operations = pre_process_operations(lines)
+                .map_err(|e| format!("Error processing operations: {}", e))?;
+            // Cut each session before merging so later saves outside the window are ignored
+            preprocessed.push(filter_operations_by_time(operations, args.from, args.until));
+        }
+        return Ok(Some(process_operations(consolidate_sessions(preprocessed))));
+    }
+    let (session_lines, start) = match args.until {
+        Some(until) => (
+            logs_from_get_table_before(&args.log_file, until),
+            format!(
+                "the last GET_TABLE entry at or before {}",
+                until.to_rfc3339()
+            ),
+        ),
+        None => (
+            logs_from_last_get_table(&args.log_file),
+            "the last GET_TABLE entry".to_string(),
+        ),
+    };
+    match session_lines {
         Ok(Some(results)) => {
-            println!(
-                "Found {} lines after the last GET_TABLE entry:",
-                results.len()
-            );
+            eprintln!("Found {} lines after {}:", results.len(), start);
             // Process the results
-            match pre_process_operations(results) {
-                Ok(operations) => {
-                    let processed_operations = process_operations(operations);
-                    match args.format.as_str() {
-                        "python" => {
-                            let python_args = python_helpers::Args {
-                                table_file: args.table_file.clone(),
-                            };
-                            match create_python(processed_operations, python_args) {
-                                Ok(file_path) => println!("Python file created at: {}", file_path),
-                                Err(e) => eprintln!("Error creating Python file: {}", e),
-                            }
-                        }
-                        "notebook" => {
-                            let notebook_args = notebook_helpers::Args {
-                                table_file: args.table_file.clone(),
-                            };
-                            match create_notebook(processed_operations, notebook_args) {
-                                Ok(file_path) => {
-                                    println!("Notebook file created at: {}", file_path)
-                                }
-                                Err(e) => eprintln!("Error creating notebook file: {}", e),
-                            }
-                        }
-                        _ => eprintln!("Unknown format specified: {}", args.format),
-                    }
-                }
-                Err(e) => eprintln!("Error processing operations: {}", e),
+            let operations = pre_process_operations(results)
+                .map_err(|e| format!("Error processing operations: {}", e))?;
+            // Cut before deduplicating so replaced operations reappear if they were
+            // still current at the cut-off
+            let operations = filter_operations_by_time(operations, args.from, args.until);
+            Ok(Some(process_operations(operations)))
+        }
+        Ok(None) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Everything needed to render a pipeline, loaded from the [`OutputArgs`].
+struct Renderer {
+    templates: Templates,
+    registry: GeneratorRegistry,
+    catalog: ServiceCatalog,
+    remap: Remap,
+    generated_at: DateTime<Utc>,
+    document_schema: bool,
+    base_url: String,
+    credentials: CredentialStrategy,
+    /// Dataset replacing the logged one
+    dataset_id: Option<String>,
+    /// Pattern of the dataset name looked up at runtime
+    dataset_name: Option<String>,
+    /// Pattern of the table name, `None` to keep the logged one
+    table_name: Option<String>,
+    loader: LoaderMode,
+    table_id: Option<String>,
+}
+
+/// Reads the configuration file and resolves the settings of the options, the environment
+/// and the selected profile. Exits with status 2 when the configuration is invalid.
+fn load_settings(
+    output: &OutputArgs,
+    destination: Option<&DestinationArgs>,
+    format: Option<String>,
+) -> (Config, Settings) {
+    let cli = CliSettings {
+        profile: output.profile.clone(),
+        base_url: output.base_url.clone(),
+        dataset_id: output.dataset_id.clone(),
+        dataset_name: output.dataset_name.clone(),
+        table_name: output.table_name.clone(),
+        loader: output.loader.clone(),
+        table_id: output.table_id.clone(),
+        format,
+        output_dir: destination.and_then(|d| d.output_dir.clone()),
+        name_template: destination.and_then(|d| d.name_template.clone()),
+        templates: output.templates.clone(),
+        credentials: output.credentials.clone(),
+    };
+    let settings = load_config(output.config.as_deref())
+        .and_then(|config| config.settings(&cli).map(|settings| (config, settings)));
+    match settings {
+        Ok((config, mut settings)) => {
+            // Command-line mappings come last, over the file's
+            settings.remap.services.extend(output.map_services.clone());
+            settings.remap.datasets.extend(output.map_datasets.clone());
+            (config, settings)
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// The built-in extender mappings with those of `file` over them. Exits with status 2 when
+/// the file cannot be loaded.
+fn load_extender_mappings(file: &str) -> ExtenderMappings {
+    match ExtenderMappings::from_file(std::path::Path::new(file)) {
+        Ok(mappings) => {
+            // On stderr, so that the JSON of schema and lint stays parseable
+            eprintln!(
+                "Using extender mappings from {} ({})",
+                file,
+                mappings.extenders().join(", ")
+            );
+            mappings
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Loads the templates, extender mappings, catalog and remapping of the output options.
+/// Exits with status 2 when one of them cannot be loaded.
+fn prepare_renderer(args: OutputArgs, settings: &Settings) -> Renderer {
+    let mut registry = GeneratorRegistry::with_builtin_generators();
+    if let Some(file) = &args.extenders {
+        registry.set_extender_mappings(load_extender_mappings(file));
+    }
+    let templates = Templates::for_registry(&registry).and_then(|mut templates| {
+        if let Some(dir) = settings.templates.value() {
+            templates.override_from_dir(std::path::Path::new(dir))?;
+            for name in templates.overridden() {
+                println!("Using template {} from {}", name, dir);
             }
         }
+        Ok(templates)
+    });
+    let templates = match templates {
+        Ok(templates) => templates,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let catalog = match load_catalog(args.catalog.as_deref()) {
+        Ok(catalog) => catalog,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let generated_at = match generation_time(args.timestamp) {
+        Ok(generated_at) => generated_at,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let patterns = [settings.dataset_name.value(), settings.table_name.value()];
+    if let Err(e) = patterns
+        .into_iter()
+        .flatten()
+        .try_for_each(check_name_pattern)
+    {
+        eprintln!("Error: {}", e);
+        std::process::exit(2);
+    }
+    Renderer {
+        templates,
+        registry,
+        catalog,
+        remap: settings.remap.clone(),
+        generated_at,
+        document_schema: args.document_schema,
+        base_url: settings
+            .base_url
+            .value()
+            .unwrap_or(DEFAULT_BASE_URL)
+            .to_string(),
+        // Checked when the settings were resolved
+        credentials: settings
+            .credentials
+            .value()
+            .and_then(|strategy| strategy.parse().ok())
+            .unwrap_or_default(),
+        dataset_id: settings.dataset_id.value.clone(),
+        dataset_name: settings.dataset_name.value.clone(),
+        table_name: settings.table_name.value.clone(),
+        // Checked when the settings were resolved
+        loader: settings
+            .loader
+            .value()
+            .and_then(|mode| mode.parse().ok())
+            .unwrap_or_default(),
+        table_id: settings.table_id.value.clone(),
+    }
+}
+
+/// Applies the configured backend URL, dataset, table name and loader to a pipeline.
+fn apply_settings(renderer: &Renderer, pipeline: &mut Pipeline) {
+    pipeline.base_url = renderer.base_url.clone();
+    pipeline.credentials = renderer.credentials;
+    if let Some(dataset_id) = &renderer.dataset_id {
+        pipeline.loader.dataset_id = dataset_id.clone();
+    }
+    pipeline.loader.mode = renderer.loader;
+    pipeline.loader.table_id = renderer.table_id.clone();
+    // The patterns were checked when the renderer was prepared; {original} can still be
+    // missing from the log
+    let named = renderer
+        .table_name
+        .as_deref()
+        .map_or(Ok(()), |pattern| pipeline.set_table_name(pattern))
+        .and_then(|()| {
+            renderer
+                .dataset_name
+                .as_deref()
+                .map_or(Ok(()), |pattern| pipeline.set_dataset_name(pattern))
+        });
+    if let Err(e) = named {
+        eprintln!("Error: {}", e);
+        std::process::exit(2);
+    }
+}
+
+/// The [`Destination`] of the options and settings. Exits with status 2 on an invalid
+/// name template.
+fn prepare_destination(args: DestinationArgs, settings: &Settings) -> Destination {
+    if let Some(template) = settings.name_template.value() {
+        if let Err(e) = check_name_template(template) {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    }
+    Destination {
+        path: args.output.map(PathBuf::from),
+        dir: settings.output_dir.value.clone().map(PathBuf::from),
+        name_template: settings.name_template.value.clone(),
+        overwrite: args.force,
+    }
+}
+
+/// Builds the pipeline of the operations, remaps its ids and checks its services against
+/// the catalog, reporting both.
+fn resolve_pipeline(
+    renderer: &Renderer,
+    operations: &[HashMap<String, String>],
+    table_file: &str,
+) -> Pipeline {
+    let mut pipeline = build_pipeline(
+        operations,
+        table_file,
+        renderer.document_schema,
+        &renderer.registry,
+        renderer.generated_at,
+    );
+    let remap = &renderer.remap;
+    if !remap.is_empty() {
+        let substitutions = remap.apply(&mut pipeline);
+        println!("Remapped {} id(s):", substitutions.len());
+        for substitution in &substitutions {
+            println!("  {}", substitution);
+        }
+        for mapping in remap.unused(&substitutions) {
+            eprintln!("Warning: {} matched no id in the session", mapping);
+        }
+    }
+    apply_settings(renderer, &mut pipeline);
+    for step in &pipeline.steps {
+        for issue in renderer.catalog.check_step(step) {
+            eprintln!("Warning: OPERATION_{}: {}", step.number, issue);
+        }
+    }
+    renderer.catalog.describe_pipeline(&mut pipeline);
+    pipeline
+}
+
+/// Builds the pipeline of the operations and writes it as a script or notebook. Exits with
+/// status 1 when the output cannot be written.
+fn render(
+    renderer: &Renderer,
+    operations: &[HashMap<String, String>],
+    table_file: &str,
+    format: &str,
+    destination: &Destination,
+) {
+    let pipeline = resolve_pipeline(renderer, operations, table_file);
+    match format {
+        "python" => match create_python(&pipeline, &renderer.templates, destination) {
+            Ok(file_path) => println!("Python file created at: {}", file_path),
+            Err(e) => {
+                eprintln!("Error creating Python file: {}", e);
+                std::process::exit(1);
+            }
+        },
+        "notebook" => match create_notebook(&pipeline, &renderer.templates, destination) {
+            Ok(file_path) => {
+                println!("Notebook file created at: {}", file_path)
+            }
+            Err(e) => {
+                eprintln!("Error creating notebook file: {}", e);
+                std::process::exit(1);
+            }
+        },
+        _ => eprintln!("Unknown format specified: {}", format),
+    }
+}
+
+fn run_generate(args: GenerateArgs) {
+    let (_, settings) = load_settings(&args.output, Some(&args.destination), args.format);
+    let renderer = prepare_renderer(args.output, &settings);
+    let destination = prepare_destination(args.destination, &settings);
+    let format = settings.format.value().unwrap_or("python");
+    let processed_operations = match load_session(&args.session) {
+        Ok(Some(operations)) => operations,
         Ok(None) => {
             println!("No GET_TABLE entry found. Creating base file with no operations.");
             // Create file with empty operations
-            let empty_operations = Vec::new();
-            match args.format.as_str() {
-                "python" => {
-                    let python_args = python_helpers::Args {
-                        table_file: args.table_file.clone(),
-                    };
-                    match create_python(empty_operations, python_args) {
-                        Ok(file_path) => println!("Python file created at: {}", file_path),
-                        Err(e) => eprintln!("Error creating Python file: {}", e),
-                    }
+            Vec::new()
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+
+    if !processed_operations.is_empty() && std::path::Path::new(&args.table_file).exists() {
+        match validate_operations(
+            &processed_operations,
+            &args.table_file,
+            args.sample_rows,
+            renderer.registry.extender_mappings(),
+        ) {
+            Ok(issues) => {
+                print_validation_report(&issues);
+                if args.strict && issues.iter().any(|i| i.severity == Severity::Error) {
+                    eprintln!("Aborting generation because of validation errors (--strict)");
+                    return;
                 }
-                "notebook" => {
-                    let notebook_args = notebook_helpers::Args {
-                        table_file: args.table_file.clone(),
-                    };
-                    match create_notebook(empty_operations, notebook_args) {
-                        Ok(file_path) => {
-                            println!("Notebook file created at: {}", file_path)
-                        }
-                        Err(e) => eprintln!("Error creating notebook file: {}", e),
-                    }
+            }
+            Err(e) => eprintln!("Could not validate against table file: {}", e),
+        }
+    }
+
+    render(
+        &renderer,
+        &processed_operations,
+        &args.table_file,
+        format,
+        &destination,
+    );
+}
+
+fn run_convert(args: ConvertArgs) {
+    let (_, settings) = load_settings(&args.output, Some(&args.destination), None);
+    let renderer = prepare_renderer(args.output, &settings);
+    let destination = prepare_destination(args.destination, &settings);
+    let operations = match read_generated_file(std::path::Path::new(&args.input)) {
+        Ok(operations) => operations,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let replayed = operations
+        .iter()
+        .filter(|op| {
+            !matches!(
+                op.get("OpType").map(|s| s.as_str()),
+                Some("GET_TABLE" | "SAVE_TABLE")
+            )
+        })
+        .count();
+    println!("Read {} operation(s) from {}", replayed, args.input);
+    if !operations
+        .iter()
+        .any(|op| op.get("OpType").is_some_and(|t| t == "SAVE_TABLE"))
+    {
+        eprintln!(
+            "Warning: {} records no SAVE_TABLE, so no deleted columns will be dropped",
+            args.input
+        );
+    }
+    render(
+        &renderer,
+        &operations,
+        &args.table_file,
+        &args.to,
+        &destination,
+    );
+}
+
+fn run_update(args: UpdateArgs) {
+    let path = std::path::Path::new(&args.file);
+    let is_notebook = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("py") => false,
+        Some("ipynb") => true,
+        _ => {
+            eprintln!(
+                "Error: {}: expected a .py script or an .ipynb notebook",
+                args.file
+            );
+            std::process::exit(2);
+        }
+    };
+    let loaded = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read '{}': {}", args.file, e))
+        .and_then(|existing| read_generated_file(path).map(|operations| (existing, operations)));
+    let (existing, embedded) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let explicit_timestamp = args.output.timestamp.is_some();
+    let (_, settings) = load_settings(&args.output, None, None);
+    let mut renderer = prepare_renderer(args.output, &settings);
+    // Rendered as when the file was generated: at the time in its name, with the schema
+    // comments if it has them
+    if !explicit_timestamp {
+        if let Some(generated_at) = generation_time_of_file(path) {
+            renderer.generated_at = generated_at;
+        }
+    }
+    renderer.document_schema |= existing.contains("EXPECTED SCHEMA AFTER OPERATION_")
+        || existing.contains("\"expected_schema\"");
+    let operations = match load_session(&args.session) {
+        Ok(Some(operations)) => operations,
+        Ok(None) => {
+            eprintln!("No GET_TABLE entry found in {}", args.session.log_file);
+            std::process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // The embedded operations, loader ones included, already carry the remapped ids, so
+    // only the log is remapped
+    let mut previous = build_pipeline(
+        &embedded,
+        &args.table_file,
+        renderer.document_schema,
+        &renderer.registry,
+        renderer.generated_at,
+    );
+    apply_settings(&renderer, &mut previous);
+    renderer.catalog.describe_pipeline(&mut previous);
+    let current = resolve_pipeline(&renderer, &operations, &args.table_file);
+    let update = if is_notebook {
+        render_notebook(&previous, &renderer.templates)
+            .and_then(|before| {
+                render_notebook(&current, &renderer.templates).map(|after| (before, after))
+            })
+            .map_err(|e| format!("could not render the notebook: {}", e))
+            .and_then(|(before, after)| update_notebook(&existing, &before, &after))
+    } else {
+        render_python(&previous, &renderer.templates)
+            .and_then(|before| {
+                render_python(&current, &renderer.templates).map(|after| (before, after))
+            })
+            .map_err(|e| format!("could not render the script: {}", e))
+            .and_then(|(before, after)| update_script(&existing, &before, &after))
+    };
+    let update = match update {
+        Ok(update) => update,
+        Err(e) => {
+            eprintln!("Error: {}: {}", args.file, e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(content) = &update.content {
+        if let Err(e) = write_output(path, content.as_bytes(), true) {
+            eprintln!("Error writing {}: {}", args.file, e);
+            std::process::exit(1);
+        }
+        warn_about_secrets(&args.file, content);
+        println!("Updated {}:", args.file);
+    } else {
+        println!("{} is up to date", args.file);
+    }
+    for change in &update.changes {
+        println!("  {}", change);
+    }
+    for conflict in &update.conflicts {
+        eprintln!("Conflict: {}", conflict);
+    }
+    if !update.conflicts.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn run_config(args: ConfigArgs) {
+    match args.action {
+        ConfigAction::Show(args) => {
+            let (config, settings) =
+                load_settings(&args.output, Some(&args.destination), args.format);
+            print!("{}", format_settings(&config, &settings));
+        }
+    }
+}
+
+fn run_schema(args: SchemaArgs) {
+    let operations = match load_session(&args.session) {
+        Ok(Some(operations)) => operations,
+        Ok(None) => {
+            eprintln!("No GET_TABLE entry found in {}", args.session.log_file);
+            return;
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    let header = match &args.table_file {
+        Some(table_file) => match read_csv_sample(table_file, 0) {
+            Ok((header, _)) => Some(header),
+            Err(e) => {
+                eprintln!("Could not read table file '{}': {}", table_file, e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let mappings = match &args.extenders {
+        Some(file) => load_extender_mappings(file),
+        None => ExtenderMappings::builtin(),
+    };
+    let steps = simulate_schema(&operations, header.as_deref(), &mappings);
+    match args.format.as_str() {
+        "json" => match serde_json::to_string_pretty(&steps) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing schema: {}", e),
+        },
+        _ => print!("{}", format_schema_steps(&steps)),
+    }
+}
+
+fn run_lint(args: LintArgs) {
+    if args.list_rules {
+        for rule in RULES {
+            println!(
+                "{:<28} {:<8} {}",
+                rule.id, rule.default_severity, rule.description
+            );
+        }
+        return;
+    }
+    let mut lint_config = match load_config(args.config.as_deref()) {
+        Ok(config) => config.lint,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    lint_config.disable.extend(args.disabled_rules);
+    let unknown_rules: Vec<&String> = lint_config
+        .disable
+        .iter()
+        .chain(lint_config.severity.keys())
+        .filter(|id| find_rule(id).is_none())
+        .collect();
+    if !unknown_rules.is_empty() {
+        eprintln!("Error: unknown lint rule(s): {:?}", unknown_rules);
+        std::process::exit(2);
+    }
+
+    let operations = match load_session(&args.session) {
+        Ok(Some(operations)) => operations,
+        Ok(None) => {
+            eprintln!("No GET_TABLE entry found in {}", args.session.log_file);
+            std::process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let mappings = match &args.extenders {
+        Some(file) => load_extender_mappings(file),
+        None => ExtenderMappings::builtin(),
+    };
+    let findings = lint_operations(&operations, &lint_config, &mappings);
+    match args.format.as_str() {
+        "json" => match serde_json::to_string_pretty(&findings) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing lint findings: {}", e),
+        },
+        _ => print!(
+            "{}",
+            format_lint_findings(&findings, &args.session.log_file)
+        ),
+    }
+
+    if lint_failed(&findings, args.deny_warnings) {
+        std::process::exit(1);
+    }
+}
+
+/// Loads session `number` (1-based) of a log, or the session `from_end` positions before the
+/// last one, and returns a label for it with the processed operations.
+fn load_numbered_session(
+    log_file: &str,
+    number: Option<usize>,
+    from_end: usize,
+) -> Result<(String, Vec<HashMap<String, String>>), String> {
+    let sessions = logs_by_session(log_file).map_err(|e| format!("{}: {}", log_file, e))?;
+    let index = match number {
+        Some(0) => return Err("session numbers start at 1".to_string()),
+        Some(n) => n - 1,
+        None => sessions
+            .len()
+            .checked_sub(1 + from_end)
+            .ok_or_else(|| format!("{} has fewer than {} sessions", log_file, 1 + from_end))?,
+    };
+    let lines = sessions.get(index).cloned().ok_or_else(|| {
+        format!(
+            "{} has {} session(s), session {} requested",
+            log_file,
+            sessions.len(),
+            index + 1
+        )
+    })?;
+    let started = lines
+        .first()
+        .and_then(|line| line.split(']').next())
+        .map(|s| s.trim_start_matches('[').to_string())
+        .unwrap_or_default();
+    let label = format!("{} (session {}, {})", log_file, index + 1, started);
+    let operations =
+        pre_process_operations(lines).map_err(|e| format!("Error processing operations: {}", e))?;
+    Ok((label, process_operations(operations)))
+}
+
+fn run_diff(args: DiffArgs) {
+    let same_log = args.right_log.is_none();
+    let right_log = args.right_log.as_deref().unwrap_or(&args.left_log);
+    let left_from_end = if same_log && args.right_session.is_none() {
+        1
+    } else {
+        0
+    };
+    let loaded =
+        load_numbered_session(&args.left_log, args.left_session, left_from_end).and_then(|left| {
+            load_numbered_session(right_log, args.right_session, 0).map(|right| (left, right))
+        });
+    let ((left_label, left), (right_label, right)) = match loaded {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let diff = diff_sessions(&left_label, &left, &right_label, &right);
+    match args.format.as_str() {
+        "json" => match serde_json::to_string_pretty(&diff) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing diff: {}", e),
+        },
+        _ => print!("{}", format_session_diff(&diff)),
+    }
+    if args.exit_code && diff.has_differences() {
+        std::process::exit(1);
+    }
+}
+
+fn run_stats(args: StatsArgs) {
+    let mut sessions = Vec::new();
+    for log_file in &args.log_files {
+        let log_sessions = match logs_by_session(log_file) {
+            Ok(log_sessions) => log_sessions,
+            Err(e) => {
+                eprintln!("Error: {}: {}", log_file, e);
+                std::process::exit(2);
+            }
+        };
+        for (index, lines) in log_sessions.into_iter().enumerate() {
+            let operations = match pre_process_operations(lines) {
+                Ok(operations) => operations,
+                Err(e) => {
+                    eprintln!("Error processing operations: {}", e);
+                    std::process::exit(2);
                 }
-                _ => eprintln!("Unknown format specified: {}", args.format),
+            };
+            let operations = filter_operations_by_time(operations, args.from, args.until);
+            // A session entirely outside the window keeps only its GET_TABLE
+            if (args.from.is_some() || args.until.is_some()) && operations.len() <= 1 {
+                continue;
+            }
+            sessions.push(LoggedSession {
+                log_file: log_file.clone(),
+                number: index + 1,
+                operations,
+            });
+        }
+    }
+
+    let stats = collect_stats(&args.log_files, sessions, args.top);
+    match args.format.as_str() {
+        "json" => match serde_json::to_string_pretty(&stats) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Error serializing stats: {}", e),
+        },
+        "csv" => {
+            if let Err(e) = write_stats_csv(&stats, std::io::stdout()) {
+                eprintln!("Error writing stats: {}", e);
             }
         }
-        Err(e) => eprintln!("Error: {}", e),
+        _ => print!("{}", format_stats(&stats)),
+    }
+}
+
+fn run_catalog(args: CatalogArgs) {
+    let catalog = match load_catalog(args.catalog.as_deref()) {
+        Ok(catalog) => catalog,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Some(id) = &args.service {
+        let known = ServiceKind::ALL
+            .iter()
+            .any(|kind| catalog.find(*kind, id).is_some());
+        if !known {
+            eprintln!("Error: no service '{}' in the catalog", id);
+            std::process::exit(2);
+        }
+    }
+    match args.format.as_str() {
+        "json" => {
+            let mut catalog = catalog;
+            if let Some(id) = &args.service {
+                catalog.reconcilers.retain(|service| &service.id == id);
+                catalog.extenders.retain(|service| &service.id == id);
+                catalog.modifiers.retain(|service| &service.id == id);
+            }
+            match serde_json::to_string_pretty(&catalog) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Error serializing catalog: {}", e),
+            }
+        }
+        _ => print!("{}", format_catalog(&catalog, args.service.as_deref())),
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    dotenv().ok();
+    match cli.command {
+        Some(Command::Generate(args)) => run_generate(args),
+        Some(Command::Schema(args)) => run_schema(args),
+        Some(Command::Lint(args)) => run_lint(args),
+        Some(Command::Diff(args)) => run_diff(args),
+        Some(Command::Stats(args)) => run_stats(args),
+        Some(Command::Catalog(args)) => run_catalog(args),
+        Some(Command::Convert(args)) => run_convert(args),
+        Some(Command::Update(args)) => run_update(args),
+        Some(Command::Config(args)) => run_config(args),
+        None => run_generate(cli.generate),
     }
 }
@@ -0,0 +1,204 @@
+use crate::generators::Operation;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Version of the `# semtparser:` line format, bumped on incompatible changes.
+pub const METADATA_VERSION: u32 = 1;
+/// Start of the metadata comment written before each operation of a Python script.
+pub const METADATA_PREFIX: &str = "# semtparser: ";
+
+/// The logged operation behind a block of generated code, embedded as one JSON line:
+///
+/// `# semtparser: {"version":1,"operation":1,"op_type":"RECONCILIATION","fields":{...}}`
+///
+/// `fields` holds the logged fields, keys sorted, with `AdditionalData` as a JSON value
+/// rather than a string when it is valid JSON. `raw` keeps the logged text of such a field
+/// when it is not the compact JSON of the value (the log reader spaces out colons), so the
+/// operation reads back exactly as it was logged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationMetadata {
+    pub version: u32,
+    /// `OPERATION_N` number of the block
+    pub operation: usize,
+    pub op_type: String,
+    pub fields: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub raw: BTreeMap<String, String>,
+}
+
+impl OperationMetadata {
+    pub fn new(number: usize, operation: &Operation) -> Self {
+        let mut raw = BTreeMap::new();
+        let fields = operation
+            .iter()
+            .map(|(key, value)| {
+                // A JSON string stays as logged, so that it reads back unchanged
+                let value = match serde_json::from_str::<Value>(value) {
+                    Ok(data) if key == "AdditionalData" && !data.is_string() => {
+                        if serde_json::to_string(&data).ok().as_ref() != Some(value) {
+                            raw.insert(key.clone(), value.clone());
+                        }
+                        data
+                    }
+                    _ => Value::String(value.clone()),
+                };
+                (key.clone(), value)
+            })
+            .collect();
+        OperationMetadata {
+            version: METADATA_VERSION,
+            operation: number,
+            op_type: operation
+                .get("OpType")
+                .cloned()
+                .unwrap_or_else(|| "UNKNOWN".to_string()),
+            fields,
+            raw,
+        }
+    }
+
+    /// Whether both describe the same operation, comparing the parsed `fields` and ignoring
+    /// how the log spaced them.
+    pub fn same_operation(&self, other: &Self) -> bool {
+        self.operation == other.operation
+            && self.op_type == other.op_type
+            && self.fields == other.fields
+    }
+
+    /// The comment line, without the line break. JSON escapes line breaks in values, so
+    /// the line cannot end early.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}{}",
+            METADATA_PREFIX,
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+
+    /// Parses a line written by [`to_line`](Self::to_line). `None` when the line is not a
+    /// metadata comment.
+    pub fn parse_line(line: &str) -> Option<Result<Self, String>> {
+        let json = line
+            .trim_end_matches(['\r', '\n'])
+            .strip_prefix(METADATA_PREFIX)?;
+        let metadata = serde_json::from_str::<Value>(json)
+            .map_err(|e| format!("invalid metadata: {}", e))
+            .and_then(|value| {
+                let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+                if version == 0 || version > METADATA_VERSION as u64 {
+                    return Err(format!(
+                        "unsupported metadata version {} (this version reads {})",
+                        version, METADATA_VERSION
+                    ));
+                }
+                serde_json::from_value::<Self>(value)
+                    .map_err(|e| format!("invalid metadata: {}", e))
+            });
+        Some(metadata)
+    }
+
+    /// The operation exactly as it was logged.
+    pub fn to_operation(&self) -> Operation {
+        self.fields
+            .iter()
+            .map(|(key, value)| {
+                let value = match (self.raw.get(key), value) {
+                    (Some(raw), _) => raw.clone(),
+                    (None, Value::String(s)) => s.clone(),
+                    (None, other) => other.to_string(),
+                };
+                (key.clone(), value)
+            })
+            .collect()
+    }
+}
+
+/// Operations embedded in a generated Python script, in order. Errors name the line.
+pub fn read_script_metadata(script: &str) -> Result<Vec<OperationMetadata>, String> {
+    script
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            OperationMetadata::parse_line(line)
+                .map(|metadata| metadata.map_err(|e| format!("line {}: {}", index + 1, e)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn metadata_lines_read_back_into_operations() {
+        let operation: Operation = HashMap::from([
+            ("OpType".to_string(), "EXTENSION".to_string()),
+            ("ColumnName".to_string(), "City \"old\"\nname".to_string()),
+            (
+                "AdditionalData".to_string(),
+                r#"{"dates":{"r0":["a","b","Date"]},"properties":"P17 P1082"}"#.to_string(),
+            ),
+            (
+                "timestamp".to_string(),
+                "2025-06-01T10:02:00+00:00".to_string(),
+            ),
+        ]);
+        let line = OperationMetadata::new(2, &operation).to_line();
+        assert!(!line.contains('\n'));
+        assert!(line.starts_with(
+            r#"# semtparser: {"version":1,"operation":2,"op_type":"EXTENSION","fields":{"AdditionalData":{"#
+        ));
+
+        let script = format!("x = 1\n# OPERATION_2: EXTENSION\n{}\ny = 2\n", line);
+        let read = read_script_metadata(&script).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].operation, 2);
+        assert_eq!(read[0].to_operation(), operation);
+
+        assert!(!line.contains("\"raw\""));
+
+        // Spacing and key order that JSON would not reproduce are kept in `raw`, while the
+        // field is still embedded as JSON
+        for logged in [
+            r#"{"properties" : "P17 P1082"}"#,
+            r#"{"properties":"P17","dates":{}}"#,
+        ] {
+            let mut spaced = operation.clone();
+            spaced.insert("AdditionalData".to_string(), logged.to_string());
+            let metadata = OperationMetadata::new(2, &spaced);
+            assert!(metadata.fields["AdditionalData"].is_object());
+            let read = OperationMetadata::parse_line(&metadata.to_line())
+                .unwrap()
+                .unwrap();
+            assert_eq!(read.to_operation()["AdditionalData"], logged);
+        }
+        let spaced = |data: &str| {
+            let mut spaced = operation.clone();
+            spaced.insert("AdditionalData".to_string(), data.to_string());
+            OperationMetadata::new(2, &spaced)
+        };
+        let compact = spaced(r#"{"properties":"P17"}"#);
+        assert!(compact.same_operation(&spaced(r#"{ "properties" : "P17" }"#)));
+        assert!(!compact.same_operation(&spaced(r#"{"properties":"P18"}"#)));
+
+        // AdditionalData that is not JSON stays a string
+        let broken: Operation = HashMap::from([
+            ("OpType".to_string(), "MODIFICATION".to_string()),
+            ("AdditionalData".to_string(), "{not json".to_string()),
+            ("Modifier".to_string(), "\"quoted\"".to_string()),
+        ]);
+        let line = OperationMetadata::new(1, &broken).to_line();
+        let read = OperationMetadata::parse_line(&line).unwrap().unwrap();
+        assert_eq!(read.to_operation(), broken);
+
+        let newer = line.replace("\"version\":1", "\"version\":2");
+        let error = read_script_metadata(&format!("\n{}", newer)).unwrap_err();
+        assert_eq!(
+            error,
+            "line 2: unsupported metadata version 2 (this version reads 1)"
+        );
+        assert!(OperationMetadata::parse_line("# OPERATION_1: EXTENSION").is_none());
+    }
+}
@@ -1,18 +1,21 @@
-use chrono;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::HashSet;
 use uuid::Uuid;
 
 use crate::{
     code_helper::{
-        get_base_export_operation, get_base_extension_operation, get_base_modification_operation,
-        get_base_notebook_dataset_loader, get_base_notebook_dataset_loader_with_column_deletion,
-        get_base_notebook_file_loader_code, get_base_propagation_operation,
-        get_base_reconciliation_operation,
+        get_base_notebook_dataset_loader, get_base_notebook_file_loader_code, Templates,
     },
-    operations::{parse_deleted_columns, parse_json},
+    credentials::warn_about_secrets,
+    generators::OutputFormat,
+    output::{write_output, Destination},
+    pipeline::{Pipeline, PipelineStep},
+    schema::{describe_columns, SchemaStep},
 };
 
+/// Namespace of the content-derived cell ids.
+const CELL_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2d8e_4b3a_5f70_9e21_8c4d_7a6b_5e30);
+
 #[derive(Serialize)]
 struct Notebook {
     cells: Vec<Cell>,
@@ -21,10 +24,6 @@ struct Notebook {
     nbformat_minor: u32,
 }
 
-pub struct Args {
-    pub table_file: String,
-}
-
 #[derive(Serialize)]
 #[serde(tag = "cell_type", rename_all = "lowercase")]
 enum Cell {
@@ -45,51 +44,109 @@ enum Cell {
 #[derive(Serialize)]
 struct Metadata {}
 
+fn expected_schema_cell(step: &SchemaStep) -> Cell {
+    let mut source = vec![format!(
+        "**Expected schema after Operation {}:**\n\n",
+        step.operation_number
+    )];
+    source.extend(
+        describe_columns(&step.columns)
+            .into_iter()
+            .map(|line| format!("- `{}`\n", line)),
+    );
+    Cell::Markdown {
+        id: String::new(),
+        metadata: serde_json::json!({
+            "semtparser": {
+                "cell_type": "expected_schema",
+                "operation_index": step.operation_number
+            }
+        }),
+        source,
+    }
+}
+
+/// Gives every cell an id derived from its content (a UUID v5 of the serialized cell), so
+/// that regenerating the same session gives the same ids. Identical cells are told apart
+/// by the number of earlier copies.
+fn assign_cell_ids(cells: &mut [Cell]) -> Result<(), serde_json::Error> {
+    let mut used = HashSet::new();
+    for cell in cells.iter_mut() {
+        let content = serde_json::to_string(cell)?;
+        let mut copy = 0;
+        let id = loop {
+            let name = format!("{}#{}", content, copy);
+            let id = Uuid::new_v5(&CELL_ID_NAMESPACE, name.as_bytes()).to_string();
+            if used.insert(id.clone()) {
+                break id;
+            }
+            copy += 1;
+        };
+        match cell {
+            Cell::Code { id: cell_id, .. } | Cell::Markdown { id: cell_id, .. } => *cell_id = id,
+        }
+    }
+    Ok(())
+}
+
+fn code_cell(metadata: serde_json::Value, code: &str) -> Cell {
+    Cell::Code {
+        id: String::new(),
+        metadata,
+        source: code.lines().map(|line| format!("{}\n", line)).collect(),
+        execution_count: None,
+        outputs: vec![],
+    }
+}
+
+fn summary_line(step: &PipelineStep) -> String {
+    format!(
+        "- **{}** {} at `{}`\n",
+        step.op_type,
+        step.summary(),
+        step.timestamp.as_deref().unwrap_or("N/A")
+    )
+}
+
 pub fn create_notebook(
-    operations: Vec<HashMap<String, String>>,
-    args: Args,
+    pipeline: &Pipeline,
+    templates: &Templates,
+    destination: &Destination,
 ) -> Result<String, std::io::Error> {
-    let current_timestamp = chrono::Utc::now().format("%Y-%m-%d_%H-%M").to_string();
-    let table_name = format!("test_table-{}", current_timestamp);
-    let path = format!("./base_notebook_file_{}.ipynb", current_timestamp);
-    let default_dataset_id = "1".to_string();
-    let used_dataset_id = operations[0]
-        .get("DatasetId")
-        .unwrap_or(&default_dataset_id);
-    // Look for deleted columns in SAVE_TABLE operations
-    let deleted_columns = operations
-        .iter()
-        .find(|op| op.get("OpType") == Some(&"SAVE_TABLE".to_string()))
-        .and_then(|op| op.get("DeletedCols"))
-        .map(|deleted_cols_str| parse_deleted_columns(deleted_cols_str))
-        .filter(|cols| !cols.is_empty());
+    let path = destination
+        .resolve(pipeline, OutputFormat::Notebook)
+        .map_err(std::io::Error::other)?;
+    let json = render_notebook(pipeline, templates)?;
+    write_output(&path, json.as_bytes(), destination.overwrite)?;
+    let path = path.display().to_string();
+    warn_about_secrets(&path, &json);
+
+    Ok(path)
+}
+
+/// The notebook of the pipeline, as pretty-printed JSON.
+pub fn render_notebook(
+    pipeline: &Pipeline,
+    templates: &Templates,
+) -> Result<String, std::io::Error> {
+    let loader = &pipeline.loader;
 
     // Create base cells - starting with summary as first cell
     let mut cells = vec![];
 
-    // Add operation summary cell as the first cell
-    // Filter operations to exclude GET_TABLE and SAVE_TABLE
-    let mut displayed_operations: Vec<&HashMap<String, String>> = operations
+    // Add operation summary cell as the first cell, listing the steps the script summarizes
+    let displayed_steps: Vec<&PipelineStep> = pipeline
+        .steps
         .iter()
-        .filter(|op| {
-            let op_type = op.get("OpType").map_or("", |s| s.as_str());
-            op_type != "GET_TABLE" && op_type != "SAVE_TABLE"
-        })
+        .filter(|step| step.in_summary())
         .collect();
 
-    // Sort operations by timestamp in ascending order
-    displayed_operations.sort_by(|a, b| {
-        let timestamp_a = a.get("timestamp").map(|s| s.as_str()).unwrap_or("");
-        let timestamp_b = b.get("timestamp").map(|s| s.as_str()).unwrap_or("");
-        timestamp_a.cmp(timestamp_b)
-    });
-
     let summary_metadata = serde_json::json!({
         "semtparser": {
             "cell_type": "summary",
-            "total_operations": displayed_operations.len(),
-            "operation_types": displayed_operations.iter()
-                .map(|op| op.get("OpType").map_or("UNKNOWN".to_string(), |s| s.clone()))
+            "total_operations": displayed_steps.len(),
+            "operation_types": displayed_steps.iter()
+                .map(|step| step.op_type.clone())
                 .collect::<Vec<String>>()
         }
     });
@@ -98,83 +155,13 @@ pub fn create_notebook(
         "# Operation Summary\n".to_string(),
         format!(
             "**Total operations processed:** {}\n\n",
-            displayed_operations.len()
+            displayed_steps.len()
         ),
     ];
-
-    for (_, operation) in displayed_operations.iter().enumerate() {
-        let op_type = operation.get("OpType").map_or("UNKNOWN", |s| s.as_str());
-        let column_name = operation.get("ColumnName").map_or("N/A", |s| s.as_str());
-        let timestamp = operation.get("timestamp").map_or("N/A", |s| s.as_str());
-
-        // Show extender/reconciler/modifier information and format appropriately
-        let (_tool_info, display_text) = match op_type {
-            "RECONCILIATION" => {
-                let reconciler_id = operation.get("Reconciler").map_or("N/A", |s| s.as_str());
-                let info = format!(" using **{}** reconciler", reconciler_id);
-                let text = format!(
-                    "- **{}** on column `{}`{} at `{}`\n",
-                    op_type, column_name, info, timestamp
-                );
-                (info, text)
-            }
-            "EXTENSION" => {
-                let extender_id = operation.get("Extender").map_or("N/A", |s| s.as_str());
-                let info = format!(" using **{}** extender", extender_id);
-                let text = format!(
-                    "- **{}** on column `{}`{} at `{}`\n",
-                    op_type, column_name, info, timestamp
-                );
-                (info, text)
-            }
-            "MODIFICATION" => {
-                let modifier_name = operation.get("Modifier").map_or("N/A", |s| s.as_str());
-                let info = format!(" using **{}** modifier", modifier_name);
-                let text = format!(
-                    "- **{}** on column `{}`{} at `{}`\n",
-                    op_type, column_name, info, timestamp
-                );
-                (info, text)
-            }
-            "EXPORT" => {
-                // For EXPORT, parse the format from AdditionalData
-                let format = if let Some(additional_data_str) = operation.get("AdditionalData") {
-                    if let Some(additional_data) = parse_json(additional_data_str) {
-                        additional_data
-                            .get("format")
-                            .and_then(|f| f.as_str())
-                            .unwrap_or("UNKNOWN")
-                            .to_uppercase()
-                    } else {
-                        "UNKNOWN".to_string()
-                    }
-                } else {
-                    "UNKNOWN".to_string()
-                };
-                let text = format!("- **{}** as **{}** at `{}`\n", op_type, format, timestamp);
-                (String::new(), text)
-            }
-            "PROPAGATE_TYPE" => {
-                let text = format!(
-                    "- **{}** on column `{}` at `{}`\n",
-                    op_type, column_name, timestamp
-                );
-                (String::new(), text)
-            }
-            _ => {
-                let text = format!(
-                    "- **{}** on column `{}` at `{}`\n",
-                    op_type, column_name, timestamp
-                );
-                (String::new(), text)
-            }
-        };
-
-        summary_lines.push(display_text);
-    }
+    summary_lines.extend(displayed_steps.iter().map(|step| summary_line(step)));
 
     cells.push(Cell::Markdown {
-        id: Uuid::new_v4().to_string(),
+        id: String::new(),
         metadata: summary_metadata,
         source: summary_lines,
     });
@@ -186,442 +173,74 @@ pub fn create_notebook(
             "operation_type": "SETUP",
             "operation_data": {
                 "description": "Initial setup and data loading"
-            }
+            },
+            "loader_operations": loader.operations
         }
     });
 
     cells.push(Cell::Markdown {
-        id: Uuid::new_v4().to_string(),
+        id: String::new(),
         metadata: operation_0_metadata.clone(),
         source: vec!["## Operation 0: Setup and Data Loading\n".to_string()],
     });
 
     // Add initial imports cell as part of Operation 0
-    cells.push(Cell::Code {
-        id: Uuid::new_v4().to_string(),
-        metadata: serde_json::json!({}),
-        source: get_base_notebook_file_loader_code()
-            .lines()
-            .map(|line| format!("{}\n", line))
-            .collect(),
-        execution_count: None,
-        outputs: vec![],
-    });
+    let setup_code =
+        get_base_notebook_file_loader_code(templates, &pipeline.base_url, pipeline.credentials)
+            .map_err(std::io::Error::other)?;
+    cells.push(code_cell(serde_json::json!({}), &setup_code));
 
     // Data loading cell with optional column deletion as part of Operation 0
-    let dataset_loader_code = match deleted_columns {
-        Some(ref cols) => get_base_notebook_dataset_loader_with_column_deletion(
-            args.table_file.as_str(),
-            used_dataset_id,
-            table_name.as_str(),
-            cols.clone(),
-        ),
-        None => get_base_notebook_dataset_loader(
-            args.table_file.as_str(),
-            used_dataset_id,
-            table_name.as_str(),
-        ),
-    };
-
-    cells.push(Cell::Code {
-        id: Uuid::new_v4().to_string(),
-        metadata: operation_0_metadata,
-        source: dataset_loader_code
-            .lines()
-            .map(|line| format!("{}\n", line))
-            .collect(),
-        execution_count: None,
-        outputs: vec![],
-    });
-
-    // Add operation cells
-    let mut displayed_operation_counter = 0; // Counter for RECONCILIATION, EXTENSION, PROPAGATE_TYPE and EXPORT operations only
-
-    // Check if there's any EXPORT operation in the operations list
-    let has_export_operation = operations
-        .iter()
-        .any(|op| op.get("OpType") == Some(&"EXPORT".to_string()));
+    let dataset_loader_code =
+        get_base_notebook_dataset_loader(templates, loader).map_err(std::io::Error::other)?;
+    cells.push(code_cell(operation_0_metadata, &dataset_loader_code));
 
-    for (index, operation) in operations.iter().enumerate() {
-        let operation_type = operation.get("OpType").unwrap();
+    if let Some(step) = &pipeline.initial_schema {
+        cells.push(expected_schema_cell(step));
+    }
 
+    for step in &pipeline.steps {
         // Create metadata object with all operation information
         let operation_metadata = serde_json::json!({
             "semtparser": {
-                "operation_index": index + 1,
-                "operation_type": operation_type,
-                "operation_data": operation
+                "operation_index": step.position,
+                "operation_type": step.op_type,
+                "operation_data": step.operation_data()
             }
         });
 
-        match operation_type.as_str() {
-            "RECONCILIATION" => {
-                displayed_operation_counter += 1; // Increment counter for displayed operations
-
-                let reconciler_id = operation.get("Reconciler").unwrap();
-                let col_name = operation.get("ColumnName").unwrap();
-
-                // Parse additional data to check for additionalColumns
-                let additional_columns =
-                    if let Some(additional_data_str) = operation.get("AdditionalData") {
-                        if let Some(additional_data) = parse_json(additional_data_str) {
-                            if let Some(additional_columns_obj) =
-                                additional_data.get("additionalColumns")
-                            {
-                                if let Some(obj) = additional_columns_obj.as_object() {
-                                    let column_names: Vec<String> =
-                                        obj.keys().map(|k| format!("\"{}\"", k)).collect();
-                                    if !column_names.is_empty() {
-                                        println!(
-                                            "Found additionalColumns in reconciliation: {:?}",
-                                            column_names
-                                        );
-                                        Some(column_names)
-                                    } else {
-                                        None
-                                    }
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    };
-
-                cells.push(Cell::Markdown {
-                    id: Uuid::new_v4().to_string(),
-                    metadata: operation_metadata.clone(),
-                    source: vec![format!(
-                        "## Operation {}: Reconciliation for column {} by {}",
-                        displayed_operation_counter, col_name, reconciler_id
-                    )],
-                });
-
-                cells.push(Cell::Code {
-                    id: Uuid::new_v4().to_string(),
-                    metadata: operation_metadata,
-                    source: get_base_reconciliation_operation(
-                        &col_name,
-                        additional_columns,
-                        reconciler_id,
-                    )
-                    .lines()
-                    .map(|line| format!("{}\n", line))
-                    .collect(),
-                    execution_count: None,
-                    outputs: vec![],
-                });
-            }
-            "EXTENSION" => {
-                displayed_operation_counter += 1; // Increment counter for displayed operations
-
-                let extender_id = operation.get("Extender").unwrap();
-                let col_name = operation.get("ColumnName").unwrap();
-
-                let additional_data = parse_json(operation.get("AdditionalData").unwrap()).unwrap();
-
-                // Look for "property" array in additional data and join values with whitespaces
-                // If "property" array exists, use its values; otherwise fallback to "properties" string
-                let mut props: Vec<String> = additional_data
-                    .get("property")
-                    .and_then(|v| v.as_array())
-                    .map(|arr| {
-                        let property_values: Vec<String> = arr.iter()
-                            .filter_map(|item| item.as_str())
-                            .map(|s| s.to_string())
-                            .collect();
-                        println!("Found 'property' array in additional data: {:?}", property_values);
-                        property_values
-                    })
-                    .unwrap_or_else(|| {
-                        // Fallback to existing "properties" field for backward compatibility
-                        let fallback_props: Vec<String> = additional_data
-                            .get("properties")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .split(' ')
-                            .filter(|s| !s.is_empty())
-                            .map(|s| s.to_string())
-                            .collect();
-                        if !fallback_props.is_empty() {
-                            println!("Using fallback 'properties' field: {:?}", fallback_props);
-                        } else {
-                            println!("No 'property' array or 'properties' field found in additional data");
-                        }
-                        fallback_props
-                    });
-
-                // Check for weatherParams and add them to properties
-                if let Some(weather_params) = additional_data.get("weatherParams") {
-                    if let Some(weather_array) = weather_params.as_array() {
-                        let weather_props: Vec<String> = weather_array
-                            .iter()
-                            .filter_map(|item| item.as_str())
-                            .map(|s| s.to_string())
-                            .collect();
-                        props.extend(weather_props);
-                        println!("Added weatherParams to properties: {:?}", weather_params);
+        match step
+            .code(OutputFormat::Notebook, templates)
+            .map_err(std::io::Error::other)?
+        {
+            Some(code) => {
+                let heading = format!("## Operation {}: {}", step.number, step.title());
+                let source = match &step.service_description {
+                    Some(description) => {
+                        vec![format!("{}\n", heading), format!("\n{}", description)]
                     }
-                }
-
-                // Check for labels and add them to properties
-                if let Some(labels) = additional_data.get("labels") {
-                    if let Some(labels_array) = labels.as_array() {
-                        let label_props: Vec<String> = labels_array
-                            .iter()
-                            .filter_map(|item| item.as_str())
-                            .map(|s| s.to_string())
-                            .collect();
-                        props.extend(label_props);
-                        println!("Added labels to properties: {:?}", labels);
-                    }
-                }
-
-                // Check for dates field and extract date column name for other_params
-                let mut other_params = Vec::new();
-                if let Some(dates) = additional_data.get("dates") {
-                    if let Some(dates_obj) = dates.as_object() {
-                        // Get the first key and extract the third element from its array
-                        if let Some((first_key, first_value)) = dates_obj.iter().next() {
-                            if let Some(date_array) = first_value.as_array() {
-                                if date_array.len() > 2 {
-                                    if let Some(date_column_name) = date_array[2].as_str() {
-                                        other_params.push(format!(
-                                            "\"date_column_name\": \"{}\"",
-                                            date_column_name
-                                        ));
-                                        println!(
-                                            "Found date column name: {} from key: {}",
-                                            date_column_name, first_key
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                let additional_params = if other_params.is_empty() {
-                    None
-                } else {
-                    Some(other_params)
+                    None => vec![heading],
                 };
-
                 cells.push(Cell::Markdown {
-                    id: Uuid::new_v4().to_string(),
+                    id: String::new(),
                     metadata: operation_metadata.clone(),
-                    source: vec![format!(
-                        "## Operation {}: Extension for column {} by {}",
-                        displayed_operation_counter, col_name, extender_id
-                    )],
-                });
-
-                cells.push(Cell::Code {
-                    id: Uuid::new_v4().to_string(),
-                    metadata: operation_metadata,
-                    source: get_base_extension_operation(
-                        &col_name,
-                        props,
-                        additional_params,
-                        extender_id,
-                    )
-                    .lines()
-                    .map(|line| format!("{}\n", line))
-                    .collect(),
-                    execution_count: None,
-                    outputs: vec![],
-                });
-            }
-            "PROPAGATE_TYPE" => {
-                displayed_operation_counter += 1; // Increment counter for displayed operations
-
-                let col_name = operation.get("ColumnName").unwrap();
-
-                let additional_data = parse_json(operation.get("AdditionalData").unwrap()).unwrap();
-
-                if let Some(data_map) = additional_data.as_object() {
-                    let value = serde_json::Value::Object(data_map.clone());
-
-                    cells.push(Cell::Markdown {
-                        id: Uuid::new_v4().to_string(),
-                        metadata: operation_metadata.clone(),
-                        source: vec![format!(
-                            "## Operation {}: Propagation for column {}",
-                            displayed_operation_counter, col_name
-                        )],
-                    });
-
-                    cells.push(Cell::Code {
-                        id: Uuid::new_v4().to_string(),
-                        metadata: operation_metadata,
-                        source: get_base_propagation_operation(&col_name, Some(&value))
-                            .lines()
-                            .map(|line| format!("{}\n", line))
-                            .collect(),
-                        execution_count: None,
-                        outputs: vec![],
-                    });
-                } else {
-                    cells.push(Cell::Markdown {
-                        id: Uuid::new_v4().to_string(),
-                        metadata: operation_metadata,
-                        source: vec![format!(
-                            "Operation {}: PROPAGATE_TYPE - Invalid additional data for column {}",
-                            displayed_operation_counter, col_name
-                        )],
-                    });
-                }
-            }
-            "EXPORT" => {
-                displayed_operation_counter += 1; // Increment counter for displayed operations
-
-                // Handle export operation
-                if let Some(additional_data_str) = operation.get("AdditionalData") {
-                    if let Some(additional_data) = parse_json(additional_data_str) {
-                        if let Some(format) = additional_data.get("format").and_then(|f| f.as_str())
-                        {
-                            let output_file = additional_data
-                                .get("outputFile")
-                                .and_then(|f| f.as_str())
-                                .unwrap_or("export_output");
-
-                            if let Some(export_code) =
-                                get_base_export_operation(format, output_file)
-                            {
-                                cells.push(Cell::Markdown {
-                                    id: Uuid::new_v4().to_string(),
-                                    metadata: operation_metadata.clone(),
-                                    source: vec![format!(
-                                        "## Operation {}: Export as {}",
-                                        displayed_operation_counter,
-                                        format.to_uppercase()
-                                    )],
-                                });
-
-                                cells.push(Cell::Code {
-                                    id: Uuid::new_v4().to_string(),
-                                    metadata: operation_metadata,
-                                    source: export_code
-                                        .lines()
-                                        .map(|line| format!("{}\n", line))
-                                        .collect(),
-                                    execution_count: None,
-                                    outputs: vec![],
-                                });
-                                println!(
-                                    "Export operation created successfully for format: {}",
-                                    format
-                                );
-                            } else {
-                                println!(
-                                    "Unsupported export format: {}, skipping export operation",
-                                    format
-                                );
-                            }
-                        } else {
-                            eprintln!("No format specified in EXPORT AdditionalData");
-                        }
-                    } else {
-                        eprintln!("Could not parse AdditionalData for EXPORT operation");
-                    }
-                } else {
-                    eprintln!("No AdditionalData found for EXPORT operation");
-                }
-            }
-            "MODIFICATION" => {
-                displayed_operation_counter += 1; // Increment counter for displayed operations
-
-                let modifier_name = operation.get("Modifier").unwrap();
-                let col_name = operation.get("ColumnName").unwrap();
-
-                let additional_data = parse_json(operation.get("AdditionalData").unwrap()).unwrap();
-
-                cells.push(Cell::Markdown {
-                    id: Uuid::new_v4().to_string(),
-                    metadata: operation_metadata.clone(),
-                    source: vec![format!(
-                        "## Operation {}: Modification for column {} by {}",
-                        displayed_operation_counter, col_name, modifier_name
-                    )],
-                });
-
-                cells.push(Cell::Code {
-                    id: Uuid::new_v4().to_string(),
-                    metadata: operation_metadata,
-                    source: get_base_modification_operation(
-                        &col_name,
-                        modifier_name,
-                        &additional_data,
-                    )
-                    .lines()
-                    .map(|line| format!("{}\n", line))
-                    .collect(),
-                    execution_count: None,
-                    outputs: vec![],
-                });
-            }
-            "GET_TABLE" | "SAVE_TABLE" => {
-                // Skip these operation types as they are not useful for notebook output
-                continue;
-            }
-            _ => {
-                cells.push(Cell::Markdown {
-                    id: Uuid::new_v4().to_string(),
-                    metadata: operation_metadata,
-                    source: vec![format!(
-                        "Operation {}: Unknown operation type: {}",
-                        index + 1,
-                        operation_type
-                    )],
+                    source,
                 });
+                cells.push(code_cell(operation_metadata, &code));
             }
+            None => cells.push(Cell::Markdown {
+                id: String::new(),
+                metadata: operation_metadata,
+                source: vec![format!("Operation {}: {}", step.number, step.title())],
+            }),
         }
-    }
-
-    // Check if no export operation was found in the logs, add default JSON export
-    if !has_export_operation {
-        println!("No export code generated, adding default JSON export");
-        displayed_operation_counter += 1;
 
-        let default_export_metadata = serde_json::json!({
-            "semtparser": {
-                "operation_index": operations.len() + 1,
-                "operation_type": "EXPORT",
-                "operation_data": {
-                    "description": "Default JSON export",
-                    "format": "json"
-                }
-            }
-        });
-
-        if let Some(default_export) = get_base_export_operation("json", "results.json") {
-            cells.push(Cell::Markdown {
-                id: Uuid::new_v4().to_string(),
-                metadata: default_export_metadata.clone(),
-                source: vec![format!(
-                    "## Operation {}: Export as JSON (Default)",
-                    displayed_operation_counter
-                )],
-            });
-
-            cells.push(Cell::Code {
-                id: Uuid::new_v4().to_string(),
-                metadata: default_export_metadata,
-                source: default_export
-                    .lines()
-                    .map(|line| format!("{}\n", line))
-                    .collect(),
-                execution_count: None,
-                outputs: vec![],
-            });
+        if let Some(schema_step) = &step.expected_schema {
+            cells.push(expected_schema_cell(schema_step));
         }
     }
 
+    assign_cell_ids(&mut cells)?;
     let notebook = Notebook {
         nbformat: 4,
         nbformat_minor: 5,
@@ -629,8 +248,5 @@ pub fn create_notebook(
         cells,
     };
 
-    let json = serde_json::to_string_pretty(&notebook)?;
-    std::fs::write(&path, json)?;
-
-    Ok(path)
+    Ok(serde_json::to_string_pretty(&notebook)?)
 }
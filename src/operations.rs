@@ -1,4 +1,5 @@
-use chrono::DateTime;
+use crate::validation::referenced_columns;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
 use rev_lines::RevLines;
 use serde_json::Value;
 use std::{
@@ -23,16 +24,14 @@ pub fn logs_from_last_get_table(path: &str) -> Result<Option<Vec<String>>, io::E
                     continue;
                 }
                 // Process the line
-                if line.contains("GET_TABLE") && !end_line.is_none() {
+                if line.contains("GET_TABLE") && end_line.is_some() {
                     start_line = Some(line);
                     break; // Stop searching after finding the first occurrence
-                } else if line.contains("SAVE_TABLE") {
-                    if end_line.is_none() {
-                        println!("Found SAVE_TABLE line: {}", line);
-                        // If we haven't set an end line yet, set it to the current line
-                        end_line = Some(line.clone());
-                        result.push(line);
-                    }
+                } else if line.contains("SAVE_TABLE") && end_line.is_none() {
+                    eprintln!("Found SAVE_TABLE line: {}", line);
+                    // If we haven't set an end line yet, set it to the current line; it is
+                    // collected with the rest of the session below
+                    end_line = Some(line);
                 }
             }
             Err(e) => {
@@ -45,10 +44,8 @@ pub fn logs_from_last_get_table(path: &str) -> Result<Option<Vec<String>>, io::E
         Some(line) => line,
         None => return Ok(None),
     };
-    let end = match end_line {
-        Some(line) => line,
-        None => String::new(), // If no end line is found, we will read until the end of the file
-    };
+    // If no end line is found, we will read until the end of the file
+    let end = end_line.unwrap_or_default();
     let content = std::fs::read_to_string(path)?;
     let mut found_start = false;
     let mut found_end = false;
@@ -62,18 +59,19 @@ pub fn logs_from_last_get_table(path: &str) -> Result<Option<Vec<String>>, io::E
             // Don't break here - continue reading to capture EXPORT operations after SAVE_TABLE
         }
         if found_start {
-            // Skip empty lines
-            if !line.trim().is_empty() {
-                result.push(line.to_string());
-            }
-
-            // After SAVE_TABLE, continue reading until we hit another GET_TABLE or SAVE_TABLE
+            // After SAVE_TABLE, continue reading until we hit another GET_TABLE or SAVE_TABLE,
+            // which belongs to the next session
             if found_end
                 && (line.contains("GET_TABLE") && line != start
                     || line.contains("SAVE_TABLE") && line != end)
             {
                 break;
             }
+
+            // Skip empty lines
+            if !line.trim().is_empty() {
+                result.push(line.to_string());
+            }
         }
     }
     if result.is_empty() {
@@ -83,6 +81,189 @@ pub fn logs_from_last_get_table(path: &str) -> Result<Option<Vec<String>>, io::E
     }
 }
 
+/// Extracts the `[timestamp]` prefix of a raw log line, if it has a valid RFC3339 one.
+fn line_timestamp(line: &str) -> Option<DateTime<FixedOffset>> {
+    let first_field = line.split(" -| ").next()?.trim();
+    if first_field.starts_with('[') && first_field.ends_with(']') {
+        DateTime::parse_from_rfc3339(first_field.trim_start_matches('[').trim_end_matches(']')).ok()
+    } else {
+        None
+    }
+}
+
+/// Parses a `--from`/`--until` cut-off. Accepts RFC3339 timestamps as written in the
+/// logs, or `YYYY-MM-DD HH:MM:SS` / `YYYY-MM-DD` which are interpreted as UTC.
+pub fn parse_cutoff_timestamp(value: &str) -> Result<DateTime<FixedOffset>, String> {
+    let trimmed = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(trimmed) {
+        return Ok(datetime);
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(trimmed, format) {
+            return Ok(naive.and_utc().fixed_offset());
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc().fixed_offset());
+    }
+    Err(format!(
+        "invalid timestamp '{}', expected RFC3339 (e.g. 2025-06-01T12:00:00Z) or YYYY-MM-DD[ HH:MM:SS]",
+        value
+    ))
+}
+
+/// Finds the session that was open at `until`: the last GET_TABLE logged at or before the
+/// cut-off, followed by every line up to the next GET_TABLE. Unlike
+/// `logs_from_last_get_table` the session does not need to have been saved yet.
+pub fn logs_from_get_table_before(
+    path: &str,
+    until: DateTime<FixedOffset>,
+) -> Result<Option<Vec<String>>, io::Error> {
+    let content = std::fs::read_to_string(path)?;
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+
+    let start_index = lines.iter().rposition(|line| {
+        line.contains("GET_TABLE") && line_timestamp(line).is_some_and(|ts| ts <= until)
+    });
+    let start_index = match start_index {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+    eprintln!("Found GET_TABLE line: {}", lines[start_index]);
+
+    let result: Vec<String> = lines[start_index..]
+        .iter()
+        .enumerate()
+        .take_while(|(offset, line)| *offset == 0 || !line.contains("GET_TABLE"))
+        .map(|(_, line)| line.to_string())
+        .collect();
+    Ok(Some(result))
+}
+
+/// Splits the log into sessions: each GET_TABLE line and every line up to the next
+/// GET_TABLE. Lines before the first GET_TABLE are ignored.
+pub fn logs_by_session(path: &str) -> Result<Vec<Vec<String>>, io::Error> {
+    let content = std::fs::read_to_string(path)?;
+    let mut sessions: Vec<Vec<String>> = Vec::new();
+    for line in content.lines().filter(|line| !line.trim().is_empty()) {
+        if line.contains("GET_TABLE") {
+            sessions.push(vec![line.to_string()]);
+        } else if let Some(session) = sessions.last_mut() {
+            session.push(line.to_string());
+        }
+    }
+    Ok(sessions)
+}
+
+/// Value of a `Key: value` field of a raw log line.
+fn log_field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    line.split(" -| ").find_map(|part| {
+        let (name, value) = part.split_once(':')?;
+        (name.trim() == key).then(|| value.trim())
+    })
+}
+
+/// Every session opened on the same DatasetId/TableId as the selected one (the last session,
+/// or the one open at `until`), oldest first. Sessions opened after `until` are left out.
+pub fn logs_for_table_sessions(
+    path: &str,
+    until: Option<DateTime<FixedOffset>>,
+) -> Result<Option<Vec<Vec<String>>>, io::Error> {
+    let sessions: Vec<Vec<String>> = logs_by_session(path)?
+        .into_iter()
+        .filter(|session| {
+            until.is_none_or(|until| line_timestamp(&session[0]).is_some_and(|ts| ts <= until))
+        })
+        .collect();
+    let table_of = |session: &Vec<String>| {
+        (
+            log_field(&session[0], "DatasetId").map(|s| s.to_string()),
+            log_field(&session[0], "TableId").map(|s| s.to_string()),
+        )
+    };
+    let selected = match sessions.last() {
+        Some(session) => table_of(session),
+        None => return Ok(None),
+    };
+    eprintln!(
+        "Consolidating sessions on dataset {} table {}",
+        selected.0.as_deref().unwrap_or("N/A"),
+        selected.1.as_deref().unwrap_or("N/A")
+    );
+    Ok(Some(
+        sessions
+            .into_iter()
+            .filter(|session| table_of(session) == selected)
+            .collect(),
+    ))
+}
+
+/// Chains preprocessed sessions on the same table into one replay: the first GET_TABLE loads
+/// the table and later sessions continue from its result instead of loading it again. The
+/// SAVE_TABLE entries are merged into the first one, whose `DeletedCols` become the union of
+/// all the deletions; a column deleted by a later save that earlier operations still use is
+/// kept, since the loader deletes columns before any operation runs.
+pub fn consolidate_sessions(
+    sessions: Vec<Vec<HashMap<String, String>>>,
+) -> Vec<HashMap<String, String>> {
+    let mut consolidated: Vec<HashMap<String, String>> = Vec::new();
+    let mut save_index: Option<usize> = None;
+    let mut deleted_columns: Vec<String> = Vec::new();
+
+    for (session_index, session) in sessions.into_iter().enumerate() {
+        for op in session {
+            match op.get("OpType").map(|s| s.as_str()) {
+                Some("GET_TABLE") if session_index > 0 => {
+                    eprintln!(
+                        "Continuing with session {} started at {}",
+                        session_index + 1,
+                        op.get("timestamp").map_or("N/A", |s| s.as_str())
+                    );
+                    continue;
+                }
+                Some("SAVE_TABLE") => {
+                    let deleted = op.get("DeletedCols").map_or("", |s| s.as_str());
+                    for column in parse_deleted_columns(deleted) {
+                        let used_earlier = session_index > 0
+                            && consolidated
+                                .iter()
+                                .any(|earlier| referenced_columns(earlier).contains(&column));
+                        if used_earlier {
+                            eprintln!(
+                                "Warning: column '{}' deleted when session {} was saved is used by earlier operations; keeping it",
+                                column,
+                                session_index + 1
+                            );
+                        } else if !deleted_columns.contains(&column) {
+                            deleted_columns.push(column);
+                        }
+                    }
+                    if save_index.is_none() {
+                        save_index = Some(consolidated.len());
+                        consolidated.push(op);
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            consolidated.push(op);
+        }
+    }
+
+    if let Some(index) = save_index {
+        let deleted = if deleted_columns.is_empty() {
+            "NO_DELETED".to_string()
+        } else {
+            deleted_columns.join("|-|")
+        };
+        consolidated[index].insert("DeletedCols".to_string(), deleted);
+    }
+    consolidated
+}
+
 pub fn pre_process_operations(
     operations: Vec<String>,
 ) -> Result<Vec<HashMap<String, String>>, Error> {
@@ -118,6 +299,51 @@ pub fn pre_process_operations(
     Ok(operations_arrays)
 }
 
+/// Keeps only the operations logged inside the `[from, until]` window. The GET_TABLE that
+/// opens the session is always kept so the table is still loaded; SAVE_TABLE is cut like any
+/// other entry, so a cut-off before the save reproduces the table before its columns were
+/// deleted. Operations without a valid timestamp cannot be placed and are dropped.
+pub fn filter_operations_by_time(
+    operations: Vec<HashMap<String, String>>,
+    from: Option<DateTime<FixedOffset>>,
+    until: Option<DateTime<FixedOffset>>,
+) -> Vec<HashMap<String, String>> {
+    if from.is_none() && until.is_none() {
+        return operations;
+    }
+    let total = operations.len();
+    let kept: Vec<HashMap<String, String>> = operations
+        .into_iter()
+        .filter(|op| {
+            if op.get("OpType").map(|s| s.as_str()) == Some("GET_TABLE") {
+                return true;
+            }
+            let timestamp = op.get("timestamp").map_or("", |s| s.as_str());
+            match DateTime::parse_from_rfc3339(timestamp) {
+                Ok(datetime) => {
+                    from.is_none_or(|from| datetime >= from)
+                        && until.is_none_or(|until| datetime <= until)
+                }
+                Err(_) => {
+                    eprintln!(
+                        "Warning: dropping {} operation without a valid timestamp from the time window",
+                        op.get("OpType").map_or("UNKNOWN", |s| s.as_str())
+                    );
+                    false
+                }
+            }
+        })
+        .collect();
+    eprintln!(
+        "Time window {} -> {}: kept {} of {} operations",
+        from.map_or("start".to_string(), |d| d.to_rfc3339()),
+        until.map_or("end".to_string(), |d| d.to_rfc3339()),
+        kept.len(),
+        total
+    );
+    kept
+}
+
 fn get_extension_key(operation: &HashMap<String, String>) -> String {
     let column_name = operation.get("ColumnName").map_or("", |v| v);
     let extender = operation.get("Extender").map_or("", |v| v);
@@ -185,7 +411,7 @@ pub fn process_operations(
                     filtered_operations.push(op);
                 } else {
                     // No extension in between, replace the previous reconciliation with this one
-                    println!(
+                    eprintln!(
                         "Replacing reconciliation for column: {} at timestamp: {} (no extension in between)",
                         col_name, timestamp
                     );
@@ -211,7 +437,7 @@ pub fn process_operations(
                     && extension_key == last_extension_key
                 {
                     // Identical extension operation, skip it
-                    println!(
+                    eprintln!(
                         "Skipping identical extension for column: {} at timestamp: {}",
                         col_name, timestamp
                     );
@@ -237,7 +463,7 @@ pub fn process_operations(
 
             if let Some(last_mod_idx) = last_modification_index {
                 // Replace the previous modification with this one (keep only the last)
-                println!(
+                eprintln!(
                     "Replacing modification for column: {} at timestamp: {}",
                     col_name, timestamp
                 );
@@ -260,7 +486,7 @@ pub fn process_operations(
 
                 if current_format == last_format {
                     // Identical export operation, skip it
-                    println!(
+                    eprintln!(
                         "Skipping identical EXPORT operation at timestamp: {}",
                         timestamp
                     );
@@ -304,3 +530,244 @@ pub fn parse_deleted_columns(deleted_cols_string: &str) -> Vec<String> {
         .filter(|col| !col.is_empty())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "\
+[2025-06-01T10:00:00Z] -| OpType: GET_TABLE -| DatasetId: 2 -| TableId: 5 -| TableName: cities
+[2025-06-01T10:01:00Z] -| OpType: RECONCILIATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| Reconciler: wikidataOpenRefine
+[2025-06-01T10:02:00Z] -| OpType: SAVE_TABLE -| DatasetId: 2 -| TableId: 5
+[2025-06-01T11:00:00Z] -| OpType: GET_TABLE -| DatasetId: 2 -| TableId: 5 -| TableName: cities
+[2025-06-01T11:01:00Z] -| OpType: RECONCILIATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| Reconciler: wikidataAlligator
+";
+
+    /// Writes `content` to a log file of its own in the temp directory.
+    fn write_log(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "semtparser-operations-{}-{}.txt",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn utc(value: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(value).unwrap()
+    }
+
+    fn operation(op_type: &str, timestamp: &str, reconciler: &str) -> HashMap<String, String> {
+        HashMap::from([
+            ("OpType".to_string(), op_type.to_string()),
+            ("timestamp".to_string(), timestamp.to_string()),
+            ("ColumnName".to_string(), "City".to_string()),
+            ("Reconciler".to_string(), reconciler.to_string()),
+        ])
+    }
+
+    #[test]
+    fn cutoffs_accept_rfc3339_and_utc_dates() {
+        assert_eq!(
+            parse_cutoff_timestamp("2025-06-01T12:00:00+02:00"),
+            Ok(utc("2025-06-01T10:00:00Z"))
+        );
+        assert_eq!(
+            parse_cutoff_timestamp(" 2025-06-01 10:30:15 "),
+            Ok(utc("2025-06-01T10:30:15Z"))
+        );
+        assert_eq!(
+            parse_cutoff_timestamp("2025-06-01T10:30:15"),
+            Ok(utc("2025-06-01T10:30:15Z"))
+        );
+        assert_eq!(
+            parse_cutoff_timestamp("2025-06-01 10:30"),
+            Ok(utc("2025-06-01T10:30:00Z"))
+        );
+        assert_eq!(
+            parse_cutoff_timestamp("2025-06-01"),
+            Ok(utc("2025-06-01T00:00:00Z"))
+        );
+        let error = parse_cutoff_timestamp("yesterday").unwrap_err();
+        assert!(error.contains("invalid timestamp 'yesterday'"), "{}", error);
+    }
+
+    #[test]
+    fn the_last_saved_session_is_read_with_its_save_table_once() {
+        let path = write_log("last", LOG);
+        let session = logs_from_last_get_table(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let session = session.unwrap();
+        assert_eq!(session.len(), 3);
+        assert!(session[0].starts_with("[2025-06-01T10:00:00Z]"));
+        assert!(session[2].contains("SAVE_TABLE"));
+    }
+
+    #[test]
+    fn the_session_open_at_the_cutoff_is_read_even_if_unsaved() {
+        let path = write_log("before", LOG);
+        let first = logs_from_get_table_before(&path, utc("2025-06-01T10:30:00Z")).unwrap();
+        let second = logs_from_get_table_before(&path, utc("2025-06-01T11:00:00Z")).unwrap();
+        let none = logs_from_get_table_before(&path, utc("2025-06-01T09:59:59Z")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let first = first.unwrap();
+        assert_eq!(first.len(), 3);
+        assert!(first[0].starts_with("[2025-06-01T10:00:00Z]"));
+        assert!(first[2].contains("SAVE_TABLE"));
+        // The later session has no SAVE_TABLE yet and still ends at the end of the log
+        let second = second.unwrap();
+        assert_eq!(second.len(), 2);
+        assert!(second[1].contains("wikidataAlligator"));
+        assert_eq!(none, None);
+    }
+
+    #[test]
+    fn the_time_window_keeps_get_table_and_drops_untimed_operations() {
+        let operations = vec![
+            operation("GET_TABLE", "2025-06-01T10:00:00+00:00", ""),
+            operation("RECONCILIATION", "2025-06-01T10:01:00+00:00", "a"),
+            operation("RECONCILIATION", "2025-06-01T10:05:00+00:00", "b"),
+            operation("RECONCILIATION", "not a timestamp", "c"),
+            operation("SAVE_TABLE", "2025-06-01T10:09:00+00:00", ""),
+        ];
+        let reconcilers = |operations: &[HashMap<String, String>]| -> Vec<String> {
+            operations
+                .iter()
+                .map(|op| format!("{}{}", op["OpType"], op["Reconciler"]))
+                .collect()
+        };
+
+        assert_eq!(
+            filter_operations_by_time(operations.clone(), None, None).len(),
+            5
+        );
+        let kept =
+            filter_operations_by_time(operations.clone(), Some(utc("2025-06-01T10:02:00Z")), None);
+        assert_eq!(
+            reconcilers(&kept),
+            ["GET_TABLE", "RECONCILIATIONb", "SAVE_TABLE"]
+        );
+        // Both bounds are inclusive
+        let kept = filter_operations_by_time(
+            operations,
+            Some(utc("2025-06-01T10:01:00Z")),
+            Some(utc("2025-06-01T10:05:00Z")),
+        );
+        assert_eq!(
+            reconcilers(&kept),
+            ["GET_TABLE", "RECONCILIATIONa", "RECONCILIATIONb"]
+        );
+    }
+
+    #[test]
+    fn the_time_cut_happens_before_deduplication() {
+        // City was reconciled twice; deduplicating first would keep only the redone
+        // reconciliation, which the cut-off then drops, losing City's reconciliation
+        let operations = vec![
+            operation("GET_TABLE", "2025-06-01T10:00:00+00:00", ""),
+            operation("RECONCILIATION", "2025-06-01T10:01:00+00:00", "first"),
+            operation("RECONCILIATION", "2025-06-01T10:10:00+00:00", "redone"),
+        ];
+        let until = Some(utc("2025-06-01T10:05:00Z"));
+
+        let replayed =
+            process_operations(filter_operations_by_time(operations.clone(), None, until));
+        assert_eq!(replayed.len(), 2);
+        assert_eq!(replayed[1]["Reconciler"], "first");
+
+        let deduplicated_first =
+            filter_operations_by_time(process_operations(operations), None, until);
+        assert_eq!(deduplicated_first.len(), 1);
+    }
+
+    const TABLES_LOG: &str = "\
+[2025-06-01T09:00:00Z] -| OpType: RECONCILIATION -| ColumnName: Before
+[2025-06-01T10:00:00Z] -| OpType: GET_TABLE -| DatasetId: 2 -| TableId: 5 -| TableName: cities
+[2025-06-01T10:01:00Z] -| OpType: RECONCILIATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| Reconciler: wikidataOpenRefine
+[2025-06-01T10:02:00Z] -| OpType: SAVE_TABLE -| DatasetId: 2 -| TableId: 5 -| DeletedCols: Notes
+
+[2025-06-01T10:30:00Z] -| OpType: GET_TABLE -| DatasetId: 2 -| TableId: 6 -| TableName: people
+[2025-06-01T10:31:00Z] -| OpType: RECONCILIATION -| DatasetId: 2 -| TableId: 6 -| ColumnName: Name -| Reconciler: wikidataOpenRefine
+[2025-06-01T10:32:00Z] -| OpType: SAVE_TABLE -| DatasetId: 2 -| TableId: 6
+[2025-06-01T11:00:00Z] -| OpType: GET_TABLE -| DatasetId: 2 -| TableId: 5 -| TableName: cities
+[2025-06-01T11:01:00Z] -| OpType: EXTENSION -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| Extender: wikidataPropertySPARQL -| AdditionalData: {\"properties\":\"P17\"}
+[2025-06-01T11:02:00Z] -| OpType: SAVE_TABLE -| DatasetId: 2 -| TableId: 5 -| DeletedCols: Tmp|-|City
+";
+
+    fn first_timestamps(sessions: &[Vec<String>]) -> Vec<&str> {
+        sessions.iter().map(|session| &session[0][1..21]).collect()
+    }
+
+    #[test]
+    fn the_log_is_split_into_sessions_at_each_get_table() {
+        let path = write_log("sessions", TABLES_LOG);
+        let sessions = logs_by_session(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // The line before the first GET_TABLE and the blank line are dropped
+        assert_eq!(
+            first_timestamps(&sessions),
+            [
+                "2025-06-01T10:00:00Z",
+                "2025-06-01T10:30:00Z",
+                "2025-06-01T11:00:00Z"
+            ]
+        );
+        assert_eq!(
+            sessions.iter().map(|s| s.len()).collect::<Vec<_>>(),
+            [3, 3, 3]
+        );
+    }
+
+    #[test]
+    fn only_the_sessions_on_the_selected_table_are_consolidated() {
+        let path = write_log("tables", TABLES_LOG);
+        let last_table = logs_for_table_sessions(&path, None).unwrap().unwrap();
+        // At 10:45 the last session opened is the one on table 6
+        let at_cutoff = logs_for_table_sessions(&path, Some(utc("2025-06-01T10:45:00Z")))
+            .unwrap()
+            .unwrap();
+        let before_any = logs_for_table_sessions(&path, Some(utc("2025-06-01T09:30:00Z"))).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            first_timestamps(&last_table),
+            ["2025-06-01T10:00:00Z", "2025-06-01T11:00:00Z"]
+        );
+        assert_eq!(first_timestamps(&at_cutoff), ["2025-06-01T10:30:00Z"]);
+        assert_eq!(before_any, None);
+    }
+
+    #[test]
+    fn sessions_on_one_table_are_replayed_as_one() {
+        let path = write_log("consolidate", TABLES_LOG);
+        let sessions = logs_for_table_sessions(&path, None).unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let preprocessed: Vec<Vec<HashMap<String, String>>> = sessions
+            .into_iter()
+            .map(|session| pre_process_operations(session).unwrap())
+            .collect();
+        let consolidated = consolidate_sessions(preprocessed);
+        let op_types: Vec<&str> = consolidated
+            .iter()
+            .map(|op| op["OpType"].as_str())
+            .collect();
+        assert_eq!(
+            op_types,
+            ["GET_TABLE", "RECONCILIATION", "SAVE_TABLE", "EXTENSION"]
+        );
+        // City is deleted by the second save but used by the first session, so it is kept
+        assert_eq!(consolidated[2]["DeletedCols"], "Notes|-|Tmp");
+        assert_eq!(consolidated[3]["timestamp"], "2025-06-01T11:01:00+00:00");
+
+        let single = consolidate_sessions(vec![vec![HashMap::from([(
+            "OpType".to_string(),
+            "SAVE_TABLE".to_string(),
+        )])]]);
+        assert_eq!(single[0]["DeletedCols"], "NO_DELETED");
+    }
+}
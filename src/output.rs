@@ -0,0 +1,195 @@
+use crate::generators::OutputFormat;
+use crate::pipeline::Pipeline;
+use std::{
+    fs,
+    io::{Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+/// Name of the generated script, without its extension.
+pub const DEFAULT_PYTHON_NAME: &str = "base_file_{timestamp}";
+/// Name of the generated notebook, without its extension.
+pub const DEFAULT_NOTEBOOK_NAME: &str = "base_notebook_file_{timestamp}";
+/// Placeholders of a name template.
+pub const NAME_PLACEHOLDERS: [&str; 3] = ["timestamp", "dataset_id", "format"];
+
+/// Where a generated script or notebook is written. The default is a timestamped file in
+/// the current directory, never overwriting an existing one.
+#[derive(Debug, Clone, Default)]
+pub struct Destination {
+    /// Exact path of the file; `dir` and `name_template` are ignored when set
+    pub path: Option<PathBuf>,
+    /// Directory of the file, created if missing (default: the current directory)
+    pub dir: Option<PathBuf>,
+    /// File name without extension, with `{timestamp}`, `{dataset_id}` and `{format}`
+    /// placeholders (default: [`DEFAULT_PYTHON_NAME`] or [`DEFAULT_NOTEBOOK_NAME`])
+    pub name_template: Option<String>,
+    /// Replace the file if it exists
+    pub overwrite: bool,
+}
+
+impl Destination {
+    /// Path the output of `pipeline` is written to in `format`.
+    pub fn resolve(&self, pipeline: &Pipeline, format: OutputFormat) -> Result<PathBuf, String> {
+        if let Some(path) = &self.path {
+            return Ok(path.clone());
+        }
+        let (default_name, extension, format_name) = match format {
+            OutputFormat::Python => (DEFAULT_PYTHON_NAME, "py", "python"),
+            OutputFormat::Notebook => (DEFAULT_NOTEBOOK_NAME, "ipynb", "notebook"),
+        };
+        let template = self.name_template.as_deref().unwrap_or(default_name);
+        let name = fill_name_template(
+            template,
+            &[
+                ("timestamp", &pipeline.timestamp_label()),
+                ("dataset_id", &pipeline.loader.dataset_id),
+                ("format", format_name),
+            ],
+        )?;
+        let dir = self.dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        Ok(dir.join(format!("{}.{}", name, extension)))
+    }
+}
+
+/// Checks that `template` only uses known placeholders and gives a usable file name.
+pub fn check_name_template(template: &str) -> Result<(), String> {
+    let values: Vec<(&str, &str)> = NAME_PLACEHOLDERS.iter().map(|name| (*name, "x")).collect();
+    fill_name_template(template, &values).map(|_| ())
+}
+
+fn fill_name_template(template: &str, values: &[(&str, &str)]) -> Result<String, String> {
+    // Logged values must not move the file to another directory
+    let values: Vec<(&str, String)> = values
+        .iter()
+        .map(|(key, value)| (*key, value.replace(['/', '\\'], "_")))
+        .collect();
+    let values: Vec<(&str, &str)> = values
+        .iter()
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect();
+    let name = fill_placeholders(template, &values)
+        .map_err(|e| format!("name template '{}': {}", template, e))?;
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(format!(
+            "the name template '{}' must give a file name, not a path (use --output-dir)",
+            template
+        ));
+    }
+    Ok(name)
+}
+
+/// `template` with each `{name}` replaced by the value of `name` in `values`.
+pub fn fill_placeholders(template: &str, values: &[(&str, &str)]) -> Result<String, String> {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| "unclosed '{'".to_string())?
+            + start;
+        let placeholder = &rest[start + 1..end];
+        let value = values
+            .iter()
+            .find(|(key, _)| *key == placeholder)
+            .ok_or_else(|| {
+                let known: Vec<String> = values
+                    .iter()
+                    .map(|(key, _)| format!("{{{}}}", key))
+                    .collect();
+                format!(
+                    "unknown placeholder {{{}}} (expected {})",
+                    placeholder,
+                    known.join(", ")
+                )
+            })?
+            .1;
+        filled.push_str(value);
+        rest = &rest[end + 1..];
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+/// Writes `content` to a temporary file next to `path`, then renames it over `path`, so
+/// `path` never holds a partial file. Fails with `AlreadyExists` when `path` exists and
+/// `overwrite` is false: the temporary file is then hard-linked to `path`, which, unlike a
+/// rename, fails rather than replace a file created since generation started.
+pub fn write_output(path: &Path, content: &[u8], overwrite: bool) -> Result<(), Error> {
+    let already_exists = || {
+        Error::new(
+            ErrorKind::AlreadyExists,
+            format!(
+                "{} already exists (use --force to replace it)",
+                path.display()
+            ),
+        )
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::other(format!("{} is not a file path", path.display())))?;
+    let temporary = dir.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let written = fs::File::create(&temporary).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    let placed = written.and_then(|_| {
+        if overwrite {
+            fs::rename(&temporary, path)
+        } else {
+            fs::hard_link(&temporary, path).map_err(|e| match e.kind() {
+                ErrorKind::AlreadyExists => already_exists(),
+                _ => e,
+            })
+        }
+    });
+    // A renamed temporary file is gone; a linked one is left next to the output
+    if !overwrite || placed.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    placed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_templates_fill_known_placeholders() {
+        let values = [("timestamp", "2025-06-01_12-00"), ("dataset_id", "a/b")];
+        assert_eq!(
+            fill_name_template("run_{dataset_id}_{timestamp}", &values).unwrap(),
+            "run_a_b_2025-06-01_12-00"
+        );
+        assert!(fill_name_template("run_{table}", &values)
+            .unwrap_err()
+            .contains("unknown placeholder {table}"));
+        assert!(fill_name_template("run_{timestamp", &values).is_err());
+        assert!(fill_name_template("out/run", &values).is_err());
+    }
+
+    #[test]
+    fn existing_files_are_kept_unless_overwriting() {
+        let dir = std::env::temp_dir().join(format!("semtparser-output-{}", std::process::id()));
+        let path = dir.join("nested").join("script.py");
+        write_output(&path, b"first", false).unwrap();
+        let refused = write_output(&path, b"second", false).unwrap_err();
+        assert_eq!(refused.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&path).unwrap(), b"first");
+        write_output(&path, b"second", true).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        // Only the output is left behind
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
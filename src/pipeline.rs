@@ -0,0 +1,598 @@
+use crate::{
+    code_helper::Templates,
+    credentials::CredentialStrategy,
+    generators::{GeneratorRegistry, OperationGenerator, OutputFormat},
+    operations::parse_deleted_columns,
+    output::fill_placeholders,
+    schema::{simulate_schema, SchemaStep},
+    validation::read_csv_sample,
+};
+use chrono::{DateTime, FixedOffset, Utc};
+use serde_json::Value;
+use std::{collections::HashMap, fmt, str::FromStr, sync::Arc};
+
+/// Dataset used when the log has no GET_TABLE to take it from.
+pub const DEFAULT_DATASET_ID: &str = "1";
+/// Backend the generated code connects to unless `BASE_URL` or a profile names another.
+pub const DEFAULT_BASE_URL: &str = "http://vm.chronos.disco.unimib.it:3003";
+/// Name of the uploaded table when the GET_TABLE has no `TableName`; `{timestamp}` is
+/// [`Pipeline::timestamp_label`].
+pub const DEFAULT_TABLE_NAME: &str = "test_table-{timestamp}";
+/// What `{original}` stands for in a table name when no `TableName` was logged.
+const UNNAMED_TABLE: &str = "test_table";
+/// Placeholders of a table or dataset name pattern.
+pub const TABLE_NAME_PLACEHOLDERS: [&str; 3] = ["original", "date", "timestamp"];
+/// Format of [`Pipeline::timestamp_label`].
+const TIMESTAMP_LABEL_FORMAT: &str = "%Y-%m-%d_%H-%M";
+/// Format of `{date}` in table and dataset names.
+const DATE_LABEL_FORMAT: &str = "%Y-%m-%d";
+/// Names of the loader modes, as accepted by `--loader`.
+pub const LOADER_MODES: [&str; 2] = ["upload", "existing"];
+/// Export added when the session never exported the table.
+const DEFAULT_EXPORT_DATA: &str = r#"{"format": "json", "outputFile": "results.json"}"#;
+
+/// How the generated code gets the table the steps run on. A generated script's `--loader`
+/// and `--table-id` arguments override it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoaderMode {
+    /// Upload the CSV file as a new table
+    #[default]
+    Upload,
+    /// Use a table already on the backend, by id or found by name. A dataset looked up by
+    /// name that does not exist is created, with the CSV file as its table
+    Existing,
+}
+
+impl LoaderMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LoaderMode::Upload => "upload",
+            LoaderMode::Existing => "existing",
+        }
+    }
+}
+
+impl FromStr for LoaderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upload" => Ok(LoaderMode::Upload),
+            "existing" => Ok(LoaderMode::Existing),
+            _ => Err(format!(
+                "unknown loader '{}' (expected one of: {})",
+                s,
+                LOADER_MODES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LoaderMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Everything the generated code needs to load the table before the first step.
+pub struct TableLoader {
+    pub mode: LoaderMode,
+    /// Existing table to use, found by `table_name` when `None`
+    pub table_id: Option<String>,
+    pub table_file: String,
+    pub dataset_id: String,
+    /// Dataset looked up by name when the code runs, in place of `dataset_id`
+    pub dataset_name: Option<String>,
+    pub table_name: String,
+    /// `TableName` and `DatasetName` of the GET_TABLE, `{original}` in name patterns
+    pub logged_table_name: Option<String>,
+    pub logged_dataset_name: Option<String>,
+    /// `DeletedCols` of the SAVE_TABLE, dropped from the CSV before the upload
+    pub deleted_columns: Vec<String>,
+    /// The GET_TABLE and SAVE_TABLE operations the loader was built from, as logged
+    pub operations: Vec<HashMap<String, String>>,
+}
+
+/// A replayed operation with its parameters resolved from the log.
+pub struct PipelineStep {
+    /// OPERATION_N numbering shared by every output format
+    pub number: usize,
+    /// 1-based position in the processed operations (GET_TABLE and SAVE_TABLE included)
+    pub position: usize,
+    pub op_type: String,
+    pub column: Option<String>,
+    pub timestamp: Option<String>,
+    /// The operation as logged
+    pub operation: HashMap<String, String>,
+    /// Parameters resolved by the generator's `parse`
+    pub params: Value,
+    /// Why the operation produces no code, if its parameters could not be resolved
+    pub skipped: Option<String>,
+    /// Added by the generator rather than logged (the default export)
+    pub generated: bool,
+    /// Expected schema after the step, with `--document-schema`
+    pub expected_schema: Option<SchemaStep>,
+    /// Markdown description of the service the step calls, from the service catalog
+    pub service_description: Option<String>,
+    generator: Arc<dyn OperationGenerator>,
+}
+
+impl PipelineStep {
+    /// Heading of the step, e.g. `Reconciliation for column City by wikidataOpenRefine`.
+    pub fn title(&self) -> String {
+        match &self.skipped {
+            Some(reason) => format!(
+                "{} on column {} skipped ({})",
+                self.op_type,
+                self.column.as_deref().unwrap_or("N/A"),
+                reason
+            ),
+            None => self.generator.describe(self),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        self.generator.summary(self)
+    }
+
+    pub fn in_summary(&self) -> bool {
+        self.generator.in_summary()
+    }
+
+    pub fn heading(&self) -> Option<String> {
+        self.generator.heading(self)
+    }
+
+    pub fn operation_data(&self) -> Value {
+        self.generator.operation_data(self)
+    }
+
+    /// Code of the step, `None` for skipped steps. A rendering error (e.g. in a user
+    /// template) names the step.
+    pub fn code(
+        &self,
+        format: OutputFormat,
+        templates: &Templates,
+    ) -> Result<Option<String>, String> {
+        if self.skipped.is_some() {
+            return Ok(None);
+        }
+        self.generator
+            .render(self, format, templates)
+            .map(Some)
+            .map_err(|e| format!("OPERATION_{} ({}): {}", self.number, self.op_type, e))
+    }
+}
+
+pub struct Pipeline {
+    /// Backend URL offered as the default by the generated code
+    pub base_url: String,
+    /// How the generated code gets the username and password
+    pub credentials: CredentialStrategy,
+    pub loader: TableLoader,
+    /// Expected schema after the table is loaded, with `--document-schema`
+    pub initial_schema: Option<SchemaStep>,
+    pub steps: Vec<PipelineStep>,
+    /// Time the output is stamped with (file names and the uploaded table name)
+    pub generated_at: DateTime<Utc>,
+}
+
+impl Pipeline {
+    /// `generated_at` as used in file and table names, e.g. `2025-06-01_10-00`.
+    pub fn timestamp_label(&self) -> String {
+        self.generated_at.format(TIMESTAMP_LABEL_FORMAT).to_string()
+    }
+
+    /// Names the uploaded table after `pattern`, e.g. `{original}-{date}`. `{original}` is
+    /// the logged table name, or `test_table` when the log has none.
+    pub fn set_table_name(&mut self, pattern: &str) -> Result<(), String> {
+        let original = self
+            .loader
+            .logged_table_name
+            .as_deref()
+            .unwrap_or(UNNAMED_TABLE);
+        self.loader.table_name = self
+            .fill_name(pattern, original)
+            .map_err(|e| format!("table name '{}': {}", pattern, e))?;
+        Ok(())
+    }
+
+    /// Makes the generated code look the dataset up by a name following `pattern` instead
+    /// of using the dataset id. `{original}` is the logged dataset name.
+    pub fn set_dataset_name(&mut self, pattern: &str) -> Result<(), String> {
+        let name = match self.loader.logged_dataset_name.as_deref() {
+            Some(original) => self.fill_name(pattern, original),
+            None if pattern.contains("{original}") => {
+                Err("the log has no dataset name for {original}".to_string())
+            }
+            None => self.fill_name(pattern, ""),
+        };
+        self.loader.dataset_name =
+            Some(name.map_err(|e| format!("dataset name '{}': {}", pattern, e))?);
+        Ok(())
+    }
+
+    fn fill_name(&self, pattern: &str, original: &str) -> Result<String, String> {
+        let date = self.generated_at.format(DATE_LABEL_FORMAT).to_string();
+        let timestamp = self.timestamp_label();
+        fill_placeholders(
+            pattern,
+            &[
+                ("original", original),
+                ("date", &date),
+                ("timestamp", &timestamp),
+            ],
+        )
+    }
+}
+
+/// Checks that a table or dataset name pattern only uses known placeholders.
+pub fn check_name_pattern(pattern: &str) -> Result<(), String> {
+    let values: Vec<(&str, &str)> = TABLE_NAME_PLACEHOLDERS
+        .iter()
+        .map(|name| (*name, "x"))
+        .collect();
+    fill_placeholders(pattern, &values)
+        .map(|_| ())
+        .map_err(|e| format!("name pattern '{}': {}", pattern, e))
+}
+
+/// Time stamped in a generated file name such as `base_file_2025-06-01_10-00.py`, read
+/// from the label at the end of the file stem.
+pub fn generation_time_of_file(path: &std::path::Path) -> Option<DateTime<Utc>> {
+    let stem = path.file_stem()?.to_str()?;
+    let label = stem.get(stem.len().checked_sub(16)?..)?;
+    chrono::NaiveDateTime::parse_from_str(label, TIMESTAMP_LABEL_FORMAT)
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// Time to stamp the output with: `explicit` (`--timestamp`), else `SOURCE_DATE_EPOCH`
+/// (seconds since the epoch), else now. Fixing it makes the output byte-reproducible.
+pub fn generation_time(explicit: Option<DateTime<FixedOffset>>) -> Result<DateTime<Utc>, String> {
+    if let Some(timestamp) = explicit {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .ok_or_else(|| format!("invalid SOURCE_DATE_EPOCH '{}'", epoch)),
+        Err(_) => Ok(Utc::now()),
+    }
+}
+
+/// Resolves the processed operations into the steps every output format renders.
+///
+/// Operations whose type has a generator in `registry` become steps; GET_TABLE provides
+/// the dataset id and the table name and SAVE_TABLE the deleted columns of the loader.
+/// When the session never exported the table, a default JSON export is appended.
+pub fn build_pipeline(
+    operations: &[HashMap<String, String>],
+    table_file: &str,
+    document_schema: bool,
+    registry: &GeneratorRegistry,
+    generated_at: DateTime<Utc>,
+) -> Pipeline {
+    let dataset_id = match operations.first().and_then(|op| op.get("DatasetId")) {
+        Some(dataset_id) => dataset_id.clone(),
+        None => {
+            eprintln!(
+                "No dataset id found in the operations, using default dataset ID {}",
+                DEFAULT_DATASET_ID
+            );
+            DEFAULT_DATASET_ID.to_string()
+        }
+    };
+    let logged_name = |key: &str| {
+        operations
+            .iter()
+            .find(|op| op.get("OpType").map(|s| s.as_str()) == Some("GET_TABLE"))
+            .and_then(|op| op.get(key))
+            .filter(|name| !name.trim().is_empty())
+            .cloned()
+    };
+    let deleted_columns = operations
+        .iter()
+        .find(|op| op.get("OpType") == Some(&"SAVE_TABLE".to_string()))
+        .and_then(|op| op.get("DeletedCols"))
+        .map(|deleted_cols_str| parse_deleted_columns(deleted_cols_str))
+        .unwrap_or_default();
+
+    let mut schema_steps = if document_schema {
+        let header = read_csv_sample(table_file, 0)
+            .ok()
+            .map(|(header, _)| header);
+        simulate_schema(operations, header.as_deref(), registry.extender_mappings())
+    } else {
+        Vec::new()
+    };
+    let initial_schema = if schema_steps.is_empty() {
+        None
+    } else {
+        Some(schema_steps.remove(0))
+    };
+
+    let mut steps: Vec<PipelineStep> = Vec::new();
+    for (index, operation) in operations.iter().enumerate() {
+        let op_type = operation.get("OpType").map_or("UNKNOWN", |s| s.as_str());
+        let generator = match registry.get(op_type) {
+            Some(generator) => generator,
+            None => {
+                if !matches!(op_type, "GET_TABLE" | "SAVE_TABLE") {
+                    eprintln!("No generator for {} operations, not replayed", op_type);
+                }
+                continue;
+            }
+        };
+        let expected_schema = schema_steps
+            .iter()
+            .position(|step| step.operation_index == Some(index))
+            .map(|i| schema_steps.remove(i));
+        let step = new_step(
+            steps.len() + 1,
+            index + 1,
+            operation.clone(),
+            generator,
+            expected_schema,
+        );
+        steps.push(step);
+    }
+
+    let has_export_operation = operations
+        .iter()
+        .any(|op| op.get("OpType") == Some(&"EXPORT".to_string()));
+    if let (false, Some(generator)) = (has_export_operation, registry.get("EXPORT")) {
+        println!("No export operation in the session, adding default JSON export");
+        let operation = HashMap::from([
+            ("OpType".to_string(), "EXPORT".to_string()),
+            (
+                "AdditionalData".to_string(),
+                DEFAULT_EXPORT_DATA.to_string(),
+            ),
+        ]);
+        let mut step = new_step(
+            steps.len() + 1,
+            operations.len() + 1,
+            operation,
+            generator,
+            None,
+        );
+        step.generated = true;
+        steps.push(step);
+    }
+
+    let mut pipeline = Pipeline {
+        base_url: std::env::var("BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string()),
+        credentials: CredentialStrategy::default(),
+        loader: TableLoader {
+            mode: LoaderMode::default(),
+            table_id: None,
+            table_file: table_file.to_string(),
+            dataset_id,
+            dataset_name: None,
+            table_name: String::new(),
+            logged_table_name: logged_name("TableName"),
+            logged_dataset_name: logged_name("DatasetName"),
+            deleted_columns,
+            operations: loader_operations(operations),
+        },
+        initial_schema,
+        steps,
+        generated_at,
+    };
+    pipeline.loader.table_name = match &pipeline.loader.logged_table_name {
+        Some(name) => name.clone(),
+        None => DEFAULT_TABLE_NAME.replace("{timestamp}", &pipeline.timestamp_label()),
+    };
+    pipeline
+}
+
+/// GET_TABLE and SAVE_TABLE of the session, once each: consolidated or hand-written logs
+/// may repeat the same line.
+fn loader_operations(operations: &[HashMap<String, String>]) -> Vec<HashMap<String, String>> {
+    let mut loader: Vec<HashMap<String, String>> = Vec::new();
+    for op in operations {
+        let op_type = op.get("OpType").map(|s| s.as_str());
+        if matches!(op_type, Some("GET_TABLE" | "SAVE_TABLE")) && !loader.contains(op) {
+            loader.push(op.clone());
+        }
+    }
+    loader
+}
+
+fn new_step(
+    number: usize,
+    position: usize,
+    operation: HashMap<String, String>,
+    generator: Arc<dyn OperationGenerator>,
+    expected_schema: Option<SchemaStep>,
+) -> PipelineStep {
+    let (params, skipped) = match generator.parse(&operation) {
+        Ok(params) => (params, None),
+        Err(reason) => {
+            eprintln!(
+                "Skipping {} on column '{}': {}",
+                generator.op_type(),
+                operation.get("ColumnName").map_or("N/A", |s| s.as_str()),
+                reason
+            );
+            (Value::Null, Some(reason))
+        }
+    };
+    let step = PipelineStep {
+        number,
+        position,
+        op_type: generator.op_type().to_string(),
+        column: operation.get("ColumnName").cloned(),
+        timestamp: operation.get("timestamp").cloned(),
+        operation,
+        params,
+        skipped,
+        generated: false,
+        expected_schema,
+        service_description: None,
+        generator,
+    };
+    if step.skipped.is_none() {
+        for warning in step.generator.validate(&step) {
+            eprintln!("Warning: OPERATION_{}: {}", step.number, warning);
+        }
+    }
+    step
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline(get_table: &[(&str, &str)]) -> Pipeline {
+        let mut operation: HashMap<String, String> = get_table
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        operation.insert("OpType".to_string(), "GET_TABLE".to_string());
+        let generated_at = DateTime::from_timestamp(1_748_772_000, 0).unwrap();
+        build_pipeline(
+            &[operation],
+            "table.csv",
+            false,
+            &GeneratorRegistry::with_builtin_generators(),
+            generated_at,
+        )
+    }
+
+    #[test]
+    fn names_default_to_the_logged_ones_and_fill_patterns() {
+        let mut logged = pipeline(&[
+            ("DatasetId", "2"),
+            ("TableName", "cities"),
+            ("DatasetName", "demo"),
+        ]);
+        assert_eq!(logged.loader.table_name, "cities");
+        assert_eq!(logged.loader.dataset_name, None);
+        logged.set_table_name("{original}-{date}").unwrap();
+        assert_eq!(logged.loader.table_name, "cities-2025-06-01");
+        logged.set_dataset_name("{original}_prod").unwrap();
+        assert_eq!(logged.loader.dataset_name.as_deref(), Some("demo_prod"));
+
+        let mut unnamed = pipeline(&[("DatasetId", "2")]);
+        assert_eq!(unnamed.loader.table_name, "test_table-2025-06-01_10-00");
+        unnamed.set_table_name("{original}-{timestamp}").unwrap();
+        assert_eq!(unnamed.loader.table_name, "test_table-2025-06-01_10-00");
+        assert!(unnamed.set_dataset_name("{original}").is_err());
+        assert!(check_name_pattern("{original}-{when}").is_err());
+    }
+
+    fn operation(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn build(operations: &[HashMap<String, String>]) -> Pipeline {
+        build_pipeline(
+            operations,
+            "table.csv",
+            false,
+            &GeneratorRegistry::with_builtin_generators(),
+            DateTime::from_timestamp(1_748_772_000, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn operations_become_numbered_steps_with_a_default_export() {
+        let save_table = operation(&[("OpType", "SAVE_TABLE"), ("DeletedCols", "Notes")]);
+        let operations = [
+            operation(&[("OpType", "GET_TABLE"), ("DatasetId", "2")]),
+            operation(&[
+                ("OpType", "RECONCILIATION"),
+                ("ColumnName", "City"),
+                ("Reconciler", "wikidataOpenRefine"),
+                ("timestamp", "2025-06-01T10:01:00+00:00"),
+            ]),
+            // No Reconciler: kept as a step, but skipped
+            operation(&[("OpType", "RECONCILIATION"), ("ColumnName", "Country")]),
+            // No generator: not a step, but it still takes a position
+            operation(&[("OpType", "FILTER"), ("ColumnName", "City")]),
+            operation(&[
+                ("OpType", "EXTENSION"),
+                ("ColumnName", "City"),
+                ("Extender", "wikidataPropertySPARQL"),
+                ("AdditionalData", r#"{"properties":"P17"}"#),
+            ]),
+            save_table.clone(),
+            save_table,
+        ];
+        let pipeline = build(&operations);
+
+        let steps: Vec<(usize, usize, &str, Option<&str>)> = pipeline
+            .steps
+            .iter()
+            .map(|step| {
+                (
+                    step.number,
+                    step.position,
+                    step.op_type.as_str(),
+                    step.column.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                (1, 2, "RECONCILIATION", Some("City")),
+                (2, 3, "RECONCILIATION", Some("Country")),
+                (3, 5, "EXTENSION", Some("City")),
+                (4, 8, "EXPORT", None),
+            ]
+        );
+        assert_eq!(
+            pipeline.steps[0].title(),
+            "Reconciliation for column City by wikidataOpenRefine"
+        );
+        assert_eq!(
+            pipeline.steps[0].timestamp.as_deref(),
+            Some("2025-06-01T10:01:00+00:00")
+        );
+        assert!(pipeline.steps[0].skipped.is_none());
+
+        let skipped = &pipeline.steps[1];
+        assert_eq!(skipped.params, Value::Null);
+        assert_eq!(
+            skipped.title(),
+            "RECONCILIATION on column Country skipped (no Reconciler)"
+        );
+
+        let export = &pipeline.steps[3];
+        assert!(export.generated);
+        assert_eq!(export.params["format"], "json");
+        assert_eq!(export.params["output_file"], "results.json");
+        assert_eq!(export.title(), "Export as JSON (Default)");
+
+        assert_eq!(pipeline.loader.dataset_id, "2");
+        assert_eq!(pipeline.loader.deleted_columns, ["Notes"]);
+        // A SAVE_TABLE repeated in the log is kept once
+        assert_eq!(pipeline.loader.operations.len(), 2);
+    }
+
+    #[test]
+    fn a_logged_export_replaces_the_default_one() {
+        let pipeline = build(&[operation(&[
+            ("OpType", "EXPORT"),
+            ("AdditionalData", r#"{"format":"xml"}"#),
+        ])]);
+        assert_eq!(pipeline.steps.len(), 1);
+        assert!(!pipeline.steps[0].generated);
+        assert_eq!(
+            pipeline.steps[0].skipped.as_deref(),
+            Some("unsupported export format: xml")
+        );
+        // Without a GET_TABLE the default dataset is used
+        assert_eq!(pipeline.loader.dataset_id, DEFAULT_DATASET_ID);
+        assert!(pipeline.loader.operations.is_empty());
+    }
+}
@@ -1,186 +1,127 @@
 use crate::code_helper::{
-    get_base_export_operation, get_base_extension_operation, get_base_modification_operation,
-    get_base_propagation_operation, get_base_python_dataset_loader,
-    get_base_python_dataset_loader_with_column_deletion, get_base_python_file_loader_code,
-    get_base_reconciliation_operation,
+    get_base_python_dataset_loader, get_base_python_file_loader_code, python_comment, Templates,
 };
-use crate::operations::{parse_deleted_columns, parse_json};
-use serde_json::Value;
+use crate::credentials::{warn_about_secrets, CredentialStrategy};
+use crate::generators::OutputFormat;
+use crate::metadata::OperationMetadata;
+use crate::output::{write_output, Destination};
+use crate::pipeline::{Pipeline, PipelineStep, TableLoader};
+use crate::schema::{describe_columns, SchemaStep};
 use std::{
     collections::HashMap,
-    fs::{File, OpenOptions},
     io::{Error, Write},
     path::Path,
 };
 
-pub struct Args {
-    pub table_file: String,
-}
-
-pub fn create_base_file(path: &str) -> Result<String, Error> {
-    let path = Path::new(path);
-    let mut file = get_file_writer(path)?;
-    let formatted_code = get_base_python_file_loader_code();
-    file.write_all(formatted_code.as_bytes())?;
-    Ok(path.to_string_lossy().to_string())
+pub fn write_base_file(
+    file: &mut impl Write,
+    base_url: &str,
+    credentials: CredentialStrategy,
+    templates: &Templates,
+) -> Result<(), Error> {
+    let formatted_code =
+        get_base_python_file_loader_code(templates, base_url, credentials).map_err(Error::other)?;
+    file.write_all(formatted_code.as_bytes())
 }
 
 pub fn write_table_loader(
-    file_path_str: &str,
-    table_path_str: &str,
-    table_name: &str,
-    dataset_id: &str,
-    deleted_columns: Option<Vec<String>>,
+    file: &mut impl Write,
+    loader: &TableLoader,
+    templates: &Templates,
 ) -> Result<(), Error> {
-    let table_path = Path::new(table_path_str);
-    let file_path = Path::new(file_path_str);
+    let table_path = Path::new(&loader.table_file);
 
-    let formatted_code = if table_path.exists() {
-        match deleted_columns {
-            Some(cols) if !cols.is_empty() => get_base_python_dataset_loader_with_column_deletion(
-                table_path_str,
-                dataset_id,
-                table_name,
-                cols,
-            ),
-            _ => get_base_python_dataset_loader(table_path_str, dataset_id, table_name),
-        }
-    } else {
+    if !table_path.exists() {
         // If table file doesn't exist, still generate the loader code but warn the user
         eprintln!(
             "Warning: Table file '{}' does not exist. Generating code with placeholder path.",
-            table_path_str
+            loader.table_file
         );
-        match deleted_columns {
-            Some(cols) if !cols.is_empty() => get_base_python_dataset_loader_with_column_deletion(
-                table_path_str,
-                dataset_id,
-                table_name,
-                cols,
-            ),
-            _ => get_base_python_dataset_loader(table_path_str, dataset_id, table_name),
-        }
-    };
-
-    //write to file
-    let mut file = get_file_writer(file_path)?;
-    file.write_all(formatted_code.as_bytes())?;
-    Ok(())
-}
-
-pub fn create_extension_operation(
-    file_path_str: &str,
-    column_name: &str,
-    extender_id: &str,
-    properties: Vec<String>,
-    additional_params: Option<Vec<String>>,
-) -> Result<(), Error> {
-    let file_path = Path::new(file_path_str);
-    let mut file = get_file_writer(file_path)?;
+    }
+    let formatted_code = get_base_python_dataset_loader(templates, loader).map_err(Error::other)?;
 
-    let formatted_code =
-        get_base_extension_operation(column_name, properties, additional_params, extender_id);
+    // GET_TABLE and SAVE_TABLE are embedded as operation 0, so the loader can be rebuilt
+    for operation in &loader.operations {
+        writeln!(file, "{}", OperationMetadata::new(0, operation).to_line())?;
+    }
     file.write_all(formatted_code.as_bytes())?;
     Ok(())
 }
 
-pub fn create_reconciliation_operation(
-    file_path_str: &str,
-    column_name: &str,
-    reconciliator_id: &str,
-    additional_columns: Option<Vec<String>>,
+fn write_step_code(
+    file: &mut impl Write,
+    step: &PipelineStep,
+    templates: &Templates,
 ) -> Result<(), Error> {
-    let file_path = Path::new(file_path_str);
-    let mut file = get_file_writer(file_path)?;
-
-    let formatted_code =
-        get_base_reconciliation_operation(column_name, additional_columns, reconciliator_id);
-    file.write_all(formatted_code.as_bytes())?;
-    Ok(())
-}
-pub fn create_propagation_operation(
-    file_path_str: &str,
-    column_name: &str,
-    additional_data: &Value,
-) -> Result<(), Error> {
-    let file_path = Path::new(file_path_str);
-    let mut file = get_file_writer(file_path)?;
-
-    let formatted_code = get_base_propagation_operation(column_name, Some(additional_data));
-    file.write_all(formatted_code.as_bytes())?;
-    Ok(())
+    let code = step
+        .code(OutputFormat::Python, templates)
+        .map_err(Error::other)?
+        .map(|code| checkpointed(step.number, &code));
+    match (code, step.heading()) {
+        (Some(code), Some(heading)) => {
+            writeln!(file, "\n# {}\n{}", python_comment(&heading), code)
+        }
+        (Some(code), None) => file.write_all(code.as_bytes()),
+        (None, _) => writeln!(
+            file,
+            "# Skipped: {}",
+            python_comment(step.skipped.as_deref().unwrap_or("no code generated"))
+        ),
+    }
 }
 
-pub fn create_modification_operation(
-    file_path_str: &str,
-    column_name: &str,
-    modifier_name: &str,
-    props: &Value,
-) -> Result<(), Error> {
-    let file_path = Path::new(file_path_str);
-    let mut file = get_file_writer(file_path)?;
-
-    let formatted_code = get_base_modification_operation(column_name, modifier_name, props);
-    file.write_all(formatted_code.as_bytes())?;
-    Ok(())
-}
-fn get_file_writer(file_path: &Path) -> Result<File, Error> {
-    if file_path.exists() {
-        OpenOptions::new().write(true).append(true).open(file_path)
-    } else {
-        File::create(file_path)
+/// The code of a step under `if run_step(N):`, so that `--resume` and the step selection
+/// of the script can skip it. `finish_step` records it in the checkpoint file, or stops the
+/// script when the step set `step_error`.
+fn checkpointed(number: usize, code: &str) -> String {
+    let body = code.trim_start_matches('\n');
+    let mut block = format!(
+        "{}if run_step({}):\n    step_error = None\n",
+        &code[..code.len() - body.len()],
+        number
+    );
+    for line in body.trim_end().lines() {
+        if !line.trim().is_empty() {
+            block.push_str("    ");
+            block.push_str(line);
+        }
+        block.push('\n');
     }
+    block.push_str(&format!("    finish_step({}, step_error)\n", number));
+    block
 }
 
 fn write_operation_separator(
-    file_path_str: &str,
+    file: &mut impl Write,
     operation: &HashMap<String, String>,
-    _operation_index: usize,
     displayed_operation_number: usize,
 ) -> Result<(), Error> {
-    let file_path = Path::new(file_path_str);
-    let mut file = get_file_writer(file_path)?;
-
-    // Create a parsable comment separator with operation metadata
+    // A readable header, then the operation as one JSON line that tools can parse back
     let separator = format!(
-        "\n# =============================================================================\n# OPERATION_{}: {}\n# METADATA: {{\n",
+        "\n# =============================================================================\n# OPERATION_{}: {}\n{}\n# =============================================================================\n\n",
         displayed_operation_number,
-        operation.get("OpType").unwrap_or(&"UNKNOWN".to_string())
+        python_comment(operation.get("OpType").map_or("UNKNOWN", |s| s.as_str())),
+        OperationMetadata::new(displayed_operation_number, operation).to_line()
     );
+    file.write_all(separator.as_bytes())
+}
 
-    file.write_all(separator.as_bytes())?;
-
-    // Write all operation fields as parsable comments
-    for (key, value) in operation {
-        let metadata_line = format!("#   \"{}\": \"{}\",\n", key, value.replace("\"", "\\\""));
-        file.write_all(metadata_line.as_bytes())?;
-    }
-
+fn write_expected_schema(file: &mut impl Write, step: &SchemaStep) -> Result<(), Error> {
     file.write_all(
-        b"# }\n# =============================================================================\n\n",
+        format!(
+            "\n# EXPECTED SCHEMA AFTER OPERATION_{}:\n",
+            step.operation_number
+        )
+        .as_bytes(),
     )?;
-
+    for line in describe_columns(&step.columns) {
+        file.write_all(format!("#   - {}\n", python_comment(&line)).as_bytes())?;
+    }
     Ok(())
 }
 
-fn write_operation_summary(
-    file_path_str: &str,
-    operations: &[HashMap<String, String>],
-) -> Result<(), Error> {
-    let file_path = Path::new(file_path_str);
-    let mut file = get_file_writer(file_path)?;
-
-    // Filter operations to only include RECONCILIATION, EXTENSION, PROPAGATE_TYPE and MODIFICATION
-    let displayed_operations: Vec<&HashMap<String, String>> = operations
-        .iter()
-        .filter(|op| {
-            let op_type = op.get("OpType").map_or("UNKNOWN", |s| s.as_str());
-            op_type == "RECONCILIATION"
-                || op_type == "EXTENSION"
-                || op_type == "PROPAGATE_TYPE"
-                || op_type == "MODIFICATION"
-        })
-        .collect();
+fn write_operation_summary(file: &mut impl Write, steps: &[PipelineStep]) -> Result<(), Error> {
+    let displayed_steps: Vec<&PipelineStep> = steps.iter().filter(|s| s.in_summary()).collect();
 
     file.write_all(
         b"\n# =============================================================================\n",
@@ -189,18 +130,16 @@ fn write_operation_summary(
     file.write_all(
         b"# =============================================================================\n",
     )?;
-    file.write_all(format!("# Total operations: {}\n", displayed_operations.len()).as_bytes())?;
-
-    for (_index, operation) in displayed_operations.iter().enumerate() {
-        let op_type = operation.get("OpType").map_or("UNKNOWN", |s| s.as_str());
-        let column_name = operation.get("ColumnName").map_or("N/A", |s| s.as_str());
-        let timestamp = operation.get("timestamp").map_or("N/A", |s| s.as_str());
+    file.write_all(format!("# Total operations: {}\n", displayed_steps.len()).as_bytes())?;
 
+    for step in displayed_steps {
         // Use a bullet style (dot) instead of numbering
         file.write_all(
             format!(
-                "# - {} on column '{}' at {}\n",
-                op_type, column_name, timestamp
+                "# - {} {} at {}\n",
+                python_comment(&step.op_type),
+                python_comment(&step.summary()),
+                python_comment(step.timestamp.as_deref().unwrap_or("N/A"))
             )
             .as_bytes(),
         )?;
@@ -214,368 +153,56 @@ fn write_operation_summary(
 }
 
 pub fn create_python(
-    operations: Vec<HashMap<String, String>>,
-    args: Args,
+    pipeline: &Pipeline,
+    templates: &Templates,
+    destination: &Destination,
 ) -> Result<String, std::io::Error> {
-    let current_timestamp = chrono::Utc::now().format("%Y-%m-%d_%H-%M").to_string();
-    let table_name = format!("test_table-{}", current_timestamp);
-    let path = format!("./base_file_{}.py", current_timestamp);
-    match create_base_file(path.as_str()) {
-        Ok(file_path) => {
-            println!("Base file created at: {}", file_path);
-        }
-        Err(e) => eprintln!("Error creating base file: {}", e),
+    let path = destination
+        .resolve(pipeline, OutputFormat::Python)
+        .map_err(Error::other)?;
+    // Rendered in full first: a script that stops halfway would fail only when it is run
+    let script = render_python(pipeline, templates)?;
+    write_output(&path, script.as_bytes(), destination.overwrite)?;
+    let path = path.display().to_string();
+    warn_about_secrets(&path, &script);
+    println!("Base file created at: {}", path);
+    println!("Table loader written successfully.");
+    for step in &pipeline.steps {
+        println!("{} operation created successfully.", step.op_type);
     }
+    Ok(path)
+}
 
-    // Debug: Print only operations with DeletedCols
-    operations.iter().for_each(|op| {
-        if let Some(deleted_cols) = op.get("DeletedCols") {
-            println!(
-                "  Parsed columns: {:?}",
-                parse_deleted_columns(deleted_cols)
-            );
-        }
-    });
-
-    // Look for deleted columns in SAVE_TABLE operations
-    let deleted_columns = operations
-        .iter()
-        .find(|op| op.get("OpType") == Some(&"SAVE_TABLE".to_string()))
-        .and_then(|op| op.get("DeletedCols"))
-        .map(|deleted_cols_str| parse_deleted_columns(deleted_cols_str))
-        .filter(|cols| !cols.is_empty());
+/// The script of the pipeline; template errors stop the generation.
+pub fn render_python(pipeline: &Pipeline, templates: &Templates) -> Result<String, Error> {
+    let mut script = Vec::new();
+    write_python(&mut script, pipeline, templates)?;
+    String::from_utf8(script).map_err(Error::other)
+}
 
-    let default_dataset_id = "0".to_string();
-    let current_dataset_id = if !operations.is_empty() {
-        operations[0]
-            .get("DatasetId")
-            .unwrap_or(&default_dataset_id)
-    } else {
-        println!("No operations found, using default dataset ID");
-        &default_dataset_id
-    };
-    match write_table_loader(
-        path.as_str(),
-        &args.table_file,
-        table_name.as_str(),
-        current_dataset_id,
-        deleted_columns,
-    ) {
-        Ok(_) => println!("Table loader written successfully."),
-        Err(e) => eprintln!("Error writing table loader: {}", e),
+/// Writes every block of the script.
+fn write_python(
+    file: &mut impl Write,
+    pipeline: &Pipeline,
+    templates: &Templates,
+) -> Result<(), Error> {
+    write_base_file(file, &pipeline.base_url, pipeline.credentials, templates)?;
+    write_table_loader(file, &pipeline.loader, templates)?;
+    if let Some(step) = &pipeline.initial_schema {
+        write_expected_schema(file, step)?;
     }
 
-    // Check if there's any EXPORT operation in the operations list
-    let has_export_operation = operations
-        .iter()
-        .any(|op| op.get("OpType") == Some(&"EXPORT".to_string()));
-
-    for (index, operation) in operations.iter().enumerate() {
-        let operation_type = operation.get("OpType").unwrap();
-
-        match operation_type.as_str() {
-            "RECONCILIATION" | "EXTENSION" | "PROPAGATE_TYPE" | "EXPORT" | "MODIFICATION" => {
-                // Only write separator and generate code for RECONCILIATION, EXTENSION, PROPAGATE_TYPE, EXPORT and MODIFICATION operations
-                // Count displayed operations by filtering up to current position
-                let displayed_operation_number = operations[..=index]
-                    .iter()
-                    .filter(|op| {
-                        let op_type = op.get("OpType").map_or("UNKNOWN", |s| s.as_str());
-                        op_type == "RECONCILIATION"
-                            || op_type == "EXTENSION"
-                            || op_type == "PROPAGATE_TYPE"
-                            || op_type == "EXPORT"
-                            || op_type == "MODIFICATION"
-                    })
-                    .count();
-
-                // Write operation separator with metadata
-                if let Err(e) = write_operation_separator(
-                    &path,
-                    operation,
-                    index + 1,
-                    displayed_operation_number,
-                ) {
-                    eprintln!("Error writing operation separator: {}", e);
-                }
-            }
-            _ => {
-                // Skip writing separators for other operation types
-                continue;
-            }
+    for step in &pipeline.steps {
+        // The default export is appended after the replayed operations without a separator
+        if !step.generated {
+            write_operation_separator(file, &step.operation, step.number)?;
         }
-
-        match operation_type.as_str() {
-            "RECONCILIATION" => {
-                let reconciler_id = operation.get("Reconciler").unwrap();
-                let col_name = operation.get("ColumnName").unwrap();
-
-                // Parse additional data to check for additionalColumns
-                let additional_columns =
-                    if let Some(additional_data_str) = operation.get("AdditionalData") {
-                        if let Some(additional_data) = parse_json(additional_data_str) {
-                            if let Some(additional_columns_obj) =
-                                additional_data.get("additionalColumns")
-                            {
-                                if let Some(obj) = additional_columns_obj.as_object() {
-                                    let column_names: Vec<String> =
-                                        obj.keys().map(|k| format!("\"{}\"", k)).collect();
-                                    if !column_names.is_empty() {
-                                        println!(
-                                            "Found additionalColumns in reconciliation: {:?}",
-                                            column_names
-                                        );
-                                        Some(column_names)
-                                    } else {
-                                        None
-                                    }
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    };
-
-                let res = create_reconciliation_operation(
-                    path.as_str(),
-                    col_name,
-                    reconciler_id,
-                    additional_columns,
-                );
-                match res {
-                    Ok(_) => {
-                        println!("Reconciliation operation created successfully.")
-                    }
-                    Err(e) => {
-                        eprintln!("Error creating reconciliation operation: {}", e)
-                    }
-                }
-            }
-            "PROPAGATE_TYPE" => {
-                let col_name = operation.get("ColumnName").unwrap();
-                let additional_data = parse_json(operation.get("AdditionalData").unwrap()).unwrap();
-
-                if let Some(data_map) = additional_data.as_object() {
-                    let value = Value::Object(data_map.clone());
-                    let res = create_propagation_operation(path.as_str(), col_name, &value);
-                    match res {
-                        Ok(_) => println!("Propagation operation created successfully."),
-                        Err(e) => eprintln!("Error creating propagation operation: {}", e),
-                    }
-                } else {
-                    eprintln!("Additional data for PROPAGATE_TYPE is not an object");
-                }
-            }
-            "EXTENSION" => {
-                // Handle extension operation
-                // Here you can implement the logic for handling extension operations
-                let extender_id = operation.get("Extender").unwrap();
-                let col_name = operation.get("ColumnName").unwrap();
-
-                let additional_data = parse_json(operation.get("AdditionalData").unwrap()).unwrap();
-
-                // Look for "property" array in additional data and join values with whitespaces
-                // If "property" array exists, use its values; otherwise fallback to "properties" string
-                let mut props: Vec<String> = additional_data
-                    .get("property")
-                    .and_then(|v| v.as_array())
-                    .map(|arr| {
-                        let property_values: Vec<String> = arr.iter()
-                            .filter_map(|item| item.as_str())
-                            .map(|s| s.to_string())
-                            .collect();
-                        println!("Found 'property' array in additional data: {:?}", property_values);
-                        property_values
-                    })
-                    .unwrap_or_else(|| {
-                        // Fallback to existing "properties" field for backward compatibility
-                        let fallback_props: Vec<String> = additional_data
-                            .get("properties")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("")
-                            .split(' ')
-                            .filter(|s| !s.is_empty())
-                            .map(|s| s.to_string())
-                            .collect();
-                        if !fallback_props.is_empty() {
-                            println!("Using fallback 'properties' field: {:?}", fallback_props);
-                        } else {
-                            println!("No 'property' array or 'properties' field found in additional data");
-                        }
-                        fallback_props
-                    });
-
-                // Check for weatherParams and add them to properties
-                if let Some(weather_params) = additional_data.get("weatherParams") {
-                    if let Some(weather_array) = weather_params.as_array() {
-                        let weather_props: Vec<String> = weather_array
-                            .iter()
-                            .filter_map(|item| item.as_str())
-                            .map(|s| s.to_string())
-                            .collect();
-                        props.extend(weather_props);
-                        println!("Added weatherParams to properties: {:?}", weather_params);
-                    }
-                }
-
-                // Check for labels and add them to properties
-                if let Some(labels) = additional_data.get("labels") {
-                    if let Some(labels_array) = labels.as_array() {
-                        let label_props: Vec<String> = labels_array
-                            .iter()
-                            .filter_map(|item| item.as_str())
-                            .map(|s| s.to_string())
-                            .collect();
-                        props.extend(label_props);
-                        println!("Added labels to properties: {:?}", labels);
-                    }
-                }
-
-                // Check for dates field and extract date column name for other_params
-                let mut other_params = Vec::new();
-                if let Some(dates) = additional_data.get("dates") {
-                    if let Some(dates_obj) = dates.as_object() {
-                        // Get the first key and extract the third element from its array
-                        if let Some((first_key, first_value)) = dates_obj.iter().next() {
-                            if let Some(date_array) = first_value.as_array() {
-                                if date_array.len() > 2 {
-                                    if let Some(date_column_name) = date_array[2].as_str() {
-                                        other_params.push(format!(
-                                            "\"date_column_name\": \"{}\"",
-                                            date_column_name
-                                        ));
-                                        println!(
-                                            "Found date column name: {} from key: {}",
-                                            date_column_name, first_key
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                let additional_params = if other_params.is_empty() {
-                    None
-                } else {
-                    Some(other_params)
-                };
-
-                match create_extension_operation(
-                    path.as_str(),
-                    col_name,
-                    extender_id,
-                    props,
-                    additional_params,
-                ) {
-                    Ok(_) => {
-                        println!("Extension operation created successfully.")
-                    }
-                    Err(e) => {
-                        eprintln!("Error creating extension operation: {}", e)
-                    }
-                }
-            }
-            "EXPORT" => {
-                // Handle export operation
-                if let Some(additional_data_str) = operation.get("AdditionalData") {
-                    if let Some(additional_data) = parse_json(additional_data_str) {
-                        if let Some(format) = additional_data.get("format").and_then(|f| f.as_str())
-                        {
-                            let output_file = additional_data
-                                .get("outputFile")
-                                .and_then(|f| f.as_str())
-                                .unwrap_or("export_output");
-
-                            if let Some(export_code) =
-                                get_base_export_operation(format, output_file)
-                            {
-                                let file_path = Path::new(&path);
-                                match get_file_writer(file_path) {
-                                    Ok(mut file) => {
-                                        use std::io::Write;
-                                        if let Err(e) = writeln!(file, "\n{}", export_code) {
-                                            eprintln!("Error writing export operation: {}", e);
-                                        } else {
-                                            println!("Export operation created successfully for format: {}", format);
-                                        }
-                                    }
-                                    Err(e) => {
-                                        eprintln!("Error getting file writer: {}", e);
-                                    }
-                                }
-                            } else {
-                                println!(
-                                    "Unsupported export format: {}, skipping export operation",
-                                    format
-                                );
-                            }
-                        } else {
-                            eprintln!("No format specified in EXPORT AdditionalData");
-                        }
-                    } else {
-                        eprintln!("Could not parse AdditionalData for EXPORT operation");
-                    }
-                } else {
-                    eprintln!("No AdditionalData found for EXPORT operation");
-                }
-            }
-            "MODIFICATION" => {
-                // Handle modification operation
-                let modifier_name = operation.get("Modifier").unwrap();
-                let col_name = operation.get("ColumnName").unwrap();
-
-                let additional_data = parse_json(operation.get("AdditionalData").unwrap()).unwrap();
-
-                match create_modification_operation(
-                    path.as_str(),
-                    col_name,
-                    modifier_name,
-                    &additional_data,
-                ) {
-                    Ok(_) => {
-                        println!("Modification operation created successfully.")
-                    }
-                    Err(e) => {
-                        eprintln!("Error creating modification operation: {}", e)
-                    }
-                }
-            }
-            _ => {
-                println!("Unknown Operation type: {}", operation_type);
-                // Here you can handle other operation types as needed
-            }
-        }
-    }
-
-    // Check if no export operation was found in the logs, add default JSON export
-    if !has_export_operation {
-        println!("No export code generated, adding default JSON export");
-        if let Some(default_export) = get_base_export_operation("json", "results.json") {
-            let file_path = Path::new(&path);
-            match get_file_writer(file_path) {
-                Ok(mut file) => {
-                    use std::io::Write;
-                    if let Err(e) = writeln!(file, "\n# Default Export (JSON)\n{}", default_export)
-                    {
-                        eprintln!("Error writing default export operation: {}", e);
-                    }
-                }
-                Err(e) => eprintln!("Error getting file writer for default export: {}", e),
-            }
+        write_step_code(file, step, templates)?;
+        if let Some(schema_step) = &step.expected_schema {
+            write_expected_schema(file, schema_step)?;
         }
     }
 
     // Write operation summary at the end of the file
-    if let Err(e) = write_operation_summary(&path, &operations) {
-        eprintln!("Error writing operation summary: {}", e);
-    }
-
-    Ok(path)
+    write_operation_summary(file, &pipeline.steps)
 }
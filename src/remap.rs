@@ -0,0 +1,213 @@
+use crate::{catalog::ServiceKind, pipeline::Pipeline};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+/// Service and dataset ids to replace when replaying on another backend, from the
+/// `[remap]` section of `semtparser.toml` and `--map-service` / `--map-dataset`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Remap {
+    /// Reconciler, extender and modifier ids, old to new
+    pub services: BTreeMap<String, String>,
+    /// Dataset ids, old to new
+    pub datasets: BTreeMap<String, String>,
+}
+
+/// One id replaced in the pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Substitution {
+    /// `OPERATION_N (TYPE)`, or `loader` for the table upload
+    pub target: String,
+    /// Log field that was rewritten, e.g. `Reconciler`
+    pub field: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl fmt::Display for Substitution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {} -> {}",
+            self.target, self.field, self.from, self.to
+        )
+    }
+}
+
+/// Parses an `old=new` pair of `--map-service` / `--map-dataset`.
+pub fn parse_id_mapping(mapping: &str) -> Result<(String, String), String> {
+    match mapping.split_once('=') {
+        Some((old, new)) if !old.trim().is_empty() && !new.trim().is_empty() => {
+            Ok((old.trim().to_string(), new.trim().to_string()))
+        }
+        _ => Err(format!("expected OLD=NEW, got '{}'", mapping)),
+    }
+}
+
+impl Remap {
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty() && self.datasets.is_empty()
+    }
+
+    /// Rewrites the service and dataset ids of the pipeline before it is rendered, and
+    /// returns every substitution made.
+    ///
+    /// Only the ids change: the parameters were read from the log with the original
+    /// service's format, which is what its `AdditionalData` follows.
+    pub fn apply(&self, pipeline: &mut Pipeline) -> Vec<Substitution> {
+        let mut substitutions = Vec::new();
+        if let Some(new) = self.datasets.get(&pipeline.loader.dataset_id) {
+            substitutions.push(Substitution {
+                target: "loader".to_string(),
+                field: "DatasetId".to_string(),
+                from: std::mem::replace(&mut pipeline.loader.dataset_id, new.clone()),
+                to: new.clone(),
+            });
+        }
+        // The logged GET_TABLE and SAVE_TABLE are embedded in the output; reported above
+        for operation in &mut pipeline.loader.operations {
+            if let Some(dataset_id) = operation.get_mut("DatasetId") {
+                if let Some(new) = self.datasets.get(dataset_id.as_str()) {
+                    *dataset_id = new.clone();
+                }
+            }
+        }
+
+        for step in &mut pipeline.steps {
+            let target = format!("OPERATION_{} ({})", step.number, step.op_type);
+            if let Some(dataset_id) = step.operation.get_mut("DatasetId") {
+                if let Some(new) = self.datasets.get(dataset_id.as_str()) {
+                    substitutions.push(Substitution {
+                        target: target.clone(),
+                        field: "DatasetId".to_string(),
+                        from: std::mem::replace(dataset_id, new.clone()),
+                        to: new.clone(),
+                    });
+                }
+            }
+
+            let (kind, field) = match ServiceKind::for_op_type(&step.op_type) {
+                Some(found) => found,
+                None => continue,
+            };
+            let new = match step
+                .operation
+                .get(field)
+                .and_then(|id| self.services.get(id))
+            {
+                Some(new) => new.clone(),
+                None => continue,
+            };
+            let old = step.operation.insert(field.to_string(), new.clone());
+            // The parameters of skipped steps are null
+            if let Some(id) = step.params.get_mut(kind.name()) {
+                *id = new.clone().into();
+            }
+            substitutions.push(Substitution {
+                target,
+                field: field.to_string(),
+                from: old.unwrap_or_default(),
+                to: new,
+            });
+        }
+        substitutions
+    }
+
+    /// Mappings that matched nothing in `substitutions`, as `--map-...` arguments.
+    pub fn unused(&self, substitutions: &[Substitution]) -> Vec<String> {
+        let used = |field_is_dataset: bool, old: &str| {
+            substitutions
+                .iter()
+                .any(|s| (s.field == "DatasetId") == field_is_dataset && s.from == old)
+        };
+        let services = self
+            .services
+            .iter()
+            .filter(|(old, _)| !used(false, old))
+            .map(|(old, new)| format!("service {}={}", old, new));
+        let datasets = self
+            .datasets
+            .iter()
+            .filter(|(old, _)| !used(true, old))
+            .map(|(old, new)| format!("dataset {}={}", old, new));
+        services.chain(datasets).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generators::GeneratorRegistry, pipeline::build_pipeline};
+    use std::collections::HashMap;
+
+    #[test]
+    fn ids_are_rewritten_once_and_reported() {
+        let operations: Vec<HashMap<String, String>> = [
+            ("GET_TABLE", "", ""),
+            ("RECONCILIATION", "Reconciler", "wikidataOpenRefine"),
+            ("RECONCILIATION", "Reconciler", "wikidataAlligator"),
+        ]
+        .iter()
+        .map(|(op_type, field, id)| {
+            let mut operation = HashMap::from([
+                ("OpType".to_string(), op_type.to_string()),
+                ("DatasetId".to_string(), "2".to_string()),
+                ("ColumnName".to_string(), "City".to_string()),
+            ]);
+            if !field.is_empty() {
+                operation.insert(field.to_string(), id.to_string());
+            }
+            operation
+        })
+        .collect();
+        let registry = GeneratorRegistry::with_builtin_generators();
+        let mut pipeline = build_pipeline(
+            &operations,
+            "table.csv",
+            false,
+            &registry,
+            chrono::Utc::now(),
+        );
+
+        let remap = Remap {
+            services: BTreeMap::from([
+                (
+                    "wikidataOpenRefine".to_string(),
+                    "wikidataAlligator".to_string(),
+                ),
+                (
+                    "wikidataAlligator".to_string(),
+                    "wikidataOpenRefine".to_string(),
+                ),
+                ("geocoder".to_string(), "geocodingHere".to_string()),
+            ]),
+            datasets: BTreeMap::from([("2".to_string(), "7".to_string())]),
+        };
+        let substitutions = remap.apply(&mut pipeline);
+        let report: Vec<String> = substitutions.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            report,
+            [
+                "loader: DatasetId 2 -> 7",
+                "OPERATION_1 (RECONCILIATION): DatasetId 2 -> 7",
+                "OPERATION_1 (RECONCILIATION): Reconciler wikidataOpenRefine -> wikidataAlligator",
+                "OPERATION_2 (RECONCILIATION): DatasetId 2 -> 7",
+                "OPERATION_2 (RECONCILIATION): Reconciler wikidataAlligator -> wikidataOpenRefine",
+            ]
+        );
+        assert_eq!(pipeline.loader.dataset_id, "7");
+        assert_eq!(pipeline.loader.operations[0]["DatasetId"], "7");
+        assert_eq!(pipeline.steps[0].params["reconciler"], "wikidataAlligator");
+        assert_eq!(pipeline.steps[1].params["reconciler"], "wikidataOpenRefine");
+        assert_eq!(
+            remap.unused(&substitutions),
+            ["service geocoder=geocodingHere"]
+        );
+
+        assert_eq!(
+            parse_id_mapping(" a = b "),
+            Ok(("a".to_string(), "b".to_string()))
+        );
+        assert!(parse_id_mapping("a=").is_err());
+    }
+}
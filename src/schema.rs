@@ -0,0 +1,413 @@
+use crate::{
+    extenders::ExtenderMappings,
+    operations::{parse_deleted_columns, parse_json},
+    validation::referenced_columns,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaColumn {
+    pub name: String,
+    /// Reconciler of the current reconciliation, if the column is reconciled
+    pub reconciled_by: Option<String>,
+    /// Type object of the last PROPAGATE_TYPE since the column was reconciled
+    pub propagated_type: Option<Value>,
+    /// OPERATION_N of the extension that created the column
+    pub created_by: Option<usize>,
+    /// Extension outputs are named after the requested property because the log does not
+    /// record the names of the columns the backend creates
+    pub placeholder: bool,
+    pub modified_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    MissingColumn,
+    NotReconciled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaProblem {
+    pub kind: ProblemKind,
+    pub message: String,
+}
+
+/// Expected table schema after one operation of the replay.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaStep {
+    /// OPERATION_N numbering of the generated code, 0 for the table loader
+    pub operation_number: usize,
+    /// Position of the operation in the processed operations
+    pub operation_index: Option<usize>,
+    pub op_type: String,
+    pub column: Option<String>,
+    pub columns: Vec<SchemaColumn>,
+    pub problems: Vec<SchemaProblem>,
+}
+
+fn new_column(name: &str) -> SchemaColumn {
+    SchemaColumn {
+        name: name.to_string(),
+        reconciled_by: None,
+        propagated_type: None,
+        created_by: None,
+        placeholder: false,
+        modified_by: Vec::new(),
+    }
+}
+
+/// Whether `name`, used by a later operation, is the column an extension created for the
+/// `placeholder` `<column>_<property>`: the same name up to case and separators, or the bare
+/// property.
+fn is_extension_output(placeholder: &str, name: &str) -> bool {
+    let normalize = |s: &str| s.to_lowercase().replace([' ', '-'], "_");
+    let placeholder = normalize(placeholder);
+    let name = normalize(name);
+    placeholder == name || placeholder.ends_with(&format!("_{}", name))
+}
+
+/// Walks the processed operations and records the expected schema after each one.
+///
+/// With a CSV `header` the initial columns are known and references to columns that do not
+/// exist are reported; a column an extension created is only recognised under its
+/// placeholder name or its property (see `is_extension_output`). Without it, columns are
+/// discovered the first time an operation uses them. `DeletedCols` of SAVE_TABLE are
+/// removed up front, like the generated loader does. Extensions create one column per
+/// property, read through `mappings` as the generated `extend_column` call is.
+pub fn simulate_schema(
+    operations: &[HashMap<String, String>],
+    header: Option<&[String]>,
+    mappings: &ExtenderMappings,
+) -> Vec<SchemaStep> {
+    let deleted_columns = operations
+        .iter()
+        .find(|op| op.get("OpType") == Some(&"SAVE_TABLE".to_string()))
+        .and_then(|op| op.get("DeletedCols"))
+        .map(|deleted_cols_str| parse_deleted_columns(deleted_cols_str))
+        .unwrap_or_default();
+
+    let mut columns: Vec<SchemaColumn> = header
+        .unwrap_or_default()
+        .iter()
+        .filter(|c| !deleted_columns.contains(c))
+        .map(|c| new_column(c))
+        .collect();
+    let mut steps = vec![SchemaStep {
+        operation_number: 0,
+        operation_index: None,
+        op_type: "SETUP".to_string(),
+        column: None,
+        columns: columns.clone(),
+        problems: Vec::new(),
+    }];
+
+    let mut displayed_operation_number = 0;
+    for (index, operation) in operations.iter().enumerate() {
+        let op_type = operation.get("OpType").map_or("UNKNOWN", |s| s.as_str());
+        if !matches!(
+            op_type,
+            "RECONCILIATION" | "EXTENSION" | "PROPAGATE_TYPE" | "EXPORT" | "MODIFICATION"
+        ) {
+            continue;
+        }
+        displayed_operation_number += 1;
+        let mut problems = Vec::new();
+
+        for name in referenced_columns(operation) {
+            if columns.iter().any(|c| c.name == name) {
+                continue;
+            }
+            if let Some(i) = columns
+                .iter()
+                .position(|c| c.placeholder && is_extension_output(&c.name, &name))
+            {
+                // The log names a column an extension created: keep its real name
+                columns[i].name = name;
+                columns[i].placeholder = false;
+                continue;
+            }
+            if deleted_columns.contains(&name) {
+                problems.push(SchemaProblem {
+                    kind: ProblemKind::MissingColumn,
+                    message: format!("column '{}' was deleted by the table loader", name),
+                });
+            } else if header.is_none() {
+                // Discovered from the log: assume it comes from the input table
+                columns.push(new_column(&name));
+            } else {
+                problems.push(SchemaProblem {
+                    kind: ProblemKind::MissingColumn,
+                    message: format!("column '{}' does not exist in the table", name),
+                });
+            }
+        }
+
+        let col_name = operation.get("ColumnName").cloned();
+        let additional_data = operation
+            .get("AdditionalData")
+            .and_then(|s| parse_json(s))
+            .unwrap_or(Value::Null);
+        let target = col_name
+            .as_ref()
+            .and_then(|name| columns.iter().position(|c| &c.name == name));
+
+        match (op_type, target) {
+            ("RECONCILIATION", Some(i)) => {
+                columns[i].reconciled_by = operation.get("Reconciler").cloned();
+                columns[i].propagated_type = None;
+            }
+            ("PROPAGATE_TYPE", Some(i)) => {
+                if columns[i].reconciled_by.is_none() {
+                    problems.push(SchemaProblem {
+                        kind: ProblemKind::NotReconciled,
+                        message: format!(
+                            "type propagated on column '{}' which is not reconciled",
+                            columns[i].name
+                        ),
+                    });
+                }
+                columns[i].propagated_type = Some(additional_data.clone());
+            }
+            ("MODIFICATION", Some(i)) => {
+                if let Some(modifier) = operation.get("Modifier") {
+                    columns[i].modified_by.push(modifier.clone());
+                }
+            }
+            ("EXTENSION", Some(i)) => {
+                let base = columns[i].name.clone();
+                let extender = operation.get("Extender").map_or("", |s| s.as_str());
+                let mut properties = mappings.properties(extender, &additional_data);
+                if properties.is_empty() {
+                    properties.push(format!(
+                        "{} output",
+                        operation
+                            .get("Extender")
+                            .map_or("extension", |s| s.as_str())
+                    ));
+                }
+                for property in properties {
+                    let name = format!("{}_{}", base, property);
+                    if columns.iter().any(|c| c.name == name) {
+                        continue;
+                    }
+                    let mut column = new_column(&name);
+                    column.created_by = Some(displayed_operation_number);
+                    column.placeholder = true;
+                    columns.push(column);
+                }
+            }
+            _ => {}
+        }
+
+        steps.push(SchemaStep {
+            operation_number: displayed_operation_number,
+            operation_index: Some(index),
+            op_type: op_type.to_string(),
+            column: col_name,
+            columns: columns.clone(),
+            problems,
+        });
+    }
+    steps
+}
+
+/// One line per column, e.g. `City [reconciled: wikidataOpenRefine, type: country]`.
+pub fn describe_columns(columns: &[SchemaColumn]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| {
+            let mut details = Vec::new();
+            if let Some(reconciler) = &column.reconciled_by {
+                details.push(format!("reconciled: {}", reconciler));
+            }
+            if let Some(type_obj) = &column.propagated_type {
+                let label = type_obj
+                    .get("label")
+                    .or_else(|| type_obj.get("id"))
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| type_obj.to_string());
+                details.push(format!("type: {}", label));
+            }
+            if !column.modified_by.is_empty() {
+                details.push(format!("modified: {}", column.modified_by.join(", ")));
+            }
+            if let Some(extension_number) = column.created_by {
+                if column.placeholder {
+                    details.push(format!(
+                        "new from OPERATION_{}, approximate name",
+                        extension_number
+                    ));
+                } else {
+                    details.push(format!("from OPERATION_{}", extension_number));
+                }
+            }
+            if details.is_empty() {
+                column.name.clone()
+            } else {
+                format!("{} [{}]", column.name, details.join(", "))
+            }
+        })
+        .collect()
+}
+
+pub fn format_schema_steps(steps: &[SchemaStep]) -> String {
+    let mut output = String::new();
+    for step in steps {
+        let heading = match &step.column {
+            Some(column) => format!(
+                "OPERATION_{}: {} on '{}'",
+                step.operation_number, step.op_type, column
+            ),
+            None => format!("OPERATION_{}: {}", step.operation_number, step.op_type),
+        };
+        output.push_str(&format!("{} ({} columns)\n", heading, step.columns.len()));
+        for line in describe_columns(&step.columns) {
+            output.push_str(&format!("  {}\n", line));
+        }
+        for problem in &step.problems {
+            output.push_str(&format!("  ! {}\n", problem.message));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extenders::builtin_mappings;
+
+    fn operation(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn extension(extender: &str, additional_data: &str) -> HashMap<String, String> {
+        operation(&[
+            ("OpType", "EXTENSION"),
+            ("ColumnName", "City"),
+            ("Extender", extender),
+            ("AdditionalData", additional_data),
+        ])
+    }
+
+    /// Columns after the last operation, starting from a City,Date table.
+    fn columns_after(operations: &[HashMap<String, String>]) -> Vec<String> {
+        let header = ["City".to_string(), "Date".to_string()];
+        let steps = simulate_schema(operations, Some(&header), builtin_mappings());
+        let last = steps.last().unwrap();
+        assert!(last.problems.is_empty(), "{:?}", last.problems);
+        last.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    #[test]
+    fn each_extender_mapping_names_its_placeholder_columns() {
+        let cases = [
+            (
+                extension("wikidataPropertySPARQL", r#"{"properties":"P17 P1082"}"#),
+                vec!["City_P17", "City_P1082"],
+            ),
+            (
+                extension("reconciledColumnExt", r#"{"property":["id","name"]}"#),
+                vec!["City_id", "City_name"],
+            ),
+            (
+                extension(
+                    "meteoPropertiesOpenMeteo",
+                    r#"{"weatherParams":["temperature_max"],"dates":{"0":["a","b","Date"]}}"#,
+                ),
+                vec!["City_temperature_max"],
+            ),
+            // Extenders without a mapping use the default one
+            (
+                extension("geoExt", r#"{"labels":["lat","lon"]}"#),
+                vec!["City_lat", "City_lon"],
+            ),
+            (extension("geoExt", "{}"), vec!["City_geoExt output"]),
+        ];
+        for (operation, created) in cases {
+            let mut expected = vec!["City", "Date"];
+            expected.extend(created);
+            assert_eq!(columns_after(std::slice::from_ref(&operation)), expected);
+
+            let header = ["City".to_string(), "Date".to_string()];
+            let steps = simulate_schema(&[operation], Some(&header), builtin_mappings());
+            assert!(steps[1].columns[2..]
+                .iter()
+                .all(|c| c.placeholder && c.created_by == Some(1)));
+        }
+    }
+
+    #[test]
+    fn placeholders_are_approximate_until_a_later_operation_names_them() {
+        let operations = vec![
+            extension("wikidataPropertySPARQL", r#"{"properties":"P17 P1082"}"#),
+            operation(&[
+                ("OpType", "RECONCILIATION"),
+                ("ColumnName", "P17"),
+                ("Reconciler", "wikidataOpenRefine"),
+            ]),
+            operation(&[
+                ("OpType", "RECONCILIATION"),
+                ("ColumnName", "Populaton"),
+                ("Reconciler", "wikidataOpenRefine"),
+            ]),
+        ];
+        let header = ["City".to_string()];
+        let steps = simulate_schema(&operations, Some(&header), builtin_mappings());
+
+        assert_eq!(
+            describe_columns(&steps[1].columns),
+            [
+                "City",
+                "City_P17 [new from OPERATION_1, approximate name]",
+                "City_P1082 [new from OPERATION_1, approximate name]",
+            ]
+        );
+        assert_eq!(
+            describe_columns(&steps[2].columns)[1],
+            "P17 [reconciled: wikidataOpenRefine, from OPERATION_1]"
+        );
+        // A column no extension created is missing, even after an extension
+        assert_eq!(steps[3].problems.len(), 1);
+        assert_eq!(steps[3].problems[0].kind, ProblemKind::MissingColumn);
+        assert_eq!(
+            steps[3].problems[0].message,
+            "column 'Populaton' does not exist in the table"
+        );
+    }
+
+    #[test]
+    fn extender_mappings_decide_the_created_columns() {
+        let path = std::env::temp_dir().join(format!(
+            "semtparser-schema-mappings-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            r#"{"extenders": {"geoExt": {"properties": [{"path": "/fields"}]}}}"#,
+        )
+        .unwrap();
+        let mappings = ExtenderMappings::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mappings = mappings.unwrap();
+
+        let operations = [extension("geoExt", r#"{"fields":["lat","lon"]}"#)];
+        let header = ["City".to_string()];
+        let names = |mappings: &ExtenderMappings| -> Vec<String> {
+            simulate_schema(&operations, Some(&header), mappings)[1]
+                .columns
+                .iter()
+                .map(|c| c.name.clone())
+                .collect()
+        };
+        // The default entry does not read `fields`
+        assert_eq!(names(builtin_mappings()), ["City", "City_geoExt output"]);
+        assert_eq!(names(&mappings), ["City", "City_lat", "City_lon"]);
+    }
+}
@@ -0,0 +1,474 @@
+use crate::catalog::is_wikidata_property;
+use crate::extenders::builtin_mappings;
+use crate::operations::{parse_json, sort_operations_by_timestamp};
+use chrono::DateTime;
+use serde::Serialize;
+use std::collections::HashMap;
+
+type Operation = HashMap<String, String>;
+
+/// One session of a log, as preprocessed (before deduplication, so redone work is counted).
+pub struct LoggedSession {
+    pub log_file: String,
+    /// 1-based position of the session in its log
+    pub number: usize,
+    pub operations: Vec<Operation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Frequency {
+    pub name: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionSummary {
+    pub log_file: String,
+    pub session: usize,
+    pub dataset_id: Option<String>,
+    pub table_id: Option<String>,
+    pub started: Option<String>,
+    pub operations: usize,
+    /// Time from the first to the last timestamped entry of the session
+    pub duration_seconds: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DurationSummary {
+    pub count: usize,
+    pub min_seconds: i64,
+    pub median_seconds: i64,
+    pub mean_seconds: f64,
+    pub max_seconds: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogStats {
+    pub logs: Vec<String>,
+    pub operations: usize,
+    pub operation_types: Vec<Frequency>,
+    pub reconcilers: Vec<Frequency>,
+    pub extenders: Vec<Frequency>,
+    pub modifiers: Vec<Frequency>,
+    /// Most requested Wikidata properties in extensions
+    pub properties: Vec<Frequency>,
+    pub reconciliations: usize,
+    /// Reconciliations of a column (of the same dataset and table) that was already reconciled
+    pub reconciliations_redone: usize,
+    pub step_intervals: Option<DurationSummary>,
+    pub session_durations: Option<DurationSummary>,
+    pub sessions: Vec<SessionSummary>,
+}
+
+/// Sorted by count, most frequent first, then by name.
+fn frequencies(counts: HashMap<String, usize>) -> Vec<Frequency> {
+    let mut frequencies: Vec<Frequency> = counts
+        .into_iter()
+        .map(|(name, count)| Frequency { name, count })
+        .collect();
+    frequencies.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    frequencies
+}
+
+fn summarize_durations(mut seconds: Vec<i64>) -> Option<DurationSummary> {
+    if seconds.is_empty() {
+        return None;
+    }
+    seconds.sort_unstable();
+    Some(DurationSummary {
+        count: seconds.len(),
+        min_seconds: seconds[0],
+        median_seconds: seconds[seconds.len() / 2],
+        mean_seconds: seconds.iter().sum::<i64>() as f64 / seconds.len() as f64,
+        max_seconds: seconds[seconds.len() - 1],
+    })
+}
+
+pub fn collect_stats(logs: &[String], sessions: Vec<LoggedSession>, top: usize) -> LogStats {
+    let mut operation_types: HashMap<String, usize> = HashMap::new();
+    let mut reconcilers: HashMap<String, usize> = HashMap::new();
+    let mut extenders: HashMap<String, usize> = HashMap::new();
+    let mut modifiers: HashMap<String, usize> = HashMap::new();
+    let mut properties: HashMap<String, usize> = HashMap::new();
+    let mut reconciled_columns: Vec<(String, String, String)> = Vec::new();
+    let mut reconciliations = 0;
+    let mut reconciliations_redone = 0;
+    let mut step_intervals = Vec::new();
+    let mut session_summaries = Vec::new();
+    let mut total_operations = 0;
+
+    let field = |op: &Operation, key: &str| op.get(key).cloned().unwrap_or_default();
+
+    for session in sessions {
+        let operations = sort_operations_by_timestamp(session.operations);
+        total_operations += operations.len();
+        let get_table = operations
+            .iter()
+            .find(|op| op.get("OpType").map(|s| s.as_str()) == Some("GET_TABLE"));
+
+        for op in &operations {
+            let op_type = op.get("OpType").map_or("UNKNOWN", |s| s.as_str());
+            *operation_types.entry(op_type.to_string()).or_insert(0) += 1;
+            match op_type {
+                "RECONCILIATION" => {
+                    *reconcilers.entry(field(op, "Reconciler")).or_insert(0) += 1;
+                    reconciliations += 1;
+                    let key = (
+                        field(op, "DatasetId"),
+                        field(op, "TableId"),
+                        field(op, "ColumnName"),
+                    );
+                    if reconciled_columns.contains(&key) {
+                        reconciliations_redone += 1;
+                    } else {
+                        reconciled_columns.push(key);
+                    }
+                }
+                "EXTENSION" => {
+                    *extenders.entry(field(op, "Extender")).or_insert(0) += 1;
+                    let additional_data = op.get("AdditionalData").and_then(|s| parse_json(s));
+                    if let Some(additional_data) = additional_data {
+                        for property in
+                            builtin_mappings().properties(&field(op, "Extender"), &additional_data)
+                        {
+                            if is_wikidata_property(&property) {
+                                *properties.entry(property).or_insert(0) += 1;
+                            }
+                        }
+                    }
+                }
+                "MODIFICATION" => {
+                    *modifiers.entry(field(op, "Modifier")).or_insert(0) += 1;
+                }
+                _ => {}
+            }
+        }
+
+        let timestamps: Vec<_> = operations
+            .iter()
+            .filter_map(|op| op.get("timestamp"))
+            .filter_map(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .collect();
+        step_intervals.extend(
+            timestamps
+                .windows(2)
+                .map(|pair| (pair[1] - pair[0]).num_seconds()),
+        );
+        session_summaries.push(SessionSummary {
+            log_file: session.log_file,
+            session: session.number,
+            dataset_id: get_table.and_then(|op| op.get("DatasetId").cloned()),
+            table_id: get_table.and_then(|op| op.get("TableId").cloned()),
+            started: timestamps.first().map(|ts| ts.to_rfc3339()),
+            operations: operations.len(),
+            duration_seconds: match (timestamps.first(), timestamps.last()) {
+                (Some(first), Some(last)) => Some((*last - *first).num_seconds()),
+                _ => None,
+            },
+        });
+    }
+
+    let mut properties = frequencies(properties);
+    properties.truncate(top);
+    LogStats {
+        logs: logs.to_vec(),
+        operations: total_operations,
+        operation_types: frequencies(operation_types),
+        reconcilers: frequencies(reconcilers),
+        extenders: frequencies(extenders),
+        modifiers: frequencies(modifiers),
+        properties,
+        reconciliations,
+        reconciliations_redone,
+        step_intervals: summarize_durations(step_intervals),
+        session_durations: summarize_durations(
+            session_summaries
+                .iter()
+                .filter_map(|s| s.duration_seconds)
+                .collect(),
+        ),
+        sessions: session_summaries,
+    }
+}
+
+/// `90061` -> `1d 01h 01m 01s`
+fn format_seconds(seconds: i64) -> String {
+    let (days, rest) = (seconds / 86400, seconds % 86400);
+    let (hours, rest) = (rest / 3600, rest % 3600);
+    let (minutes, seconds) = (rest / 60, rest % 60);
+    if days > 0 {
+        format!("{}d {:02}h {:02}m {:02}s", days, hours, minutes, seconds)
+    } else if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+pub fn format_stats(stats: &LogStats) -> String {
+    let mut output = format!(
+        "{} session(s), {} operation(s) in {}\n",
+        stats.sessions.len(),
+        stats.operations,
+        stats.logs.join(", ")
+    );
+    let sections = [
+        ("Operations by type", &stats.operation_types),
+        ("Reconcilers", &stats.reconcilers),
+        ("Extenders", &stats.extenders),
+        ("Modifiers", &stats.modifiers),
+        ("Top Wikidata properties", &stats.properties),
+    ];
+    for (title, frequencies) in sections {
+        output.push_str(&format!("\n{}:\n", title));
+        if frequencies.is_empty() {
+            output.push_str("  (none)\n");
+        }
+        for frequency in frequencies {
+            output.push_str(&format!("  {:<32} {}\n", frequency.name, frequency.count));
+        }
+    }
+
+    output.push_str(&format!(
+        "\nReconciliations redone: {} of {}",
+        stats.reconciliations_redone, stats.reconciliations
+    ));
+    if stats.reconciliations > 0 {
+        output.push_str(&format!(
+            " ({:.1}%)",
+            stats.reconciliations_redone as f64 * 100.0 / stats.reconciliations as f64
+        ));
+    }
+    output.push('\n');
+
+    for (title, summary) in [
+        ("Time between steps", &stats.step_intervals),
+        ("Session duration", &stats.session_durations),
+    ] {
+        match summary {
+            Some(summary) => output.push_str(&format!(
+                "{}: min {}, median {}, mean {}, max {} ({} measured)\n",
+                title,
+                format_seconds(summary.min_seconds),
+                format_seconds(summary.median_seconds),
+                format_seconds(summary.mean_seconds.round() as i64),
+                format_seconds(summary.max_seconds),
+                summary.count
+            )),
+            None => output.push_str(&format!("{}: no timestamps\n", title)),
+        }
+    }
+
+    output.push_str("\nSessions:\n");
+    for session in &stats.sessions {
+        output.push_str(&format!(
+            "  {} #{}: dataset {} table {}, started {}, {} operation(s), {}\n",
+            session.log_file,
+            session.session,
+            session.dataset_id.as_deref().unwrap_or("N/A"),
+            session.table_id.as_deref().unwrap_or("N/A"),
+            session.started.as_deref().unwrap_or("N/A"),
+            session.operations,
+            session
+                .duration_seconds
+                .map_or("no timestamps".to_string(), format_seconds)
+        ));
+    }
+    output
+}
+
+/// Flat `metric,name,value` rows, one per counted item, so the report loads in a spreadsheet.
+pub fn write_stats_csv<W: std::io::Write>(stats: &LogStats, writer: W) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(["metric", "name", "value"])?;
+    writer.write_record(["operations", "", &stats.operations.to_string()])?;
+    for (metric, frequencies) in [
+        ("operation_type", &stats.operation_types),
+        ("reconciler", &stats.reconcilers),
+        ("extender", &stats.extenders),
+        ("modifier", &stats.modifiers),
+        ("property", &stats.properties),
+    ] {
+        for frequency in frequencies {
+            writer.write_record([metric, &frequency.name, &frequency.count.to_string()])?;
+        }
+    }
+    writer.write_record(["reconciliations", "", &stats.reconciliations.to_string()])?;
+    writer.write_record([
+        "reconciliations_redone",
+        "",
+        &stats.reconciliations_redone.to_string(),
+    ])?;
+    for (metric, summary) in [
+        ("step_interval_seconds", &stats.step_intervals),
+        ("session_duration_seconds", &stats.session_durations),
+    ] {
+        if let Some(summary) = summary {
+            writer.write_record([metric, "min", &summary.min_seconds.to_string()])?;
+            writer.write_record([metric, "median", &summary.median_seconds.to_string()])?;
+            writer.write_record([metric, "mean", &format!("{:.1}", summary.mean_seconds)])?;
+            writer.write_record([metric, "max", &summary.max_seconds.to_string()])?;
+        }
+    }
+    for session in &stats.sessions {
+        if let Some(duration) = session.duration_seconds {
+            writer.write_record([
+                "session_seconds",
+                &format!("{}#{}", session.log_file, session.session),
+                &duration.to_string(),
+            ])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operation(timestamp: &str, op_type: &str, fields: &[(&str, &str)]) -> Operation {
+        let mut operation = HashMap::from([
+            ("timestamp".to_string(), timestamp.to_string()),
+            ("OpType".to_string(), op_type.to_string()),
+            ("DatasetId".to_string(), "2".to_string()),
+            ("TableId".to_string(), "5".to_string()),
+        ]);
+        for (key, value) in fields {
+            operation.insert(key.to_string(), value.to_string());
+        }
+        operation
+    }
+
+    fn session(number: usize, operations: Vec<Operation>) -> LoggedSession {
+        LoggedSession {
+            log_file: "logs.txt".to_string(),
+            number,
+            operations,
+        }
+    }
+
+    fn counts(frequencies: &[Frequency]) -> Vec<(&str, usize)> {
+        frequencies
+            .iter()
+            .map(|f| (f.name.as_str(), f.count))
+            .collect()
+    }
+
+    #[test]
+    fn operations_are_counted_per_type_and_service() {
+        let first = session(
+            1,
+            vec![
+                operation("2025-06-01T10:00:00Z", "GET_TABLE", &[]),
+                operation(
+                    "2025-06-01T10:01:00Z",
+                    "RECONCILIATION",
+                    &[("ColumnName", "City"), ("Reconciler", "wikidataOpenRefine")],
+                ),
+                // Logged out of order: sorted before the intervals are measured
+                operation(
+                    "2025-06-01T10:03:00Z",
+                    "RECONCILIATION",
+                    &[("ColumnName", "City"), ("Reconciler", "wikidataAlligator")],
+                ),
+                operation(
+                    "2025-06-01T10:02:00Z",
+                    "EXTENSION",
+                    &[
+                        ("ColumnName", "City"),
+                        ("Extender", "wikidataPropertySPARQL"),
+                        ("AdditionalData", r#"{"properties":"P17 P1082 label"}"#),
+                    ],
+                ),
+            ],
+        );
+        let second = session(
+            2,
+            vec![
+                operation("2025-06-01T11:00:00Z", "GET_TABLE", &[]),
+                operation(
+                    "2025-06-01T11:00:30Z",
+                    "RECONCILIATION",
+                    &[
+                        ("ColumnName", "Country"),
+                        ("Reconciler", "wikidataOpenRefine"),
+                    ],
+                ),
+                operation(
+                    "2025-06-01T11:01:00Z",
+                    "EXTENSION",
+                    &[
+                        ("ColumnName", "Country"),
+                        ("Extender", "wikidataPropertySPARQL"),
+                        ("AdditionalData", r#"{"properties":"P17"}"#),
+                    ],
+                ),
+                operation(
+                    "2025-06-01T11:02:00Z",
+                    "MODIFICATION",
+                    &[("ColumnName", "Date"), ("Modifier", "dateFormatter")],
+                ),
+            ],
+        );
+        let stats = collect_stats(&["logs.txt".to_string()], vec![first, second], 1);
+
+        assert_eq!(stats.operations, 8);
+        assert_eq!(
+            counts(&stats.operation_types),
+            [
+                ("RECONCILIATION", 3),
+                ("EXTENSION", 2),
+                ("GET_TABLE", 2),
+                ("MODIFICATION", 1)
+            ]
+        );
+        assert_eq!(
+            counts(&stats.reconcilers),
+            [("wikidataOpenRefine", 2), ("wikidataAlligator", 1)]
+        );
+        assert_eq!(counts(&stats.extenders), [("wikidataPropertySPARQL", 2)]);
+        assert_eq!(counts(&stats.modifiers), [("dateFormatter", 1)]);
+        // Only Wikidata properties are counted, truncated to --top
+        assert_eq!(counts(&stats.properties), [("P17", 2)]);
+        assert_eq!(stats.reconciliations, 3);
+        assert_eq!(stats.reconciliations_redone, 1);
+
+        let intervals = stats.step_intervals.unwrap();
+        assert_eq!(intervals.count, 6);
+        assert_eq!(intervals.min_seconds, 30);
+        assert_eq!(intervals.max_seconds, 60);
+        assert_eq!(
+            stats
+                .sessions
+                .iter()
+                .map(|s| (s.session, s.operations, s.duration_seconds))
+                .collect::<Vec<_>>(),
+            [(1, 4, Some(180)), (2, 4, Some(120))]
+        );
+        assert_eq!(stats.session_durations.unwrap().median_seconds, 180);
+    }
+
+    #[test]
+    fn an_empty_log_has_no_counts_or_durations() {
+        let stats = collect_stats(&["empty.txt".to_string()], Vec::new(), 10);
+        assert_eq!(stats.operations, 0);
+        assert!(stats.operation_types.is_empty());
+        assert!(stats.reconcilers.is_empty());
+        assert!(stats.properties.is_empty());
+        assert_eq!(stats.reconciliations_redone, 0);
+        assert!(stats.step_intervals.is_none());
+        assert!(stats.session_durations.is_none());
+        assert!(stats.sessions.is_empty());
+
+        let mut csv = Vec::new();
+        write_stats_csv(&stats, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "metric,name,value\noperations,,0\nreconciliations,,0\nreconciliations_redone,,0\n"
+        );
+        assert!(format_stats(&stats).contains("empty.txt"));
+    }
+}
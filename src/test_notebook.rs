@@ -1,4 +1,3 @@
-use chrono;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::env;
@@ -75,14 +74,10 @@ pub fn create_notebook(
                     "base_url = \"{}\"\n",
                     env::var("BASE_URL").unwrap_or_else(|_| "__BASE_URL__".to_string())
                 ),
-                format!(
-                    "username = \"{}\"\n",
-                    env::var("USERNAME").unwrap_or_else(|_| "__USERNAME__".to_string())
-                ),
-                format!(
-                    "password = \"{}\"\n",
-                    env::var("PASSWORD").unwrap_or_else(|_| "__PASSWORD__".to_string())
-                ),
+                // Credentials are read when the notebook runs, never written into it
+                "import os\n".to_string(),
+                "username = os.environ[\"USERNAME\"]\n".to_string(),
+                "password = os.environ[\"PASSWORD\"]\n".to_string(),
                 "api_url = base_url + \"/api\"\n".to_string(),
                 "\n".to_string(),
                 "Auth_manager = TokenManager(api_url, username, password)\n".to_string(),
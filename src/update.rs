@@ -0,0 +1,767 @@
+use crate::{generators::Operation, metadata::OperationMetadata};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// First line of a region of a generated file that `update` leaves as it is.
+pub const KEEP_BEGIN: &str = "# semtparser-keep: begin";
+/// Last line of a kept region.
+pub const KEEP_END: &str = "# semtparser-keep: end";
+
+const SEPARATOR: &str =
+    "# =============================================================================";
+const LOADER: &str = "table loader";
+const SUMMARY: &str = "operation summary";
+const DEFAULT_EXPORT: &str = "default export";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// A block for an operation new in the log
+    Added,
+    /// A block regenerated because its operation changed or moved in the log
+    Updated,
+    /// A block whose operation is no longer in the log
+    Removed,
+    /// Edits to a block that the log did not change
+    KeptEdits,
+}
+
+/// What `update` did to one block of the file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// The block, e.g. `RECONCILIATION on column 'City'` or `table loader`
+    pub unit: String,
+    pub kind: ChangeKind,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ChangeKind::Added => write!(f, "added {}", self.unit),
+            ChangeKind::Updated => write!(f, "updated {}", self.unit),
+            ChangeKind::Removed => write!(f, "removed {}", self.unit),
+            ChangeKind::KeptEdits => write!(f, "kept the edits to {}", self.unit),
+        }
+    }
+}
+
+/// A block edited in the file whose operation also changed in the log. The edited block is
+/// kept; merging in the change is left to the user.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub unit: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.unit, self.reason)
+    }
+}
+
+/// The updated file and what changed in it.
+#[derive(Debug, Clone)]
+pub struct Update {
+    /// The new content, `None` when the file is already up to date
+    pub content: Option<String>,
+    pub changes: Vec<Change>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Merges the regeneration of a Python script into the `existing` one. `previous` is the
+/// script generated from the operations embedded in `existing` and `regenerated` the script
+/// of the current log, both rendered with the options `existing` was generated with.
+pub fn update_script(existing: &str, previous: &str, regenerated: &str) -> Result<Update, String> {
+    let merged = merge(
+        script_units(existing)?,
+        script_units(previous)?,
+        script_units(regenerated)?,
+    )?;
+    let content: String = merged
+        .units
+        .into_iter()
+        .flat_map(|unit| unit.parts)
+        .collect();
+    Ok(Update {
+        content: (content != existing).then_some(content),
+        changes: merged.changes,
+        conflicts: merged.conflicts,
+    })
+}
+
+/// [`update_script`] for notebooks. Cells are compared by type and source: the outputs
+/// and metadata the user added to a kept cell stay.
+pub fn update_notebook(
+    existing: &str,
+    previous: &str,
+    regenerated: &str,
+) -> Result<Update, String> {
+    let parse = |notebook: &str| -> Result<Value, String> {
+        serde_json::from_str(notebook).map_err(|e| format!("invalid notebook: {}", e))
+    };
+    let mut notebook = parse(existing)?;
+    let merged = merge(
+        notebook_units(&notebook)?,
+        notebook_units(&parse(previous)?)?,
+        notebook_units(&parse(regenerated)?)?,
+    )?;
+    let cells: Value = merged
+        .units
+        .into_iter()
+        .flat_map(|unit| unit.parts)
+        .collect();
+    let content = if cells == notebook["cells"] {
+        None
+    } else {
+        notebook["cells"] = cells;
+        Some(
+            serde_json::to_string_pretty(&notebook)
+                .map_err(|e| format!("could not serialize the notebook: {}", e))?,
+        )
+    };
+    Ok(Update {
+        content,
+        changes: merged.changes,
+        conflicts: merged.conflicts,
+    })
+}
+
+/// A generated block of a file, or lines and cells the user added (`key` is `None`). Its
+/// parts are compared one by one: the header and code of a script block, the cells of a
+/// notebook block.
+struct Unit<P> {
+    key: Option<String>,
+    parts: Vec<P>,
+}
+
+trait Part: Clone {
+    /// Whether both parts hold the same generated content.
+    fn same(&self, other: &Self) -> bool;
+    /// The part without its kept regions, and the regions.
+    fn without_kept(&self) -> Result<(Self, Vec<String>), String>;
+    /// The part with `regions` added at its end.
+    fn with_kept(self, regions: &[String]) -> Self;
+    /// The part as it is in the file, with the metadata of its regenerated version.
+    fn refreshed(self, regenerated: &Self) -> Self;
+    /// Whether the part is code, where kept regions of a regenerated block go.
+    fn is_code(&self) -> bool;
+}
+
+enum Outcome<P> {
+    /// Nothing changed in the log; whether the file has edits
+    Kept(Vec<P>, bool),
+    /// Regenerated parts; whether edits to other parts were kept
+    Updated(Vec<P>, bool),
+    Conflict,
+}
+
+/// Resolves a block of the file whose operation is still in the log. Parts the user did
+/// not edit follow the log, edited parts stay unless the log changed them too.
+fn resolve<P: Part>(
+    existing: &[P],
+    previous: &[P],
+    regenerated: &[P],
+) -> Result<Outcome<P>, String> {
+    let stripped: Vec<(P, Vec<String>)> = existing
+        .iter()
+        .map(P::without_kept)
+        .collect::<Result<_, _>>()?;
+    if existing.len() == previous.len() && previous.len() == regenerated.len() {
+        let mut parts = Vec::new();
+        let (mut edited, mut changed) = (false, false);
+        for (((part, (bare, kept)), before), after) in existing
+            .iter()
+            .zip(&stripped)
+            .zip(previous)
+            .zip(regenerated)
+        {
+            let part_edited = !bare.same(before);
+            let part_changed = !after.same(before);
+            if part_edited && part_changed {
+                return Ok(Outcome::Conflict);
+            }
+            edited |= part_edited;
+            changed |= part_changed;
+            parts.push(match part_changed {
+                true => after.clone().with_kept(kept),
+                false => part.clone().refreshed(after),
+            });
+        }
+        return Ok(match changed {
+            true => Outcome::Updated(parts, edited),
+            false => Outcome::Kept(parts, edited),
+        });
+    }
+
+    // The parts no longer line up (a cell was deleted, the step is now skipped...): the
+    // block is compared as a whole
+    let edited = !same_parts(stripped.iter().map(|(bare, _)| bare), previous);
+    let changed = !same_parts(regenerated.iter(), previous);
+    Ok(match (edited, changed) {
+        (_, false) => Outcome::Kept(existing.to_vec(), edited),
+        (false, true) => {
+            let regions: Vec<String> = stripped.into_iter().flat_map(|(_, kept)| kept).collect();
+            let mut parts = regenerated.to_vec();
+            let target = parts
+                .iter()
+                .rposition(P::is_code)
+                .or(parts.len().checked_sub(1));
+            if let Some(index) = target {
+                parts[index] = parts[index].clone().with_kept(&regions);
+            }
+            Outcome::Updated(parts, false)
+        }
+        (true, true) => Outcome::Conflict,
+    })
+}
+
+fn same_parts<'a, P: Part + 'a>(parts: impl ExactSizeIterator<Item = &'a P>, other: &[P]) -> bool {
+    parts.len() == other.len() && parts.zip(other).all(|(part, other)| part.same(other))
+}
+
+struct Merged<P> {
+    units: Vec<Unit<P>>,
+    changes: Vec<Change>,
+    conflicts: Vec<Conflict>,
+}
+
+/// Merges by key, in the order of `regenerated`. What the user added after a block, and
+/// blocks kept because of conflicts, stay after that block.
+fn merge<P: Part>(
+    existing: Vec<Unit<P>>,
+    previous: Vec<Unit<P>>,
+    regenerated: Vec<Unit<P>>,
+) -> Result<Merged<P>, String> {
+    let previous: HashMap<String, Vec<P>> = previous
+        .into_iter()
+        .filter_map(|unit| Some((unit.key?, unit.parts)))
+        .collect();
+    let regenerated_keys: HashSet<String> = regenerated
+        .iter()
+        .filter_map(|unit| unit.key.clone())
+        .collect();
+    let mut changes = Vec::new();
+    let mut conflicts = Vec::new();
+    let change = |unit: &str, kind| Change {
+        unit: unit.to_string(),
+        kind,
+    };
+    let conflict = |unit: &str, reason: &str| Conflict {
+        unit: unit.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let mut leading = Vec::new();
+    let mut resolved: HashMap<String, Vec<P>> = HashMap::new();
+    let mut attached: HashMap<String, Vec<Unit<P>>> = HashMap::new();
+    let mut anchor: Option<String> = None;
+    for unit in existing {
+        if let Some(key) = unit.key.clone() {
+            let before = previous.get(&key).map_or(&[][..], Vec::as_slice);
+            if regenerated_keys.contains(&key) {
+                let after = regenerated
+                    .iter()
+                    .find(|unit| unit.key.as_ref() == Some(&key))
+                    .map_or(&[][..], |unit| unit.parts.as_slice());
+                let parts = match resolve(&unit.parts, before, after)
+                    .map_err(|e| format!("{}: {}", key, e))?
+                {
+                    Outcome::Kept(parts, edited) => {
+                        if edited {
+                            changes.push(change(&key, ChangeKind::KeptEdits));
+                        }
+                        parts
+                    }
+                    Outcome::Updated(parts, edited) => {
+                        changes.push(change(&key, ChangeKind::Updated));
+                        if edited {
+                            changes.push(change(&key, ChangeKind::KeptEdits));
+                        }
+                        parts
+                    }
+                    Outcome::Conflict => {
+                        conflicts.push(conflict(
+                            &key,
+                            "edited in the file and changed in the log; kept the edited version",
+                        ));
+                        unit.parts
+                    }
+                };
+                resolved.insert(key.clone(), parts);
+                anchor = Some(key);
+                continue;
+            }
+            let untouched = unit
+                .parts
+                .iter()
+                .map(P::without_kept)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("{}: {}", key, e))?;
+            let unedited = untouched.iter().all(|(_, kept)| kept.is_empty())
+                && same_parts(untouched.iter().map(|(bare, _)| bare), before);
+            if unedited {
+                changes.push(change(&key, ChangeKind::Removed));
+                continue;
+            }
+            conflicts.push(conflict(
+                &key,
+                "edited in the file and no longer in the log; kept the edited version",
+            ));
+        }
+        match &anchor {
+            Some(key) => attached.entry(key.clone()).or_default().push(unit),
+            None => leading.push(unit),
+        }
+    }
+
+    let mut units = leading;
+    for unit in regenerated {
+        let Some(key) = unit.key.clone() else {
+            continue;
+        };
+        match resolved.remove(&key) {
+            Some(parts) => units.push(Unit {
+                key: Some(key.clone()),
+                parts,
+            }),
+            None => {
+                changes.push(change(&key, ChangeKind::Added));
+                units.push(unit);
+            }
+        }
+        units.extend(attached.remove(&key).unwrap_or_default());
+    }
+    Ok(Merged {
+        units,
+        changes,
+        conflicts,
+    })
+}
+
+/// Keys of the replayed operations: the type and column, numbered when they repeat.
+#[derive(Default)]
+struct StepKeys(HashMap<String, usize>);
+
+impl StepKeys {
+    fn next(&mut self, operation: &Operation) -> String {
+        let op_type = operation.get("OpType").map_or("UNKNOWN", |s| s.as_str());
+        let key = match operation.get("ColumnName") {
+            Some(column) => format!("{} on column '{}'", op_type, column),
+            None => op_type.to_string(),
+        };
+        let count = self.0.entry(key.clone()).or_default();
+        *count += 1;
+        match count {
+            1 => key,
+            n => format!("{} (#{})", key, n),
+        }
+    }
+}
+
+/// Splits `text` into the lines outside kept regions and the regions, markers included.
+fn split_kept(text: &str) -> Result<(String, Vec<String>), String> {
+    let mut rest = String::new();
+    let mut regions = Vec::new();
+    let mut region: Option<String> = None;
+    for line in text.split_inclusive('\n') {
+        let marker = line.trim();
+        if let Some(current) = region.as_mut() {
+            if marker == KEEP_BEGIN {
+                return Err(format!("'{}' inside a kept region", KEEP_BEGIN));
+            }
+            current.push_str(line);
+            if marker == KEEP_END {
+                regions.extend(region.take());
+            }
+        } else if marker == KEEP_BEGIN {
+            region = Some(line.to_string());
+        } else if marker == KEEP_END {
+            return Err(format!("'{}' without '{}'", KEEP_END, KEEP_BEGIN));
+        } else {
+            rest.push_str(line);
+        }
+    }
+    match region {
+        Some(_) => Err(format!("'{}' without '{}'", KEEP_BEGIN, KEEP_END)),
+        None => Ok((rest, regions)),
+    }
+}
+
+/// `text` with `regions` inserted before its trailing blank lines.
+fn append_regions(text: &str, regions: &[String]) -> String {
+    if regions.is_empty() {
+        return text.to_string();
+    }
+    let (body, trailing) = text.split_at(text.trim_end_matches('\n').len());
+    let mut out = body.to_string();
+    if !body.is_empty() {
+        out.push('\n');
+    }
+    for region in regions {
+        out.push_str(region);
+        if !region.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    // The first trailing line break ended the body
+    out.push_str(trailing.get(1..).unwrap_or(""));
+    out
+}
+
+/// Whether two metadata lines describe the same operation, however the log spaced it.
+fn same_metadata_line(line: &str, other: &str) -> bool {
+    match (
+        OperationMetadata::parse_line(line),
+        OperationMetadata::parse_line(other),
+    ) {
+        (Some(Ok(metadata)), Some(Ok(other))) => metadata.same_operation(&other),
+        _ => false,
+    }
+}
+
+impl Part for String {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+            || self.lines().count() == other.lines().count()
+                && self
+                    .lines()
+                    .zip(other.lines())
+                    .all(|(line, other)| line == other || same_metadata_line(line, other))
+    }
+
+    fn without_kept(&self) -> Result<(Self, Vec<String>), String> {
+        split_kept(self)
+    }
+
+    fn with_kept(self, regions: &[String]) -> Self {
+        append_regions(&self, regions)
+    }
+
+    fn refreshed(self, _regenerated: &Self) -> Self {
+        self
+    }
+
+    fn is_code(&self) -> bool {
+        true
+    }
+}
+
+/// The blocks of a generated script: the table loader, one block per operation (its
+/// separator header, then its code), then the default export and summary. Lines after the
+/// summary are the user's.
+fn script_units(script: &str) -> Result<Vec<Unit<String>>, String> {
+    let lines: Vec<&str> = script.split_inclusive('\n').collect();
+    let is_separator = |index: usize| lines.get(index).is_some_and(|l| l.trim_end() == SEPARATOR);
+    let mut keys = StepKeys::default();
+    let mut units = vec![Unit {
+        key: Some(LOADER.to_string()),
+        parts: vec![String::new()],
+    }];
+    let mut in_tail = false;
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let operation = if !in_tail
+            && is_separator(index)
+            && lines
+                .get(index + 1)
+                .is_some_and(|l| l.starts_with("# OPERATION_"))
+        {
+            lines
+                .get(index + 2)
+                .and_then(|l| OperationMetadata::parse_line(l))
+                .transpose()
+                .map_err(|e| format!("line {}: {}", index + 3, e))?
+        } else {
+            None
+        };
+        let summary = is_separator(index)
+            && lines
+                .get(index + 1)
+                .is_some_and(|l| l.trim_end() == "# OPERATION SUMMARY");
+        if let Some(metadata) = operation.filter(|_| is_separator(index + 3)) {
+            units.push(Unit {
+                key: Some(keys.next(&metadata.to_operation())),
+                parts: vec![lines[index..index + 4].concat(), String::new()],
+            });
+            index += 4;
+            continue;
+        }
+        if !in_tail && (summary || line.starts_with("# Default Export (")) {
+            in_tail = true;
+            units.push(Unit {
+                key: Some(SUMMARY.to_string()),
+                parts: vec![String::new()],
+            });
+        }
+        if summary {
+            // Header, total and bullets, closed by the third separator
+            let end = (index + 3..lines.len())
+                .find(|&i| is_separator(i))
+                .map_or(lines.len(), |i| i + 1);
+            if let Some(part) = units.last_mut().and_then(|unit| unit.parts.last_mut()) {
+                part.extend(lines[index..end].iter().copied());
+            }
+            let rest: String = lines[end..].concat();
+            if !rest.is_empty() {
+                units.push(Unit {
+                    key: None,
+                    parts: vec![rest],
+                });
+            }
+            break;
+        }
+        if let Some(part) = units.last_mut().and_then(|unit| unit.parts.last_mut()) {
+            part.push_str(line);
+        }
+        index += 1;
+    }
+    Ok(units)
+}
+
+fn source_text(cell: &Value) -> String {
+    match &cell["source"] {
+        Value::String(source) => source.clone(),
+        Value::Array(lines) => lines.iter().filter_map(|line| line.as_str()).collect(),
+        _ => String::new(),
+    }
+}
+
+fn set_source(cell: &mut Value, text: &str) {
+    cell["source"] = text
+        .split_inclusive('\n')
+        .map(|line| Value::String(line.to_string()))
+        .collect();
+}
+
+impl Part for Value {
+    fn same(&self, other: &Self) -> bool {
+        self["cell_type"] == other["cell_type"] && source_text(self) == source_text(other)
+    }
+
+    fn without_kept(&self) -> Result<(Self, Vec<String>), String> {
+        let mut cell = self.clone();
+        if !self.is_code() {
+            return Ok((cell, Vec::new()));
+        }
+        let (rest, kept) = split_kept(&source_text(self))?;
+        if !kept.is_empty() {
+            set_source(&mut cell, &rest);
+        }
+        Ok((cell, kept))
+    }
+
+    fn with_kept(mut self, regions: &[String]) -> Self {
+        if !regions.is_empty() {
+            let text = append_regions(&source_text(&self), regions);
+            set_source(&mut self, &text);
+        }
+        self
+    }
+
+    fn refreshed(mut self, regenerated: &Self) -> Self {
+        let metadata = &regenerated["metadata"]["semtparser"];
+        if !metadata.is_null() && self["metadata"].is_object() {
+            self["metadata"]["semtparser"] = metadata.clone();
+        }
+        self
+    }
+
+    fn is_code(&self) -> bool {
+        self["cell_type"] == "code"
+    }
+}
+
+/// The blocks of a generated notebook, from the `semtparser` metadata of its cells: the
+/// summary, the setup cells, and the cells of each operation with its expected schema.
+/// Other cells are the user's, except the setup code cell between the setup cells.
+fn notebook_units(notebook: &Value) -> Result<Vec<Unit<Value>>, String> {
+    let cells = notebook["cells"]
+        .as_array()
+        .ok_or_else(|| "invalid notebook: no cells".to_string())?;
+    let mut keys = StepKeys::default();
+    let mut units: Vec<Unit<Value>> = Vec::new();
+    // `operation_index` of the last unit, whose cells share it
+    let mut last_index = None;
+    for cell in cells {
+        let metadata = &cell["metadata"]["semtparser"];
+        let index = metadata["operation_index"].as_u64();
+        let key = if !metadata.is_object() {
+            None
+        } else if metadata["cell_type"] == "expected_schema" {
+            if let Some(unit) = units.iter_mut().rev().find(|unit| unit.key.is_some()) {
+                unit.parts.push(cell.clone());
+                continue;
+            }
+            None
+        } else if metadata["cell_type"] == "summary" {
+            Some(SUMMARY.to_string())
+        } else if metadata["operation_type"] == "SETUP" {
+            Some(LOADER.to_string())
+        } else if index.is_some() && index == last_index {
+            if let Some(unit) = units.last_mut() {
+                unit.parts.push(cell.clone());
+                continue;
+            }
+            None
+        } else {
+            match metadata["operation_data"].as_object() {
+                Some(data) if data.contains_key("OpType") => Some(
+                    keys.next(
+                        &data
+                            .iter()
+                            .map(|(key, value)| {
+                                let value = value.as_str().map_or(value.to_string(), String::from);
+                                (key.clone(), value)
+                            })
+                            .collect(),
+                    ),
+                ),
+                _ => Some(DEFAULT_EXPORT.to_string()),
+            }
+        };
+        last_index = match key.as_deref() {
+            Some(LOADER) | Some(SUMMARY) | None => None,
+            Some(_) => index,
+        };
+        match units.last_mut() {
+            Some(unit) if unit.key == key => unit.parts.push(cell.clone()),
+            _ => units.push(Unit {
+                key,
+                parts: vec![cell.clone()],
+            }),
+        }
+    }
+
+    // The setup code cell has no metadata of its own
+    let loader: Vec<usize> = units
+        .iter()
+        .enumerate()
+        .filter(|(_, unit)| unit.key.as_deref() == Some(LOADER))
+        .map(|(index, _)| index)
+        .collect();
+    if let (Some(&first), Some(&last)) = (loader.first(), loader.last()) {
+        let merged: Vec<Value> = units
+            .drain(first + 1..=last)
+            .flat_map(|unit| unit.parts)
+            .collect();
+        units[first].parts.extend(merged);
+    }
+    Ok(units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: usize, reconciler: &str) -> String {
+        format!(
+            "\n{SEPARATOR}\n# OPERATION_{number}: RECONCILIATION\n# semtparser: {{\"version\":1,\"operation\":{number},\"op_type\":\"RECONCILIATION\",\"fields\":{{\"ColumnName\":\"City\",\"OpType\":\"RECONCILIATION\",\"Reconciler\":\"{reconciler}\"}}}}\n{SEPARATOR}\n\nreconcile('City', '{reconciler}')\n"
+        )
+    }
+
+    fn script(blocks: &[String]) -> String {
+        format!(
+            "import x\nload()\n{}\n{SEPARATOR}\n# OPERATION SUMMARY\n{SEPARATOR}\n# Total operations: {}\n{SEPARATOR}\n",
+            blocks.concat(),
+            blocks.len()
+        )
+    }
+
+    #[test]
+    fn edits_and_kept_regions_survive_regeneration() {
+        let original = script(&[block(1, "a")]);
+        let changed = script(&[block(1, "b")]);
+
+        // A changed operation replaces an unedited block, keeping the marked region
+        let marked = original.replace(
+            "'a')\n",
+            "'a')\n# semtparser-keep: begin\nplot()\n# semtparser-keep: end\n",
+        );
+        let update = update_script(&marked, &original, &changed).unwrap();
+        assert!(update.conflicts.is_empty());
+        assert_eq!(
+            update.changes[0].to_string(),
+            "updated RECONCILIATION on column 'City'"
+        );
+        assert!(update
+            .content
+            .unwrap()
+            .contains("'b')\n# semtparser-keep: begin\nplot()\n# semtparser-keep: end\n\n"));
+
+        // An unmarked edit is kept while the log is unchanged, and a conflict once it changes
+        let edited = original.replace("'a')", "'a', threshold=0.9)");
+        let unchanged = update_script(&edited, &original, &original).unwrap();
+        assert_eq!(unchanged.content, None);
+        assert_eq!(
+            unchanged.changes[0].to_string(),
+            "kept the edits to RECONCILIATION on column 'City'"
+        );
+        let conflicting = update_script(&edited, &original, &changed).unwrap();
+        assert_eq!(
+            conflicting.conflicts[0].to_string(),
+            "RECONCILIATION on column 'City': edited in the file and changed in the log; kept the edited version"
+        );
+        assert_eq!(conflicting.content, None);
+
+        // A new operation goes before the summary; code after the summary is the user's
+        let extended = script(&[block(1, "a"), block(2, "b")]);
+        let appended = format!("{}\nprint(table)\n", original);
+        let update = update_script(&appended, &original, &extended).unwrap();
+        assert_eq!(
+            update.changes.last().unwrap().to_string(),
+            "added RECONCILIATION on column 'City' (#2)"
+        );
+        assert_eq!(
+            update.content.unwrap(),
+            format!("{}\nprint(table)\n", extended)
+        );
+
+        let broken = original.replace("load()\n", "load()\n# semtparser-keep: begin\n");
+        assert_eq!(
+            update_script(&broken, &original, &original).unwrap_err(),
+            "table loader: '# semtparser-keep: begin' without '# semtparser-keep: end'"
+        );
+    }
+
+    #[test]
+    fn respaced_additional_data_is_not_a_change() {
+        let original = script(&[block(1, "a")]);
+        let metadata_line = original
+            .lines()
+            .find(|line| line.starts_with("# semtparser: "))
+            .unwrap()
+            .to_string();
+        let with_data = |data: &str| {
+            let operation: Operation = [
+                ("OpType", "RECONCILIATION"),
+                ("ColumnName", "City"),
+                ("Reconciler", "a"),
+                ("AdditionalData", data),
+            ]
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+            original.replace(
+                &metadata_line,
+                &OperationMetadata::new(1, &operation).to_line(),
+            )
+        };
+        let previous = with_data(r#"{"threshold" : 0.9}"#);
+        let regenerated = with_data(r#"{"threshold":0.9}"#);
+        assert_ne!(previous, regenerated);
+
+        // The edit is kept: the operation only changed how it was spaced in the log
+        let edited = previous.replace("'a')", "'a', threshold=0.9)");
+        let update = update_script(&edited, &previous, &regenerated).unwrap();
+        assert!(update.conflicts.is_empty());
+        assert_eq!(
+            update.changes[0].to_string(),
+            "kept the edits to RECONCILIATION on column 'City'"
+        );
+    }
+}
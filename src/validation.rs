@@ -0,0 +1,423 @@
+use crate::{
+    extenders::ExtenderMappings,
+    operations::{parse_deleted_columns, parse_json},
+    schema::{simulate_schema, ProblemKind},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, io};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.pad("warning"),
+            Severity::Error => f.pad("error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub operation_number: usize,
+    pub op_type: String,
+    pub message: String,
+    pub suggestion: Option<String>,
+}
+
+/// Reads the header of the CSV table and up to `sample_rows` data rows.
+pub fn read_csv_sample(
+    table_path: &str,
+    sample_rows: usize,
+) -> Result<(Vec<String>, Vec<Vec<String>>), io::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(table_path)
+        .map_err(io::Error::other)?;
+    let header: Vec<String> = reader
+        .headers()
+        .map_err(io::Error::other)?
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records().take(sample_rows) {
+        let record = record.map_err(io::Error::other)?;
+        rows.push(record.iter().map(|v| v.to_string()).collect());
+    }
+    Ok((header, rows))
+}
+
+/// Columns an operation reads from the table: its `ColumnName`, the keys of
+/// `additionalColumns` and, for extensions, the date columns listed in `dates`.
+pub fn referenced_columns(operation: &HashMap<String, String>) -> Vec<String> {
+    let mut columns = Vec::new();
+    if let Some(col_name) = operation.get("ColumnName") {
+        if !col_name.is_empty() {
+            columns.push(col_name.clone());
+        }
+    }
+
+    let additional_data = match operation.get("AdditionalData").and_then(|s| parse_json(s)) {
+        Some(data) => data,
+        None => return columns,
+    };
+    if let Some(obj) = additional_data
+        .get("additionalColumns")
+        .and_then(|v| v.as_object())
+    {
+        columns.extend(obj.keys().cloned());
+    }
+    if operation.get("OpType").map(|s| s.as_str()) == Some("EXTENSION") {
+        if let Some(dates_obj) = additional_data.get("dates").and_then(|v| v.as_object()) {
+            for date_array in dates_obj.values().filter_map(|v| v.as_array()) {
+                if let Some(date_column_name) = date_array.get(2).and_then(|v| v.as_str()) {
+                    if !columns.iter().any(|c| c == date_column_name) {
+                        columns.push(date_column_name.to_string());
+                    }
+                }
+            }
+        }
+    }
+    columns
+}
+
+fn normalize_column_name(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b_chars.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push(
+                (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+    previous[b_chars.len()]
+}
+
+/// Suggests the column the log most likely meant: first a case/whitespace-insensitive
+/// match, then the closest name within a small edit distance.
+pub fn suggest_column(missing: &str, available: &[String]) -> Option<String> {
+    let normalized = normalize_column_name(missing);
+    if let Some(found) = available
+        .iter()
+        .find(|c| normalize_column_name(c) == normalized)
+    {
+        return Some(found.clone());
+    }
+    let max_distance = (missing.chars().count() / 4).max(1);
+    available
+        .iter()
+        .map(|c| (levenshtein(&missing.to_lowercase(), &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, c)| c.clone())
+}
+
+/// Checks that every column an operation uses exists when the operation runs in the
+/// generated replay, in the schema the simulator expects before that operation: the CSV
+/// header minus the columns the loader deletes (`DeletedCols` of SAVE_TABLE), plus the
+/// columns of the earlier extensions. The log does not record the names of the columns an
+/// extension creates, so a column the schema simulator matches to an extension output is
+/// only reported as a warning; any other missing column is an error.
+pub fn validate_operations(
+    operations: &[HashMap<String, String>],
+    table_path: &str,
+    sample_rows: usize,
+    mappings: &ExtenderMappings,
+) -> Result<Vec<ValidationIssue>, io::Error> {
+    let (header, rows) = read_csv_sample(table_path, sample_rows)?;
+    let mut issues = Vec::new();
+
+    let deleted_columns = operations
+        .iter()
+        .find(|op| op.get("OpType") == Some(&"SAVE_TABLE".to_string()))
+        .and_then(|op| op.get("DeletedCols"))
+        .map(|deleted_cols_str| parse_deleted_columns(deleted_cols_str))
+        .unwrap_or_default();
+
+    let steps = simulate_schema(operations, Some(&header), mappings);
+    for (previous, step) in steps.iter().zip(steps.iter().skip(1)) {
+        let Some(index) = step.operation_index else {
+            continue;
+        };
+        // Extension outputs keep their approximate names until an operation uses them
+        let available: Vec<String> = previous
+            .columns
+            .iter()
+            .filter(|c| !c.placeholder)
+            .map(|c| c.name.clone())
+            .collect();
+        for column in referenced_columns(&operations[index]) {
+            if available.contains(&column) {
+                continue;
+            }
+            let mut issue = ValidationIssue {
+                severity: Severity::Error,
+                operation_number: step.operation_number,
+                op_type: step.op_type.clone(),
+                message: String::new(),
+                suggestion: None,
+            };
+            let created_by = step
+                .columns
+                .iter()
+                .find(|c| c.name == column)
+                .and_then(|c| c.created_by);
+            let suggestion = suggest_column(&column, &available);
+            let is_spelling_mismatch = suggestion
+                .as_ref()
+                .is_some_and(|s| normalize_column_name(s) == normalize_column_name(&column));
+            if deleted_columns.contains(&column) {
+                issue.message = format!(
+                    "column '{}' is deleted by the table loader (DeletedCols) before this operation runs",
+                    column
+                );
+            } else if is_spelling_mismatch {
+                issue.message = format!("column '{}' does not exist in the CSV header", column);
+                issue.suggestion = suggestion;
+            } else if let Some(extension_number) = created_by {
+                let extension = steps
+                    .iter()
+                    .find(|s| s.operation_number == extension_number)
+                    .and_then(|s| s.operation_index)
+                    .map(|i| &operations[i]);
+                issue.severity = Severity::Warning;
+                issue.message = format!(
+                    "column '{}' is not in the CSV header; assuming it was created by the {} extension of '{}'",
+                    column,
+                    extension.and_then(|op| op.get("Extender")).map_or("", |s| s.as_str()),
+                    extension.and_then(|op| op.get("ColumnName")).map_or("", |s| s.as_str())
+                );
+            } else {
+                // Also suggest the columns created by the extensions so far
+                let known: Vec<String> = previous.columns.iter().map(|c| c.name.clone()).collect();
+                issue.message = format!("column '{}' does not exist in the CSV header", column);
+                issue.suggestion = suggest_column(&column, &known);
+            }
+            issues.push(issue);
+        }
+
+        // Column existence is checked above with suggestions; the simulator adds the
+        // sequences that cannot work regardless of the columns
+        for problem in &step.problems {
+            if problem.kind != ProblemKind::MissingColumn {
+                issues.push(ValidationIssue {
+                    severity: Severity::Error,
+                    operation_number: step.operation_number,
+                    op_type: step.op_type.clone(),
+                    message: problem.message.clone(),
+                    suggestion: None,
+                });
+            }
+        }
+    }
+
+    if !rows.is_empty() {
+        issues.extend(check_sampled_rows(operations, &header, &rows));
+    }
+    Ok(issues)
+}
+
+fn check_sampled_rows(
+    operations: &[HashMap<String, String>],
+    header: &[String],
+    rows: &[Vec<String>],
+) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut reported: Vec<String> = Vec::new();
+    for operation in operations {
+        let op_type = operation.get("OpType").map_or("UNKNOWN", |s| s.as_str());
+        if op_type != "RECONCILIATION" {
+            continue;
+        }
+        let column = match operation.get("ColumnName") {
+            Some(column) => column,
+            None => continue,
+        };
+        let index = match header.iter().position(|h| h == column) {
+            Some(index) => index,
+            None => continue,
+        };
+        let all_empty = rows
+            .iter()
+            .all(|row| row.get(index).is_none_or(|v| v.trim().is_empty()));
+        if all_empty && !reported.contains(column) {
+            reported.push(column.clone());
+            issues.push(ValidationIssue {
+                severity: Severity::Warning,
+                operation_number: 0,
+                op_type: op_type.to_string(),
+                message: format!(
+                    "column '{}' is empty in all {} sampled rows",
+                    column,
+                    rows.len()
+                ),
+                suggestion: None,
+            });
+        }
+    }
+    issues
+}
+
+pub fn print_validation_report(issues: &[ValidationIssue]) {
+    if issues.is_empty() {
+        println!("Validation passed: all referenced columns exist in the table.");
+        return;
+    }
+    eprintln!("Validation found {} problem(s):", issues.len());
+    for issue in issues {
+        let location = if issue.operation_number > 0 {
+            format!("OPERATION_{} ({})", issue.operation_number, issue.op_type)
+        } else {
+            issue.op_type.clone()
+        };
+        match &issue.suggestion {
+            Some(suggestion) => eprintln!(
+                "  {}: {}: {} (did you mean '{}'?)",
+                issue.severity, location, issue.message, suggestion
+            ),
+            None => eprintln!("  {}: {}: {}", issue.severity, location, issue.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extenders::builtin_mappings;
+
+    fn operation(fields: &[(&str, &str)]) -> HashMap<String, String> {
+        fields
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Validates `operations` against a City,Country table.
+    fn validate(operations: &[HashMap<String, String>]) -> Vec<ValidationIssue> {
+        let table = std::env::temp_dir().join(format!(
+            "semtparser-validation-{}-{:?}.csv",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        std::fs::write(&table, "City,Country\nRome,Italy\n").unwrap();
+        let issues =
+            validate_operations(operations, &table.to_string_lossy(), 5, builtin_mappings())
+                .unwrap();
+        std::fs::remove_file(&table).unwrap();
+        issues
+    }
+
+    fn sparql_extension() -> HashMap<String, String> {
+        operation(&[
+            ("OpType", "EXTENSION"),
+            ("ColumnName", "City"),
+            ("Extender", "wikidataPropertySPARQL"),
+            ("AdditionalData", r#"{"properties":"P17 P1082"}"#),
+        ])
+    }
+
+    /// Validates `later` after a SPARQL extension of City.
+    fn validate_after_extension(later: HashMap<String, String>) -> Vec<ValidationIssue> {
+        validate(&[
+            operation(&[("OpType", "GET_TABLE")]),
+            reconciliation_of("City"),
+            sparql_extension(),
+            later,
+        ])
+    }
+
+    fn reconciliation_of(column: &str) -> HashMap<String, String> {
+        operation(&[
+            ("OpType", "RECONCILIATION"),
+            ("ColumnName", column),
+            ("Reconciler", "wikidataOpenRefine"),
+        ])
+    }
+
+    #[test]
+    fn a_typo_after_an_extension_is_still_an_error() {
+        let issues = validate_after_extension(reconciliation_of("Contry"));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].operation_number, 3);
+        assert_eq!(
+            issues[0].message,
+            "column 'Contry' does not exist in the CSV header"
+        );
+        assert_eq!(issues[0].suggestion.as_deref(), Some("Country"));
+
+        let issues = validate_after_extension(reconciliation_of("City_P71"));
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].suggestion.as_deref(), Some("City_P17"));
+    }
+
+    #[test]
+    fn a_column_created_by_the_extension_is_a_warning() {
+        for column in ["City_P17", "P1082", "city p17"] {
+            let issues = validate_after_extension(reconciliation_of(column));
+            assert_eq!(issues.len(), 1, "{}", column);
+            assert_eq!(issues[0].severity, Severity::Warning);
+            assert_eq!(
+                issues[0].message,
+                format!(
+                    "column '{}' is not in the CSV header; assuming it was created by the wikidataPropertySPARQL extension of 'City'",
+                    column
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn header_columns_pass_and_spelling_mismatches_are_suggested() {
+        assert!(validate_after_extension(reconciliation_of("Country")).is_empty());
+        let issues = validate_after_extension(reconciliation_of("country"));
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].suggestion.as_deref(), Some("Country"));
+    }
+
+    #[test]
+    fn columns_are_checked_against_the_schema_before_each_operation() {
+        // Used before the extension creates it
+        let issues = validate(&[
+            operation(&[("OpType", "GET_TABLE")]),
+            reconciliation_of("City_P17"),
+            sparql_extension(),
+        ]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[0].operation_number, 1);
+
+        // Once an operation has named an extension output, later uses of it pass
+        let issues = validate(&[
+            operation(&[("OpType", "GET_TABLE")]),
+            sparql_extension(),
+            reconciliation_of("P1082"),
+            reconciliation_of("P1082"),
+        ]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Warning);
+        assert_eq!(issues[0].operation_number, 2);
+    }
+}
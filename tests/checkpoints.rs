@@ -0,0 +1,212 @@
+//! Every step of a generated script can be skipped and is recorded in the checkpoint.
+
+mod common;
+
+use common::{main_command, run_ok, test_dir};
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output, Stdio},
+};
+
+/// Three modifications on different columns, then the default export: four steps.
+const MODIFICATIONS: &str = r#"[2025-06-01T10:00:00Z] -| OpType: GET_TABLE -| DatasetId: 2 -| TableId: 5 -| TableName: cities
+[2025-06-01T10:01:00Z] -| OpType: MODIFICATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| Modifier: toUppercase -| AdditionalData: {}
+[2025-06-01T10:02:00Z] -| OpType: MODIFICATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: Country -| Modifier: toUppercase -| AdditionalData: {}
+[2025-06-01T10:03:00Z] -| OpType: MODIFICATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: Date -| Modifier: dateFormatter -| AdditionalData: {"formatType":"iso"}
+[2025-06-01T10:04:00Z] -| OpType: SAVE_TABLE -| DatasetId: 2 -| TableId: 5 -| DeletedCols: Notes
+"#;
+
+/// Stand-ins for the modules the generated script imports. `modify` prints the column and
+/// fails on the column named by `FAIL`.
+const STUBS: &[(&str, &str)] = &[
+    (
+        "pandas.py",
+        "class DataFrame:\n    columns = []\n    def head(self): return self\n    def to_string(self): return ''\n    def drop(self, **kwargs): return self\n\ndef read_csv(path): return DataFrame()\n",
+    ),
+    (
+        "semt_py/__init__.py",
+        "class AuthManager:\n    def __init__(self, *args): pass\n    def get_token(self): return 'token'\n",
+    ),
+    (
+        "semt_py/table_manager.py",
+        "class TableManager:\n    def __init__(self, *args): pass\n    def add_table(self, dataset_id, df, name):\n        print('UPLOAD')\n        return '77', 'ok', {}\n    def get_table(self, dataset_id, table_id): return {}\n",
+    ),
+    (
+        "semt_py/modification_manager.py",
+        "import os\n\nclass ModificationManager:\n    def __init__(self, *args): pass\n    def modify(self, column_name, **kwargs):\n        print('MODIFY', column_name)\n        if os.environ.get('FAIL') == column_name:\n            raise RuntimeError('token expired')\n        return {}, {}\n",
+    ),
+    (
+        "semt_py/utils.py",
+        "class Utility:\n    def __init__(self, *args): pass\n    def push_to_backend(self, *args, **kwargs): return 'pushed', None\n    @staticmethod\n    def display_json_table(**kwargs): return None\n    def download_json(self, output_file, **kwargs): return output_file\n",
+    ),
+    (
+        "semt_py/dataset_manager.py",
+        "class DatasetManager:\n    def __init__(self, *args): pass\n",
+    ),
+    (
+        "semt_py/extension_manager.py",
+        "class ExtensionManager:\n    def __init__(self, *args): pass\n",
+    ),
+    (
+        "semt_py/reconciliation_manager.py",
+        "class ReconciliationManager:\n    def __init__(self, *args): pass\n",
+    ),
+];
+
+fn python3_available() -> bool {
+    Command::new("python3")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Fails the test unless python3 compiles `script` of `dir`.
+fn assert_compiles(dir: &Path, script: &str) {
+    let compiled = Command::new("python3")
+        .current_dir(dir)
+        .args(["-m", "py_compile", script])
+        .output()
+        .unwrap();
+    assert!(
+        compiled.status.success(),
+        "{}",
+        String::from_utf8_lossy(&compiled.stderr)
+    );
+}
+
+/// Runs the generated `replay.py` against the stubs, without a terminal to prompt on.
+fn replay(dir: &Path, args: &[&str], fail: Option<&str>) -> Output {
+    let mut command = Command::new("python3");
+    command
+        .current_dir(dir)
+        .arg("replay.py")
+        .args(["--base-url", "http://localhost:3003", "--username", "u"])
+        .args(["--password", "p", "--dataset-id", "2", "--table-name", "t"])
+        .args(["--csv-file", "table.csv"])
+        .args(args)
+        .env("PYTHONPATH", dir.join("stubs"))
+        .env_remove("FAIL")
+        .stdin(Stdio::null());
+    if let Some(column) = fail {
+        command.env("FAIL", column);
+    }
+    command.output().unwrap()
+}
+
+/// The columns modified by a run, and whether it exported the table.
+fn steps_run(output: &Output) -> (Vec<String>, bool) {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let modified = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("MODIFY "))
+        .map(|column| column.to_string())
+        .collect();
+    (modified, stdout.contains("JSON downloaded"))
+}
+
+#[test]
+fn every_step_is_guarded_and_checkpointed() {
+    let dir = test_dir("checkpoints", common::LOG);
+    run_ok(main_command(&dir).args(["-l", "logs.txt", "-t", "table.csv", "-o", "replay.py"]));
+    let script = fs::read_to_string(dir.join("replay.py")).unwrap();
+    if python3_available() {
+        assert_compiles(&dir, "replay.py");
+    } else {
+        eprintln!("python3 not available, skipping the compile check");
+    }
+    let _ = fs::remove_dir_all(&dir);
+
+    for arg in ["--resume", "--from-step", "--to-step", "--only-steps"] {
+        assert!(script.contains(&format!("parser.add_argument('{}'", arg)));
+    }
+    // Four replayed operations and the default export
+    for number in 1..=5 {
+        let guard = format!("\nif run_step({}):\n    step_error = None\n", number);
+        let start = script
+            .find(&guard)
+            .unwrap_or_else(|| panic!("OPERATION_{} is not guarded", number));
+        let finish = format!("\n    finish_step({}, step_error)\n", number);
+        let end = script[start..]
+            .find(&finish)
+            .unwrap_or_else(|| panic!("OPERATION_{} is not checkpointed", number));
+        let block = &script[start + guard.len()..start + end];
+        assert!(
+            block
+                .lines()
+                .all(|line| line.is_empty() || line.starts_with("    ")),
+            "OPERATION_{} is not indented:\n{}",
+            number,
+            block
+        );
+        assert!(block.contains("        step_error = e"));
+    }
+}
+
+#[test]
+fn scripts_resume_and_run_selected_steps() {
+    if !python3_available() {
+        eprintln!("python3 not available, skipping the replay");
+        return;
+    }
+    let dir = test_dir("checkpoints-replay", MODIFICATIONS);
+    run_ok(main_command(&dir).args(["-l", "logs.txt", "-t", "table.csv", "-o", "replay.py"]));
+    for (path, source) in STUBS {
+        let path = dir.join("stubs").join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    assert_compiles(&dir, "replay.py");
+    let checkpoint = dir.join("replay.checkpoint.json");
+
+    // OPERATION_2 fails: the script stops and records OPERATION_1 only
+    let failed = replay(&dir, &[], Some("Country"));
+    assert!(!failed.status.success());
+    assert!(String::from_utf8_lossy(&failed.stderr).contains("OPERATION_2 failed: token expired"));
+    assert_eq!(
+        steps_run(&failed),
+        (vec!["City".into(), "Country".into()], false)
+    );
+
+    // --resume continues on the same table from the failed step
+    let resumed = replay(&dir, &["--resume"], None);
+    let stdout = String::from_utf8_lossy(&resumed.stdout);
+    assert!(resumed.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Resuming on table 77 of dataset 2"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Skipping OPERATION_1: already completed"));
+    assert!(!stdout.contains("UPLOAD"));
+    assert_eq!(
+        steps_run(&resumed),
+        (vec!["Country".into(), "Date".into()], true)
+    );
+    let completed: serde_json::Value =
+        serde_json::from_slice(&fs::read(&checkpoint).unwrap()).unwrap();
+    assert_eq!(
+        completed["tables"]["2/77"]["completed"],
+        serde_json::json!([1, 2, 3, 4])
+    );
+
+    // A selection runs only its steps
+    fs::remove_file(&checkpoint).unwrap();
+    let only = replay(&dir, &["--only-steps", "1,3"], None);
+    assert!(only.status.success());
+    assert_eq!(
+        steps_run(&only),
+        (vec!["City".into(), "Date".into()], false)
+    );
+    let range = replay(&dir, &["--from-step", "2", "--to-step", "3"], None);
+    assert!(range.status.success());
+    assert_eq!(
+        steps_run(&range),
+        (vec!["Country".into(), "Date".into()], false)
+    );
+    let from = replay(&dir, &["--from-step", "3"], None);
+    assert!(from.status.success());
+    assert_eq!(steps_run(&from), (vec!["Date".into()], true));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
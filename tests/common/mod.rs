@@ -0,0 +1,56 @@
+//! Fixtures and helpers shared by the integration tests.
+
+// Each test crate uses only part of this module
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+/// A session with one operation of each replayed type and a deleted column.
+pub const LOG: &str = r#"[2025-06-01T10:00:00Z] -| OpType: GET_TABLE -| DatasetId: 2 -| TableId: 5 -| TableName: cities -| DatasetName: demo
+[2025-06-01T10:01:00Z] -| OpType: RECONCILIATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| Reconciler: wikidataOpenRefine -| AdditionalData: {"additionalColumns":{"Country":"x"}}
+[2025-06-01T10:02:00Z] -| OpType: EXTENSION -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| Extender: wikidataPropertySPARQL -| AdditionalData: {"properties":"P17 P1082"}
+[2025-06-01T10:03:00Z] -| OpType: PROPAGATE_TYPE -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| AdditionalData: {"id":"wd:Q515","label":"city"}
+[2025-06-01T10:04:00Z] -| OpType: MODIFICATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: Date -| Modifier: dateFormatter -| AdditionalData: {"formatType":"iso"}
+[2025-06-01T10:05:00Z] -| OpType: SAVE_TABLE -| DatasetId: 2 -| TableId: 5 -| DeletedCols: Notes
+"#;
+
+/// The CSV table of [`LOG`].
+pub const TABLE: &str = "City,Country,Date,Notes\nRome,Italy,2025-01-01,a\n";
+
+/// Fixes the generation time, and so the names of the generated files.
+pub const TIMESTAMP: [&str; 2] = ["--timestamp", "2025-06-01T12:00:00Z"];
+pub const SCRIPT: &str = "base_file_2025-06-01_12-00.py";
+pub const NOTEBOOK: &str = "base_notebook_file_2025-06-01_12-00.ipynb";
+
+/// An empty directory of its own for test `name`, with `log` as `logs.txt` and [`TABLE`]
+/// as `table.csv`.
+pub fn test_dir(name: &str, log: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("semtparser-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("logs.txt"), log).unwrap();
+    fs::write(dir.join("table.csv"), TABLE).unwrap();
+    dir
+}
+
+/// The `main` binary, run in `dir` without the `SOURCE_DATE_EPOCH` of the environment.
+pub fn main_command(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_main"));
+    command.current_dir(dir).env_remove("SOURCE_DATE_EPOCH");
+    command
+}
+
+/// Runs `command`, failing the test with its stderr unless it succeeds.
+pub fn run_ok(command: &mut Command) -> Output {
+    let output = command.output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
@@ -0,0 +1,71 @@
+//! Scripts and notebooks read back into the operations they were generated from.
+
+mod common;
+
+use common::{main_command, run_ok, test_dir, NOTEBOOK, SCRIPT, TIMESTAMP};
+use std::{fs, path::Path};
+
+/// Runs the binary in `dir` and returns the bytes of the file named `output`, removing it.
+fn run(dir: &Path, args: &[&str], output: &str) -> Vec<u8> {
+    run_ok(
+        main_command(dir)
+            .args(args)
+            .args(["-t", "table.csv"])
+            .args(TIMESTAMP),
+    );
+    let path = dir.join(output);
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    bytes
+}
+
+#[test]
+fn converted_outputs_match_direct_generation() {
+    // AdditionalData spaced as semT may log it: it must read back exactly
+    let log = common::LOG.replace(
+        r#"{"properties":"P17 P1082"}"#,
+        r#"{"properties" : "P17 P1082"}"#,
+    );
+    let dir = test_dir("convert", &log);
+    let script_name = SCRIPT;
+    let notebook_name = NOTEBOOK;
+
+    let script = run(&dir, &["-l", "logs.txt", "-f", "python"], script_name);
+    fs::write(dir.join("pipeline.py"), &script).unwrap();
+    let notebook = run(&dir, &["-l", "logs.txt", "-f", "notebook"], notebook_name);
+    fs::write(dir.join("pipeline.ipynb"), &notebook).unwrap();
+
+    // A notebook carries everything the script is generated from
+    let from_notebook = run(
+        &dir,
+        &["convert", "pipeline.ipynb", "--to", "python"],
+        script_name,
+    );
+    assert!(from_notebook == script, "notebook -> python differs");
+
+    // And back: the script's metadata regenerates the same script through a notebook
+    let converted = run(
+        &dir,
+        &["convert", "pipeline.py", "--to", "notebook"],
+        notebook_name,
+    );
+    assert!(converted == notebook, "python -> notebook differs");
+    fs::write(dir.join("converted.ipynb"), &converted).unwrap();
+    let round_trip = run(
+        &dir,
+        &["convert", "converted.ipynb", "--to", "python"],
+        script_name,
+    );
+    assert!(round_trip == script, "python -> notebook -> python differs");
+
+    // A file without metadata is refused
+    fs::write(dir.join("plain.py"), "print('hello')\n").unwrap();
+    let result = main_command(&dir)
+        .args(["convert", "plain.py", "--to", "notebook"])
+        .output()
+        .unwrap();
+    assert_eq!(result.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&result.stderr).contains("no semtparser metadata found"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
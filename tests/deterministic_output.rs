@@ -0,0 +1,80 @@
+//! Generating twice from the same log must give byte-identical files.
+
+mod common;
+
+use common::{main_command, run_ok, test_dir};
+use std::{fs, path::PathBuf};
+
+/// Runs the generator in a fresh directory and returns the single file it wrote.
+fn generate(run: &str, format: &str, args: &[&str], envs: &[(&str, &str)]) -> (PathBuf, Vec<u8>) {
+    let dir = test_dir(&format!("deterministic-{}-{}", format, run), common::LOG);
+    let mut command = main_command(&dir);
+    command
+        .args([
+            "-l",
+            "logs.txt",
+            "-t",
+            "table.csv",
+            "-f",
+            format,
+            "--document-schema",
+        ])
+        .args(args);
+    for (key, value) in envs {
+        command.env(key, value);
+    }
+    run_ok(&mut command);
+
+    let outputs: Vec<PathBuf> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("base_")
+        })
+        .collect();
+    assert_eq!(outputs.len(), 1, "{:?}", outputs);
+    let bytes = fs::read(&outputs[0]).unwrap();
+    let name = PathBuf::from(outputs[0].file_name().unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+    (name, bytes)
+}
+
+#[test]
+fn regenerating_gives_identical_bytes() {
+    for (format, expected_name) in [("python", common::SCRIPT), ("notebook", common::NOTEBOOK)] {
+        let (first_name, first) = generate("first", format, &common::TIMESTAMP, &[]);
+        let (second_name, second) = generate("second", format, &common::TIMESTAMP, &[]);
+        assert_eq!(first_name, PathBuf::from(expected_name));
+        assert_eq!(second_name, first_name);
+        assert!(first == second, "{} output differs between runs", format);
+
+        // 2025-06-01T12:00:00Z
+        let epoch = [("SOURCE_DATE_EPOCH", "1748779200")];
+        let (epoch_name, from_epoch) = generate("epoch", format, &[], &epoch);
+        assert_eq!(epoch_name, first_name);
+        assert!(
+            from_epoch == first,
+            "{} output differs with SOURCE_DATE_EPOCH",
+            format
+        );
+    }
+}
+
+#[test]
+fn notebook_cell_ids_are_unique() {
+    let (_, bytes) = generate("ids", "notebook", &["--timestamp", "2025-06-01"], &[]);
+    let notebook: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let ids: Vec<&str> = notebook["cells"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|cell| cell["id"].as_str().unwrap())
+        .collect();
+    let mut unique = ids.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), ids.len());
+}
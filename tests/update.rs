@@ -0,0 +1,177 @@
+//! Updating a generated notebook from a longer log keeps what the analyst did to it.
+
+mod common;
+
+use common::{main_command, test_dir, NOTEBOOK, SCRIPT};
+use serde_json::{json, Value};
+use std::{fs, path::Path};
+
+const SESSION: &str = r#"[2025-06-01T10:00:00Z] -| OpType: GET_TABLE -| DatasetId: 2 -| TableId: 5 -| TableName: cities -| DatasetName: demo
+[2025-06-01T10:01:00Z] -| OpType: RECONCILIATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| Reconciler: wikidataOpenRefine -| AdditionalData: {"additionalColumns":{"Country":"x"}}
+[2025-06-01T10:02:00Z] -| OpType: MODIFICATION -| DatasetId: 2 -| TableId: 5 -| ColumnName: Date -| Modifier: dateFormatter -| AdditionalData: {"formatType":"iso"}
+"#;
+const EXTENSION: &str = r#"[2025-06-01T10:03:00Z] -| OpType: EXTENSION -| DatasetId: 2 -| TableId: 5 -| ColumnName: City -| Extender: wikidataPropertySPARQL -| AdditionalData: {"properties":"P17"}
+"#;
+const SAVE: &str = "[2025-06-01T10:05:00Z] -| OpType: SAVE_TABLE -| DatasetId: 2 -| TableId: 5 -| DeletedCols: Notes\n";
+
+fn run(dir: &Path, args: &[&str]) -> std::process::Output {
+    main_command(dir)
+        .args(args)
+        .args(["-l", "logs.txt", "-t", "table.csv"])
+        .output()
+        .unwrap()
+}
+
+fn operation_type(cell: &Value) -> &str {
+    cell["metadata"]["semtparser"]["operation_type"]
+        .as_str()
+        .unwrap_or("")
+}
+
+fn read_cells(dir: &Path) -> Vec<Value> {
+    let notebook: Value = serde_json::from_slice(&fs::read(dir.join(NOTEBOOK)).unwrap()).unwrap();
+    notebook["cells"].as_array().unwrap().clone()
+}
+
+fn write_cells(dir: &Path, cells: Vec<Value>) {
+    let mut notebook: Value =
+        serde_json::from_slice(&fs::read(dir.join(NOTEBOOK)).unwrap()).unwrap();
+    notebook["cells"] = Value::Array(cells);
+    fs::write(dir.join(NOTEBOOK), notebook.to_string()).unwrap();
+}
+
+#[test]
+fn new_operations_are_merged_around_user_edits() {
+    let dir = test_dir("update", &format!("{}{}", SESSION, SAVE));
+    let generated = run(
+        &dir,
+        &["-f", "notebook", "--timestamp", "2025-06-01T12:00:00Z"],
+    );
+    assert!(generated.status.success());
+
+    // The analyst runs the modification, and plots the reconciled column
+    let mut cells = Vec::new();
+    for mut cell in read_cells(&dir) {
+        let is_code = cell["cell_type"] == "code";
+        if is_code && operation_type(&cell) == "MODIFICATION" {
+            cell["outputs"] =
+                json!([{"output_type": "stream", "name": "stdout", "text": ["done\n"]}]);
+        }
+        let reconciliation = is_code && operation_type(&cell) == "RECONCILIATION";
+        cells.push(cell);
+        if reconciliation {
+            cells.push(json!({
+                "cell_type": "code",
+                "id": "plot",
+                "metadata": {},
+                "source": ["plot(table)\n"],
+                "execution_count": null,
+                "outputs": []
+            }));
+        }
+    }
+    write_cells(&dir, cells);
+
+    // ...and extends the table in the backend
+    fs::write(
+        dir.join("logs.txt"),
+        format!("{}{}{}", SESSION, EXTENSION, SAVE),
+    )
+    .unwrap();
+    let updated = run(&dir, &["update", NOTEBOOK]);
+    let stdout = String::from_utf8_lossy(&updated.stdout);
+    assert!(updated.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("  added EXTENSION on column 'City'"),
+        "{}",
+        stdout
+    );
+
+    let cells = read_cells(&dir);
+    let order: Vec<String> = cells
+        .iter()
+        .filter(|cell| cell["cell_type"] == "code")
+        .map(|cell| match cell["id"].as_str() {
+            Some("plot") => "plot".to_string(),
+            _ => operation_type(cell).to_string(),
+        })
+        .collect();
+    assert_eq!(
+        order,
+        [
+            "",
+            "SETUP",
+            "RECONCILIATION",
+            "plot",
+            "MODIFICATION",
+            "EXTENSION",
+            "EXPORT"
+        ]
+    );
+    let modification = cells
+        .iter()
+        .find(|cell| cell["cell_type"] == "code" && operation_type(cell) == "MODIFICATION")
+        .unwrap();
+    assert_eq!(modification["outputs"][0]["text"][0], "done\n");
+
+    // Editing a cell whose operation then changes in the log is a conflict
+    let cells = cells
+        .into_iter()
+        .map(|mut cell| {
+            if cell["cell_type"] == "code" && operation_type(&cell) == "MODIFICATION" {
+                cell["source"] = json!(["# my own formatting\n"]);
+            }
+            cell
+        })
+        .collect();
+    write_cells(&dir, cells);
+    fs::write(
+        dir.join("logs.txt"),
+        format!("{}{}{}", SESSION, EXTENSION, SAVE).replace("\"iso\"", "\"european\""),
+    )
+    .unwrap();
+    let conflicting = run(&dir, &["update", NOTEBOOK]);
+    assert_eq!(conflicting.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&conflicting.stderr).contains(
+        "Conflict: MODIFICATION on column 'Date': edited in the file and changed in the log"
+    ));
+    assert!(fs::read_to_string(dir.join(NOTEBOOK))
+        .unwrap()
+        .contains("# my own formatting"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn remapped_files_are_up_to_date_with_their_log() {
+    let dir = test_dir("update-remap", &format!("{}{}", SESSION, SAVE));
+    let generated = run(
+        &dir,
+        &[
+            "--map-dataset",
+            "2=7",
+            "--timestamp",
+            "2025-06-01T12:00:00Z",
+        ],
+    );
+    assert!(generated.status.success());
+    let script = fs::read_to_string(dir.join(SCRIPT)).unwrap();
+
+    // The ids are remapped again when the log is read, so nothing changed
+    let updated = run(&dir, &["update", SCRIPT, "--map-dataset", "2=7"]);
+    let stdout = String::from_utf8_lossy(&updated.stdout);
+    assert!(
+        updated.status.success(),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&updated.stderr)
+    );
+    assert!(
+        stdout.contains(&format!("{} is up to date", SCRIPT)),
+        "{}",
+        stdout
+    );
+    assert_eq!(fs::read_to_string(dir.join(SCRIPT)).unwrap(), script);
+
+    fs::remove_dir_all(&dir).unwrap();
+}